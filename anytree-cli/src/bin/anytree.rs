@@ -1,6 +1,14 @@
+use std::path::PathBuf;
 use std::process::exit;
 
-use anytree_cli::commands::{Cli, Commands};
+use anytree_cli::commands::build::builder_dir;
+use anytree_cli::commands::{cache, sbom, CacheCommands, Cli, Commands, SbomCommands};
+use anytree_cli::config::Config;
+use anytree_plugin_cargo_dependencies::store::Store;
+use anytree_plugin_cargo_dependencies::{LoadOptions, MirrorOptions};
+use anytree_utils::credentials::Credentials;
+use anytree_utils::http::HttpConfig;
+use anytree_utils::rewrite::UrlRewrites;
 use clap::Parser;
 
 fn main() {
@@ -29,14 +37,70 @@ fn run() -> anyhow::Result<()> {
     };
 
     match cli.command {
-        Commands::Build { sbom, dir } => {
+        Commands::Build {
+            sbom,
+            dir,
+            skip_unknown,
+            jobs,
+            store,
+            no_store,
+            missing_checksum,
+            ref_mismatch,
+            signatures,
+            keyring,
+        } => {
             if !sbom.exists() {
                 anyhow::bail!("{sbom:?} does not exist");
             }
 
             // TODO: cache
-            anytree_cli::commands::build::build(sbom, dir)?;
+            let store = (!no_store).then(|| store.unwrap_or_else(Store::default_root));
+            let config = Config::load(cli.config.as_deref())?;
+            let options = LoadOptions {
+                skip_unknown,
+                jobs,
+                http: HttpConfig::from_env()?,
+                credentials: Credentials::new(config.credentials),
+                rewrites: UrlRewrites::new(config.rewrites),
+                store,
+                missing_checksum,
+                ref_mismatch,
+                signatures,
+                keyrings: keyring,
+            };
+            anytree_cli::commands::build::build(sbom, dir, &options)?;
         }
+        Commands::Mirror { sbom, dest, output, journal } => {
+            let config = Config::load(cli.config.as_deref())?;
+            let journal =
+                journal.unwrap_or_else(|| PathBuf::from(format!("{}.journal", output.display())));
+            let options = MirrorOptions {
+                dest,
+                journal,
+                http: HttpConfig::from_env()?,
+                credentials: Credentials::new(config.credentials),
+                rewrites: UrlRewrites::new(config.rewrites),
+            };
+            anytree_cli::commands::mirror::mirror(&sbom, &output, &options)?;
+        }
+        Commands::Cache { store, command } => {
+            let store = Store::new(store.unwrap_or_else(Store::default_root));
+            let builder = builder_dir();
+            let out = &mut std::io::stdout();
+            match command {
+                CacheCommands::Ls => cache::ls(&builder, out)?,
+                CacheCommands::Du => cache::du(&builder, &store, out)?,
+                CacheCommands::Prune { older_than, max_size, dry_run } => {
+                    cache::prune(&builder, older_than, max_size, dry_run, out)?
+                }
+                CacheCommands::Gc { dry_run } => cache::gc(&builder, &store, dry_run, out)?,
+            }
+        }
+        Commands::Sbom { command } => match command {
+            SbomCommands::Convert { sbom, output, to, report } => {
+                sbom::convert(&sbom, &output, to, report.as_deref())?
+            }
+        },
     }
 
     Ok(())
@@ -1,11 +1,25 @@
-use std::fs::{create_dir_all, File};
+use std::fs::create_dir_all;
 use std::path::{Path, PathBuf};
 
+use anytree_plugin_cargo_dependencies::LoadOptions;
 use uuid::Uuid;
 
-pub fn build(sbom_path: impl AsRef<Path>, cache: Option<impl AsRef<str>>) -> anyhow::Result<()> {
-    let sbom: anytree_sbom::CycloneDXBom =
-        serde_json::from_reader(File::open(sbom_path.as_ref())?)?;
+use crate::commands::cache::{lock_run, RunInfo};
+
+/// Dir with the run dirs of all the builds
+pub fn builder_dir() -> PathBuf {
+    dirs::cache_dir().unwrap_or_else(|| PathBuf::from(".cache")).join("anytree").join("builder")
+}
+
+pub fn build(
+    sbom_path: impl AsRef<Path>,
+    cache: Option<impl AsRef<str>>,
+    options: &LoadOptions,
+) -> anyhow::Result<()> {
+    let data = std::fs::read(sbom_path.as_ref())?;
+    // JSON, XML or protobuf
+    let sbom = anytree_sbom::CycloneDXBom::parse(&data)
+        .map_err(|e| anyhow::format_err!("Failed to parse {:?}: {e}", sbom_path.as_ref()))?;
 
     let container_name = if let Some(run_dir) = cache {
         // TODO: remove container with such name if it is not running otherwise
@@ -26,18 +40,18 @@ pub fn build(sbom_path: impl AsRef<Path>, cache: Option<impl AsRef<str>>) -> any
         container_name
     );
 
-    let builder_dir = dirs::cache_dir()
-        .unwrap_or_else(|| PathBuf::from(".cache"))
-        .join("anytree")
-        .join("builder");
+    let builder_dir = builder_dir();
     tracing::trace!(?builder_dir, "Using builder directory");
 
     create_dir_all(&builder_dir)?;
     let builder_dir = builder_dir.canonicalize()?;
     tracing::trace!(?builder_dir, "Canonicalized builder directory");
 
+    // the run dir is kept from `anytree cache` until the build is done
+    let _run_lock = lock_run(&builder_dir, &container_name)?;
     let container_dir = builder_dir.join(&container_name);
     create_dir_all(&container_dir)?;
+    RunInfo::touch(&container_dir, sbom_path.as_ref(), &sbom)?;
 
     if let Some(sbom_platform) = &sbom
         .metadata
@@ -62,7 +76,13 @@ pub fn build(sbom_path: impl AsRef<Path>, cache: Option<impl AsRef<str>>) -> any
             if let Some(prop) = properties.iter().find(|prop| prop.name == "target") {
                 if prop.value == anytree_plugin_cargo::PROJECT_TYPE {
                     tracing::trace!("Found cargo target");
-                    anytree_plugin_cargo::build(component, &sbom, &container_name, container_dir)?;
+                    anytree_plugin_cargo::build(
+                        component,
+                        &sbom,
+                        &container_name,
+                        container_dir,
+                        options,
+                    )?;
                     return Ok(());
                 }
                 if prop.value == anytree_plugin_bash::PROJECT_TYPE {
@@ -0,0 +1,496 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anytree_plugin_cargo_dependencies::store::{disk_usage, write_atomic, Store};
+use chrono::{DateTime, Utc};
+use fs4::FileExt;
+use serde::{Deserialize, Serialize};
+
+/// Run dir subfolders, see `anytree_plugin_cargo`
+const RUN_PARTS: [&str; 3] = ["src", "cargo", "target"];
+const RUN_INFO_NAME: &str = "run.json";
+/// Lock files of the run dirs, in the builder dir
+const LOCKS_SUBFOLDER: &str = ".locks";
+
+/// Stored in every run dir to tell which SBOM it was built from and when
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RunInfo {
+    pub sbom: PathBuf,
+    pub serial_number: Option<String>,
+    pub created: DateTime<Utc>,
+    pub last_used: DateTime<Utc>,
+}
+
+impl RunInfo {
+    /// Records the build of `sbom` in the run dir
+    pub fn touch(
+        run_dir: &Path,
+        sbom_path: &Path,
+        sbom: &anytree_sbom::CycloneDXBom,
+    ) -> anyhow::Result<()> {
+        let now = Utc::now();
+        let created = read_info(run_dir).map_or(now, |info| info.created);
+        let info = Self {
+            sbom: sbom_path.canonicalize()?,
+            serial_number: sbom.serial_number.clone(),
+            created,
+            last_used: now,
+        };
+        write_atomic(run_dir.join(RUN_INFO_NAME), serde_json::to_vec_pretty(&info)?)
+    }
+}
+
+/// Locks the run dir `name` of the builder dir for a build, [`prune`] and
+/// [`gc`] leave the run alone until the lock is dropped
+pub fn lock_run(builder: &Path, name: &str) -> anyhow::Result<File> {
+    let lock = create_lock(builder, name)?;
+    FileExt::lock_shared(&lock)
+        .map_err(|e| anyhow::format_err!("Failed to lock run {name}: {e}"))?;
+    Ok(lock)
+}
+
+fn create_lock(builder: &Path, name: &str) -> anyhow::Result<File> {
+    let path = builder.join(LOCKS_SUBFOLDER).join(name);
+    std::fs::create_dir_all(builder.join(LOCKS_SUBFOLDER))?;
+    File::create(&path).map_err(|e| anyhow::format_err!("Failed to create lock {:?}: {e}", path))
+}
+
+fn read_info(run_dir: &Path) -> Option<RunInfo> {
+    let file = File::open(run_dir.join(RUN_INFO_NAME)).ok()?;
+    serde_json::from_reader(file).ok()
+}
+
+/// Run dir of the builder cache
+struct Run {
+    name: String,
+    path: PathBuf,
+    info: Option<RunInfo>,
+    /// `last_used` of the info or modification time of the dir for the runs
+    /// made before the info was recorded
+    last_used: DateTime<Utc>,
+}
+
+impl Run {
+    fn size(&self) -> anyhow::Result<u64> {
+        disk_usage(&self.path)
+    }
+
+    /// SBOM the run was built from is gone
+    fn is_unreferenced(&self) -> bool {
+        self.info.as_ref().map_or(false, |info| !info.sbom.exists())
+    }
+}
+
+/// Runs of the builder dir sorted from the least recently used
+fn runs(builder: &Path) -> anyhow::Result<Vec<Run>> {
+    if !builder.exists() {
+        return Ok(vec![]);
+    }
+    let mut runs = vec![];
+    for entry in std::fs::read_dir(builder)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() || entry.file_name() == LOCKS_SUBFOLDER {
+            continue;
+        }
+        let info = read_info(&entry.path());
+        let last_used = match &info {
+            Some(info) => info.last_used,
+            None => entry.metadata()?.modified()?.into(),
+        };
+        runs.push(Run {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            path: entry.path(),
+            info,
+            last_used,
+        });
+    }
+    runs.sort_by(|a, b| (a.last_used, &a.name).cmp(&(b.last_used, &b.name)));
+    Ok(runs)
+}
+
+/// Lists the run dirs with the SBOM they were built from
+pub fn ls(builder: &Path, out: &mut dyn Write) -> anyhow::Result<()> {
+    writeln!(out, "{:<52} {:<17} SBOM", "RUN", "LAST USED")?;
+    for run in runs(builder)? {
+        let sbom = match &run.info {
+            Some(RunInfo { sbom, serial_number: Some(serial_number), .. }) => {
+                format!("{} ({serial_number})", sbom.display())
+            }
+            Some(RunInfo { sbom, .. }) => sbom.display().to_string(),
+            None => "-".to_string(),
+        };
+        let missing = if run.is_unreferenced() { " [missing]" } else { "" };
+        writeln!(out, "{:<52} {:<17} {sbom}{missing}", run.name, format_date(run.last_used))?;
+    }
+    Ok(())
+}
+
+/// Prints the disk usage of every run dir part and of the store. Files linked
+/// from the store are counted in every dir they appear in.
+pub fn du(builder: &Path, store: &Store, out: &mut dyn Write) -> anyhow::Result<()> {
+    writeln!(out, "{:<52} {:>10} {:>10} {:>10} {:>10}", "RUN", "SRC", "CARGO", "TARGET", "TOTAL")?;
+    let mut total = 0;
+    for run in runs(builder)? {
+        let mut run_total = 0;
+        let mut parts = vec![];
+        for part in RUN_PARTS {
+            let size = disk_usage(&run.path.join(part))?;
+            run_total += size;
+            parts.push(format_size(size));
+        }
+        total += run_total;
+        writeln!(
+            out,
+            "{:<52} {:>10} {:>10} {:>10} {:>10}",
+            run.name,
+            parts[0],
+            parts[1],
+            parts[2],
+            format_size(run_total)
+        )?;
+    }
+    let store_size = disk_usage(store.root())?;
+    writeln!(
+        out,
+        "{:<52} {:>43}",
+        format!("store ({})", store.root().display()),
+        format_size(store_size)
+    )?;
+    writeln!(out, "{:<52} {:>43}", "total", format_size(total + store_size))?;
+    Ok(())
+}
+
+/// Removes run dirs not used for `older_than`, then the least recently used
+/// ones until the rest fit into `max_size`. Runs of the running builds are
+/// kept.
+pub fn prune(
+    builder: &Path,
+    older_than: Option<Duration>,
+    max_size: Option<u64>,
+    dry_run: bool,
+    out: &mut dyn Write,
+) -> anyhow::Result<()> {
+    let mut runs = runs(builder)?;
+    if let Some(older_than) = older_than {
+        let deadline = chrono::Duration::from_std(older_than)
+            .ok()
+            .and_then(|age| Utc::now().checked_sub_signed(age))
+            .ok_or(anyhow::format_err!("Age {older_than:?} is too big"))?;
+        let (old, rest): (Vec<_>, Vec<_>) =
+            runs.into_iter().partition(|run| run.last_used < deadline);
+        runs = rest;
+        for run in old {
+            if !remove_run(builder, &run, dry_run, out)? {
+                runs.push(run);
+            }
+        }
+        runs.sort_by(|a, b| (a.last_used, &a.name).cmp(&(b.last_used, &b.name)));
+    }
+    if let Some(max_size) = max_size {
+        let mut sizes = runs.iter().map(Run::size).collect::<anyhow::Result<Vec<_>>>()?;
+        let mut total: u64 = sizes.iter().sum();
+        for (run, size) in runs.iter().zip(sizes.drain(..)) {
+            if total <= max_size {
+                break;
+            }
+            if remove_run(builder, run, dry_run, out)? {
+                total -= size;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Removes run dirs whose SBOM no longer exists and store entries no
+/// remaining run dir uses
+pub fn gc(builder: &Path, store: &Store, dry_run: bool, out: &mut dyn Write) -> anyhow::Result<()> {
+    let _lock = store.lock_exclusive()?;
+    let mut removed = vec![];
+    for run in runs(builder)?.into_iter().filter(Run::is_unreferenced) {
+        let path = run.path.canonicalize()?;
+        if remove_run(builder, &run, dry_run, out)? {
+            removed.push(path);
+        }
+    }
+    // a dry run keeps the runs, their entries must look unused all the same
+    for entry in store.unused_entries(|dir| removed.iter().any(|run| dir.starts_with(run)))? {
+        writeln!(out, "{} store entry {}/{}", removing(dry_run), entry.kind, entry.key)?;
+        if !dry_run {
+            store.remove(&entry)?;
+        }
+    }
+    if !dry_run {
+        store.remove_stale_manifests()?;
+    }
+    Ok(())
+}
+
+/// Removes the run dir unless a build holds its lock, returns whether it was
+/// removed
+fn remove_run(
+    builder: &Path,
+    run: &Run,
+    dry_run: bool,
+    out: &mut dyn Write,
+) -> anyhow::Result<bool> {
+    // a dry run must not create the lock file, builds always do
+    let lock = match dry_run {
+        true => File::open(builder.join(LOCKS_SUBFOLDER).join(&run.name)).ok(),
+        false => Some(create_lock(builder, &run.name)?),
+    };
+    // the lock is held until the dir is gone
+    if lock.as_ref().map_or(false, |lock| lock.try_lock_exclusive().is_err()) {
+        writeln!(out, "Keeping run {}, a build is using it", run.name)?;
+        return Ok(false);
+    }
+    let last_used = format_date(run.last_used);
+    writeln!(out, "{} run {} (last used {last_used})", removing(dry_run), run.name)?;
+    if !dry_run {
+        std::fs::remove_dir_all(&run.path)
+            .map_err(|e| anyhow::format_err!("Failed to remove {:?}: {e}", run.path))?;
+    }
+    Ok(true)
+}
+
+fn removing(dry_run: bool) -> &'static str {
+    if dry_run {
+        "Would remove"
+    } else {
+        "Removing"
+    }
+}
+
+fn format_date(date: DateTime<Utc>) -> String {
+    date.format("%Y-%m-%d %H:%M").to_string()
+}
+
+fn format_size(size: u64) -> String {
+    const UNITS: [&str; 4] = ["K", "M", "G", "T"];
+    if size < 1024 {
+        return format!("{size}B");
+    }
+    let mut value = size as f64;
+    let mut unit = "";
+    for next in UNITS {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = next;
+    }
+    format!("{value:.1}{unit}")
+}
+
+/// Size like `500M`, `10G` or plain bytes, units are powers of 1024
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let (number, multiplier) = match value.char_indices().last() {
+        Some((i, unit)) if unit.is_ascii_alphabetic() => {
+            let power = match unit.to_ascii_uppercase() {
+                'B' => 0,
+                'K' => 1,
+                'M' => 2,
+                'G' => 3,
+                'T' => 4,
+                _ => return Err(format!("unknown size unit: {unit}")),
+            };
+            (&value[..i], 1024u64.pow(power))
+        }
+        _ => (value, 1),
+    };
+    let number: u64 = number.trim().parse().map_err(|e| format!("wrong size {value}: {e}"))?;
+    number.checked_mul(multiplier).ok_or(format!("size is too big: {value}"))
+}
+
+/// Duration like `30d`, `12h` or `2w`
+pub fn parse_age(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let Some((i, unit)) = value.char_indices().last() else {
+        return Err("empty age".to_string());
+    };
+    let seconds = match unit {
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        _ => return Err(format!("age must end with one of m, h, d, w: {value}")),
+    };
+    let number: u64 = value[..i].parse().map_err(|e| format!("wrong age {value}: {e}"))?;
+    let seconds = number.checked_mul(seconds).ok_or(format!("age is too big: {value}"))?;
+    Ok(Duration::from_secs(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    /// Run dir built from `sbom` `days` ago with `size` bytes of sources
+    fn run(builder: &Path, name: &str, sbom: &Path, days: i64, size: usize) -> PathBuf {
+        let dir = builder.join(name);
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src").join("lib.rs"), vec![b' '; size]).unwrap();
+        let last_used = Utc::now() - chrono::Duration::days(days);
+        let info = RunInfo {
+            sbom: sbom.to_path_buf(),
+            serial_number: None,
+            created: last_used,
+            last_used,
+        };
+        std::fs::write(dir.join(RUN_INFO_NAME), serde_json::to_vec(&info).unwrap()).unwrap();
+        dir
+    }
+
+    /// Sizes of all the files under `dir`
+    fn snapshot(dir: &Path) -> BTreeMap<PathBuf, u64> {
+        let mut files = BTreeMap::new();
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            match path.is_dir() {
+                true => files.extend(snapshot(&path)),
+                false => {
+                    files.insert(path.clone(), std::fs::metadata(&path).unwrap().len());
+                }
+            }
+        }
+        files
+    }
+
+    fn output(command: impl FnOnce(&mut dyn Write) -> anyhow::Result<()>) -> String {
+        let mut out = vec![];
+        command(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn names(builder: &Path) -> Vec<String> {
+        runs(builder).unwrap().into_iter().map(|run| run.name).collect()
+    }
+
+    #[test]
+    fn test_ls_and_du() {
+        let dir = tempfile::tempdir().unwrap();
+        let builder = dir.path().join("builder");
+        let sbom = dir.path().join("sbom.json");
+        std::fs::write(&sbom, b"{}").unwrap();
+        run(&builder, "new", &sbom, 1, 2048);
+        run(&builder, "old", &dir.path().join("gone.json"), 3, 1024);
+        let _lock = lock_run(&builder, "new").unwrap();
+
+        let ls = output(|out| ls(&builder, out));
+        let lines = ls.lines().collect::<Vec<_>>();
+        assert_eq!(3, lines.len(), "{ls}");
+        assert!(lines[1].starts_with("old ") && lines[1].ends_with("gone.json [missing]"));
+        assert!(lines[2].starts_with("new ") && lines[2].ends_with("sbom.json"));
+
+        let store = Store::new(dir.path().join("store"));
+        std::fs::create_dir_all(store.root().join("crate")).unwrap();
+        std::fs::write(store.root().join("crate").join("sha-256-00"), vec![0; 3072]).unwrap();
+        let du = output(|out| du(&builder, &store, out));
+        let lines = du.lines().map(|l| l.split_whitespace().collect()).collect::<Vec<Vec<_>>>();
+        assert_eq!(vec!["old", "1.0K", "0B", "0B", "1.0K"], lines[1]);
+        assert_eq!(vec!["new", "2.0K", "0B", "0B", "2.0K"], lines[2]);
+        assert_eq!("3.0K", *lines[3].last().unwrap());
+        assert_eq!(vec!["total", "6.0K"], lines[4]);
+    }
+
+    #[test]
+    fn test_prune() {
+        let dir = tempfile::tempdir().unwrap();
+        let builder = dir.path().join("builder");
+        let sbom = dir.path().join("sbom.json");
+        run(&builder, "a", &sbom, 10, 1024);
+        run(&builder, "b", &sbom, 5, 2048);
+        run(&builder, "c", &sbom, 1, 4096);
+        let day = Duration::from_secs(24 * 60 * 60);
+
+        let before = snapshot(dir.path());
+        let pruned = output(|out| prune(&builder, Some(7 * day), Some(0), true, out));
+        assert!(pruned.contains("Would remove run a"), "{pruned}");
+        assert!(pruned.contains("Would remove run c"), "{pruned}");
+        assert_eq!(before, snapshot(dir.path()));
+
+        prune(&builder, Some(7 * day), None, false, &mut vec![]).unwrap();
+        assert_eq!(vec!["b", "c"], names(&builder));
+        // b and c with their run.json are over 6K
+        prune(&builder, None, Some(6 * 1024), false, &mut vec![]).unwrap();
+        assert_eq!(vec!["c"], names(&builder));
+
+        let lock = lock_run(&builder, "c").unwrap();
+        let pruned = output(|out| prune(&builder, None, Some(0), false, out));
+        assert!(pruned.contains("Keeping run c, a build is using it"), "{pruned}");
+        assert_eq!(vec!["c"], names(&builder));
+        drop(lock);
+        prune(&builder, Some(Duration::ZERO), None, false, &mut vec![]).unwrap();
+        assert!(names(&builder).is_empty());
+    }
+
+    #[test]
+    fn test_gc() {
+        let dir = tempfile::tempdir().unwrap();
+        let builder = dir.path().join("builder");
+        let sbom = dir.path().join("sbom.json");
+        std::fs::write(&sbom, b"{}").unwrap();
+        let store = Store::new(dir.path().join("store"));
+        let fetch = |path: &Path| Ok(std::fs::write(path, b"crate")?);
+        let build = |name: &str, sbom: &Path, key: &str| {
+            let deps = run(&builder, name, sbom, 1, 0).join("cargo");
+            std::fs::create_dir_all(&deps).unwrap();
+            // every build is a separate process
+            let store = Store::new(store.root());
+            let _lock = store.lock_shared().unwrap();
+            store.entry("crate", key, |_| Ok(()), fetch).unwrap();
+            store.record_run(&deps).unwrap();
+            deps
+        };
+        build("kept", &sbom, "sha-256-00");
+        build("unreferenced", &dir.path().join("gone.json"), "sha-256-01");
+        let removed = build("removed", &sbom, "sha-256-02");
+        std::fs::remove_dir_all(removed.parent().unwrap()).unwrap();
+
+        let before = snapshot(dir.path());
+        let collected = output(|out| gc(&builder, &store, true, out));
+        assert!(collected.starts_with("Would remove run unreferenced "), "{collected}");
+        assert!(collected.contains("Would remove store entry crate/sha-256-01"), "{collected}");
+        assert!(collected.contains("Would remove store entry crate/sha-256-02"), "{collected}");
+        assert!(!collected.contains("sha-256-00"), "{collected}");
+        assert_eq!(before, snapshot(dir.path()));
+
+        gc(&builder, &store, false, &mut vec![]).unwrap();
+        assert_eq!(vec!["kept"], names(&builder));
+        let entries = store.entries().unwrap();
+        assert_eq!(vec!["sha-256-00"], entries.iter().map(|e| &e.key).collect::<Vec<_>>());
+        assert_eq!(1, std::fs::read_dir(store.root().join(".runs")).unwrap().count());
+    }
+
+    #[test]
+    fn test_parse_limits() {
+        assert_eq!(Ok(512), parse_size("512"));
+        assert_eq!(Ok(500 * 1024 * 1024), parse_size("500M"));
+        assert_eq!(Ok(10 * 1024 * 1024 * 1024), parse_size("10g"));
+        assert!(parse_size("10X").is_err());
+
+        assert_eq!(Ok(Duration::from_secs(30 * 24 * 60 * 60)), parse_age("30d"));
+        assert_eq!(Ok(Duration::from_secs(12 * 60 * 60)), parse_age("12h"));
+        assert!(parse_age("30").is_err());
+        assert!(parse_age(&format!("{}w", u64::MAX / 2)).is_err());
+
+        assert_eq!("1023B", format_size(1023));
+        assert_eq!("1.5K", format_size(1536));
+        assert_eq!("2.0G", format_size(2 * 1024 * 1024 * 1024));
+    }
+
+    #[test]
+    fn test_prune_needs_a_limit() {
+        use clap::Parser;
+
+        use crate::commands::Cli;
+
+        assert!(Cli::try_parse_from(["anytree", "cache", "prune"]).is_err());
+        assert!(Cli::try_parse_from(["anytree", "cache", "prune", "--max-size", "1G"]).is_ok());
+        let both = ["anytree", "cache", "prune", "--older-than", "1d", "--max-size", "1G"];
+        assert!(Cli::try_parse_from(both).is_ok());
+    }
+}
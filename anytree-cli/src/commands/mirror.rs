@@ -0,0 +1,21 @@
+use std::path::Path;
+
+use anytree_plugin_cargo_dependencies::MirrorOptions;
+
+/// Mirrors the dependencies of the SBOM at `sbom_path`, see
+/// [`anytree_plugin_cargo_dependencies::mirror`], and writes the SBOM pointing
+/// at the mirrors to `output`
+pub fn mirror(sbom_path: &Path, output: &Path, options: &MirrorOptions) -> anyhow::Result<()> {
+    let data = std::fs::read(sbom_path)
+        .map_err(|e| anyhow::format_err!("Failed to open {:?}: {e}", sbom_path))?;
+    let sbom = anytree_sbom::CycloneDXBom::parse(&data)
+        .map_err(|e| anyhow::format_err!("Failed to parse {:?}: {e}", sbom_path))?;
+    let sbom = sbom.to_json()?;
+    let mirrored = anytree_plugin_cargo_dependencies::mirror(&sbom, options)?;
+    let mut data = serde_json::to_string_pretty(&mirrored)?;
+    data.push('\n');
+    std::fs::write(output, data)
+        .map_err(|e| anyhow::format_err!("Failed to write {:?}: {e}", output))?;
+    tracing::info!("SBOM pointing at the mirrors is written to {:?}", output);
+    Ok(())
+}
@@ -1,13 +1,22 @@
 pub mod build;
+pub mod cache;
+pub mod mirror;
+pub mod sbom;
 
 use std::path::PathBuf;
+use std::time::Duration;
 
-use clap::{Parser, Subcommand};
+use anytree_plugin_cargo_dependencies::Policy;
+use clap::{ArgGroup, Parser, Subcommand};
+use sbom::OutputFormat;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 #[command(propagate_version = true)]
 pub struct Cli {
+    /// Config file, defaults to ~/.config/anytree/config.toml
+    #[arg(long, env = "ANYTREE_CONFIG", global = true)]
+    pub config: Option<PathBuf>,
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -19,5 +28,119 @@ pub enum Commands {
         sbom: PathBuf,
         #[arg(name = "run_dir")]
         dir: Option<String>,
+        /// Skip SBOM library components of unsupported types instead of failing
+        #[arg(long)]
+        skip_unknown: bool,
+        /// Number of dependencies fetched in parallel, defaults to the number
+        /// of CPUs
+        #[arg(short, long, default_value_t = 0, hide_default_value = true)]
+        jobs: usize,
+        /// Dependency store shared by the builds, defaults to
+        /// ~/.cache/anytree/store
+        #[arg(long, env = "ANYTREE_STORE")]
+        store: Option<PathBuf>,
+        /// Fetch dependencies right into the run dir, without the shared store
+        #[arg(long, conflicts_with = "store")]
+        no_store: bool,
+        /// What to do with registry crates that neither SBOM nor the index has
+        /// a checksum for: require (fail), warn or ignore
+        #[arg(long, default_value = "require")]
+        missing_checksum: Policy,
+        /// What to do when a git dependency tag or branch from SBOM does not
+        /// point at the pinned commit: require (fail), warn or ignore (skip the
+        /// check)
+        #[arg(long, default_value = "require")]
+        ref_mismatch: Policy,
+        /// What to do when the commit (or the tag from SBOM) of the project or
+        /// a git dependency is not signed by a trusted key: require (fail),
+        /// warn or ignore. Components may set their own signature_policy.
+        /// Not checked by default.
+        #[arg(long)]
+        signatures: Option<Policy>,
+        /// OpenPGP keyring or SSH allowed signers file with the keys trusted
+        /// to sign commits and tags, in addition to the signing_key properties
+        /// of the components. May be repeated.
+        #[arg(long)]
+        keyring: Vec<PathBuf>,
+    },
+    /// Push the registry crates and the git dependencies of SBOM to git repos
+    /// and write SBOM that fetches them from there
+    Mirror {
+        #[arg(name = "sbom_path")]
+        sbom: PathBuf,
+        /// Base URL of the mirror repos, every component goes to
+        /// <DEST>/<name>-<version>. Local bare repos are created, remote ones
+        /// must exist.
+        #[arg(long)]
+        dest: String,
+        /// Where to write SBOM pointing at the mirrors
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Journal of the mirrored components to resume from, defaults to
+        /// <OUTPUT>.journal
+        #[arg(long)]
+        journal: Option<PathBuf>,
+    },
+    /// Inspect and clean the run dirs and the dependency store
+    Cache {
+        /// Dependency store shared by the builds, defaults to
+        /// ~/.cache/anytree/store
+        #[arg(long, env = "ANYTREE_STORE", global = true)]
+        store: Option<PathBuf>,
+        #[command(subcommand)]
+        command: CacheCommands,
+    },
+    /// Work with SBOM files
+    Sbom {
+        #[command(subcommand)]
+        command: SbomCommands,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CacheCommands {
+    /// List run dirs with the SBOM and the date of the last build
+    Ls,
+    /// Show disk usage of run dirs and the store
+    Du,
+    /// Remove run dirs by age or to fit into a size budget
+    #[command(group(ArgGroup::new("limit").required(true).multiple(true)))]
+    Prune {
+        /// Remove run dirs not used for this long, e.g. 30d, 12h, 2w
+        #[arg(long, group = "limit", value_parser = cache::parse_age)]
+        older_than: Option<Duration>,
+        /// Remove the least recently used run dirs until the rest fit, e.g.
+        /// 10G
+        #[arg(long, group = "limit", value_parser = cache::parse_size)]
+        max_size: Option<u64>,
+        /// Only print what would be removed
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Remove run dirs whose SBOM is gone and store entries no run dir uses
+    Gc {
+        /// Only print what would be removed
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SbomCommands {
+    /// Convert SBOM between CycloneDX (JSON, XML, protobuf) and SPDX 2.3
+    /// (JSON, tag-value), reporting what has no place in the target
+    Convert {
+        #[arg(name = "sbom_path")]
+        sbom: PathBuf,
+        /// Where to write the converted SBOM
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Format of the converted SBOM
+        #[arg(long, value_enum)]
+        to: OutputFormat,
+        /// Where to write the list of what was not converted, one item per
+        /// line, printed to stderr otherwise
+        #[arg(long)]
+        report: Option<PathBuf>,
     },
 }
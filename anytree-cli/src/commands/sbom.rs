@@ -0,0 +1,78 @@
+use std::path::Path;
+
+use anytree_sbom::spdx::{self, SpdxDocument};
+use anytree_sbom::{CycloneDXBom, Format};
+use clap::ValueEnum;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// CycloneDX JSON
+    CyclonedxJson,
+    /// CycloneDX XML
+    CyclonedxXml,
+    /// CycloneDX protobuf
+    CyclonedxProtobuf,
+    /// SPDX 2.3 JSON
+    SpdxJson,
+    /// SPDX 2.3 tag-value
+    SpdxTagValue,
+}
+
+/// Converts the CycloneDX or SPDX SBOM at `sbom_path` to the format `to` and
+/// writes it to `output`. What has no place in the other standard is listed in
+/// `report`, or on stderr without one.
+pub fn convert(
+    sbom_path: &Path,
+    output: &Path,
+    to: OutputFormat,
+    report: Option<&Path>,
+) -> anyhow::Result<()> {
+    let data = std::fs::read(sbom_path)
+        .map_err(|e| anyhow::format_err!("Failed to open {:?}: {e}", sbom_path))?;
+    let parse_error = |e| anyhow::format_err!("Failed to parse {:?}: {e}", sbom_path);
+    let mut unmapped = vec![];
+    let sbom = match spdx::Format::detect(&data) {
+        Some(format) => {
+            let document = SpdxDocument::parse_as(&data, format).map_err(parse_error)?;
+            let conversion = document.to_cyclonedx();
+            unmapped.extend(conversion.unmapped);
+            conversion.output
+        }
+        None => CycloneDXBom::parse(&data).map_err(parse_error)?,
+    };
+
+    let mut spdx = |format| {
+        let conversion = SpdxDocument::from_cyclonedx(&sbom);
+        unmapped.extend(conversion.unmapped);
+        conversion.output.write(format)
+    };
+    let data = match to {
+        OutputFormat::CyclonedxJson => sbom.write(Format::Json)?,
+        OutputFormat::CyclonedxXml => sbom.write(Format::Xml)?,
+        OutputFormat::CyclonedxProtobuf => sbom.write(Format::Protobuf)?,
+        OutputFormat::SpdxJson => spdx(spdx::Format::Json)?,
+        OutputFormat::SpdxTagValue => spdx(spdx::Format::TagValue)?,
+    };
+    std::fs::write(output, data)
+        .map_err(|e| anyhow::format_err!("Failed to write {:?}: {e}", output))?;
+    tracing::info!("Converted SBOM is written to {:?}", output);
+
+    match report {
+        Some(report) => {
+            let text = unmapped.iter().map(|item| format!("{item}\n")).collect::<String>();
+            std::fs::write(report, text)
+                .map_err(|e| anyhow::format_err!("Failed to write {:?}: {e}", report))?;
+            if !unmapped.is_empty() {
+                tracing::warn!("{} items not converted, see {:?}", unmapped.len(), report);
+            }
+        }
+        None if !unmapped.is_empty() => {
+            eprintln!("Not converted:");
+            for item in &unmapped {
+                eprintln!("  {item}");
+            }
+        }
+        None => {}
+    }
+    Ok(())
+}
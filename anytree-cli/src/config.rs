@@ -0,0 +1,42 @@
+use std::path::{Path, PathBuf};
+
+use anytree_utils::credentials::HostCredentials;
+use anytree_utils::rewrite::RewriteRule;
+use serde::Deserialize;
+
+const CONFIG_NAME: &str = "config.toml";
+
+/// User settings of anytree, kept out of the SBOMs and the run dirs
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Credentials for the git remotes, per host. Hosts without any get the
+    /// keys of ssh-agent and the git credential helpers.
+    #[serde(default)]
+    pub credentials: Vec<HostCredentials>,
+    /// Rewrite rules for the URLs of the sources, e.g. to fetch them from
+    /// mirrors
+    #[serde(default, rename = "rewrite")]
+    pub rewrites: Vec<RewriteRule>,
+}
+
+impl Config {
+    /// Config from `path`, else from ~/.config/anytree/config.toml if it
+    /// exists
+    pub fn load(path: Option<&Path>) -> anyhow::Result<Self> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match Self::default_path().filter(|path| path.exists()) {
+                Some(path) => path,
+                None => return Ok(Self::default()),
+            },
+        };
+        let data = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow::format_err!("Failed to read config {:?}: {e}", path))?;
+        toml::from_str(&data).map_err(|e| anyhow::format_err!("Wrong config {:?}: {e}", path))
+    }
+
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("anytree").join(CONFIG_NAME))
+    }
+}
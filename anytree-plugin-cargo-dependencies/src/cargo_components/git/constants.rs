@@ -1,10 +1,19 @@
 pub const CARGO_GIT_SUBFOLDER: &str = "git";
 pub const DB_SUBFOLDER: &str = "db";
 pub const CHECKOUTS_SUBFOLDER: &str = "checkouts";
+/// Dir of the dependencies dir with the branch histories fetched to check the
+/// branches from SBOM, not mounted into the container
+pub const HISTORY_SUBFOLDER: &str = "git-history";
 pub const REF_PATH: &str = "refs/remotes/origin";
 pub const REF_FILE_NAME: &str = "HEAD";
 pub const CARGO_OK_FILE_NAME: &str = ".cargo-ok";
 
+pub const TAG_PROPERTY: &str = "tag";
+pub const BRANCH_PROPERTY: &str = "branch";
+
+pub const DB_STORE_KIND: &str = "git-db";
+pub const CHECKOUT_STORE_KIND: &str = "git-checkout";
+
 pub const CACHE_DIR_TAG_NAME: &str = "CACHEDIR.TAG";
 
 pub const DEFAULT_CACHE_DIR_TAG: &str = "Signature: 8a477f597d28d172789f06886806bc55
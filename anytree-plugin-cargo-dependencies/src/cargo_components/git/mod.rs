@@ -1,85 +1,119 @@
-mod constants;
+pub(super) mod constants;
 
 use std::fs::File;
 use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
-use std::sync::Once;
 
 use anytree_sbom::Component;
-use anytree_utils::crypto::hash::check_hashes;
+use anytree_utils::credentials::Credentials;
+use anytree_utils::crypto::hash::check_source_hashes;
+use anytree_utils::{git, nar};
 
 use crate::cargo_components::git::constants::*;
-use crate::cargo_components::helper::{get_component_properties, get_suffix_hash};
+use crate::cargo_components::helper::{
+    get_component_properties, get_suffix_hash, COMPLETE_MARKER_NAME,
+};
+use crate::cargo_components::registry::constants::COMMIT_PROPERTY;
+use crate::loader::{DependencyLoader, LoadContext};
+use crate::signature::check_signatures;
+use crate::store::write_atomic;
+use crate::Policy;
 
-static INIT: Once = Once::new();
 pub const LIBRARY_TYPE: &str = "cargo/git";
 
 pub struct CargoGitComponent {}
 
-fn init(cargo_root: impl AsRef<Path>) {
+fn init(cargo_root: impl AsRef<Path>) -> anyhow::Result<()> {
     tracing::trace!("Init cargo git dirs.");
     // create default cache dir tag
     let mut git_path = PathBuf::from(cargo_root.as_ref());
     git_path.push(CARGO_GIT_SUBFOLDER);
-    std::fs::create_dir_all(&git_path).expect("Failed to create directory for cargo git registry");
+    std::fs::create_dir_all(&git_path).map_err(|e| {
+        anyhow::format_err!("Failed to create directory for cargo git registry: {e}")
+    })?;
     git_path.push(CACHE_DIR_TAG_NAME);
-    let mut cache_dir_tag_file =
-        File::create(git_path.to_str().unwrap()).expect("Failed to create default cargo git tag");
+    let mut cache_dir_tag_file = File::create(&git_path)
+        .map_err(|e| anyhow::format_err!("Failed to create default cargo git tag: {e}"))?;
     cache_dir_tag_file
         .write_all(DEFAULT_CACHE_DIR_TAG.as_bytes())
-        .expect("Failed to write default cargo git tag");
+        .map_err(|e| anyhow::format_err!("Failed to write default cargo git tag: {e}"))
+}
+
+impl DependencyLoader for CargoGitComponent {
+    fn mime_type(&self) -> &'static str {
+        LIBRARY_TYPE
+    }
+
+    fn prepare(&self, _components: &[&Component], context: &LoadContext) -> anyhow::Result<()> {
+        init(context.deps_root)
+    }
+
+    fn load(&self, component: &Component, context: &LoadContext) -> anyhow::Result<()> {
+        Self::save(context, component)
+    }
 }
 
 impl CargoGitComponent {
-    pub fn save(cargo_root: &Path, component: &Component) -> anyhow::Result<()> {
+    pub fn save(context: &LoadContext, component: &Component) -> anyhow::Result<()> {
         tracing::info!("Loading cargo git component {}", component.name,);
-        INIT.call_once(|| init(cargo_root));
+        let cargo_root = context.deps_root;
         let mut path = PathBuf::from(cargo_root);
         path.push(CARGO_GIT_SUBFOLDER);
 
         let name = &component.name;
         let properties = get_component_properties(component)?;
+        let credentials = &context.options.credentials;
         let url = &component
             .external_references
             .as_ref()
-            .and_then(|v| v.get(0))
+            .and_then(|v| v.first())
             .ok_or(anyhow::format_err!("Failed to get url for component: {}", component.name))?
             .url;
         let commit = properties
-            .get("commit")
+            .get(COMMIT_PROPERTY)
             .ok_or(anyhow::format_err!("Failed to get dependency commit"))?;
+        // the tag names a ref file in the clone and on the remote
+        let tag = properties.get(TAG_PROPERTY);
+        if let Some(tag) = tag {
+            git::check_tag_name(tag)?;
+        }
+        // dirs and store keys follow the URL from SBOM, fetches go to the
+        // rewritten one
+        let remote = &*context.options.rewrites.rewrite(url);
 
         let mut clone_dir = path.clone();
         clone_dir.push(DB_SUBFOLDER);
 
         let dir_suffix = get_suffix_hash(url, None);
         clone_dir.push(format!("{}-{}", name, &dir_suffix));
+        let _lock = context.locks.lock(clone_dir.to_string_lossy());
+        // bare clone is taken from the store only for the commit it was
+        // verified for
+        let key = format!("{}-{}", name, get_suffix_hash(&format!("{url}#{commit}"), None));
+        context.fetch_entry(
+            DB_STORE_KIND,
+            &key,
+            &clone_dir,
+            |path| check_db(component, path, commit),
+            |staging| clone_db(remote, commit, staging, credentials),
+        )?;
+        // SBOM may pin an abbreviated commit, the refs need the full one
+        let commit = &git::resolve_commit(&clone_dir, commit)?;
 
-        if clone_dir.exists() {
-            return Ok(());
-        }
-        // Clone bare repo
-        tracing::trace!("Cloning the bare repo. url: {}", &url);
-        let status = Command::new("git")
-            .arg("clone")
-            .arg("--bare")
-            .arg(url)
-            .arg(clone_dir.as_os_str())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .status()
-            .map_err(|e| anyhow::format_err!("Failed to bare clone repo: {e}"))?;
-
-        if !status.success() {
-            anyhow::bail!("Failed to clone bare repo: {}", url);
-        }
-
-        // check hashes if specified in SBOM
-        if let Some(hashes) = &component.hashes {
-            let data = git_archive(&clone_dir, commit)?;
-            check_hashes(hashes, data)?;
+        if context.options.ref_mismatch != Policy::Ignore {
+            let history_dir =
+                cargo_root.join(HISTORY_SUBFOLDER).join(format!("{}-{}", name, &dir_suffix));
+            let _history_lock = context.locks.lock(history_dir.to_string_lossy());
+            context.options.ref_mismatch.apply(check_refs(
+                tag,
+                properties.get(BRANCH_PROPERTY),
+                &history_dir,
+                remote,
+                commit,
+                credentials,
+            ))?;
         }
+        check_signatures(component, &clone_dir, url, commit, context.options)?;
 
         // Simple bare clone is not enough for cargo install need to stare ref
         let mut ref_path = clone_dir.clone();
@@ -87,20 +121,18 @@ impl CargoGitComponent {
         std::fs::create_dir_all(&ref_path)?;
         ref_path.push(REF_FILE_NAME);
         if !ref_path.exists() {
-            let mut file = File::create(&ref_path)
+            write_atomic(&ref_path, commit)
                 .map_err(|e| anyhow::format_err!("Failed to create ref file: {e}"))?;
-            file.write_all(commit.clone().as_bytes())?;
         }
 
         // if tag was specified need to store tag
-        let tag = properties.get("tag");
         if let Some(tag) = tag {
             let mut tags_path = clone_dir.clone();
             tags_path.push(REF_PATH);
             tags_path.push("tags");
             std::fs::create_dir_all(&tags_path)?;
             tags_path.push(tag);
-            std::fs::write(tags_path, commit)
+            write_atomic(tags_path, commit)
                 .map_err(|e| anyhow::format_err!("Failed to write tag: {e}"))?;
         }
 
@@ -110,58 +142,137 @@ impl CargoGitComponent {
         let mut trimmed_commit = commit.clone();
         trimmed_commit.truncate(7);
         checkout_dir.push(trimmed_commit);
+        context.fetch_entry(
+            CHECKOUT_STORE_KIND,
+            &key,
+            &checkout_dir,
+            |path| {
+                // Cargo treats the checkout as complete once it has .cargo-ok
+                if !path.join(CARGO_OK_FILE_NAME).exists() {
+                    anyhow::bail!("Checkout of {name} is incomplete");
+                }
+                Ok(())
+            },
+            |staging| checkout(component, &clone_dir, commit, staging, context),
+        )?;
+        Ok(())
+    }
+}
 
-        std::fs::create_dir_all(&checkout_dir)?;
-
-        // clone dir from bare repo
-        tracing::trace!("Cloning from bare repo to the ordinary one. path: {:?}", &checkout_dir);
-        let status = Command::new("git")
-            .arg("clone")
-            .arg("--recurse-submodules")
-            .arg(clone_dir.as_os_str())
-            .arg(checkout_dir.as_os_str())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .status()?;
-
-        if !status.success() {
-            anyhow::bail!("Failed to clone repo: {:?}", clone_dir);
+/// Tag and branch from SBOM still point at the full `commit` on the remote:
+/// the tag was not moved and the branch was not rewritten. The branch history
+/// is fetched into `history`, see [`git::branch_contains`].
+fn check_refs(
+    tag: Option<&String>,
+    branch: Option<&String>,
+    history: &Path,
+    url: &str,
+    commit: &str,
+    credentials: &Credentials,
+) -> anyhow::Result<()> {
+    if let Some(tag) = tag {
+        match git::ls_remote(url, &format!("refs/tags/{tag}"), credentials)? {
+            Some(target) if target == *commit => {}
+            Some(target) => {
+                anyhow::bail!(
+                    "Tag {tag} of {url} points to {target}, not to the pinned commit {commit}"
+                )
+            }
+            None => anyhow::bail!("Tag {tag} not found in {url}"),
         }
-
-        // checkout commit
-        tracing::trace!("Checkout the commit dir: {:?}, commit: {}", &checkout_dir, commit);
-        let status = Command::new("git")
-            .arg("checkout")
-            .arg("-f")
-            .arg(commit)
-            .current_dir(checkout_dir.as_os_str())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .status()?;
-
-        if !status.success() {
-            anyhow::bail!("Failed to checkout commit: {} in {:?}", commit, checkout_dir);
+    }
+    if let Some(branch) = branch {
+        if !git::branch_contains(history, url, branch, commit, credentials)? {
+            anyhow::bail!("Branch {branch} of {url} does not contain the pinned commit {commit}");
         }
+    }
+    Ok(())
+}
 
-        checkout_dir.push(CARGO_OK_FILE_NAME);
-        if !checkout_dir.exists() {
-            tracing::trace!("Create a cargo-ok file: {:?}", &checkout_dir);
-            File::create(&checkout_dir)?;
-        }
+fn clone_db(url: &str, commit: &str, dest: &Path, credentials: &Credentials) -> anyhow::Result<()> {
+    git::clone_bare(url, commit, dest, credentials)?;
+    File::create(dest.join(COMPLETE_MARKER_NAME))?;
+    Ok(())
+}
 
-        Ok(())
+/// Bare clone is complete and has the commit. Its content is checked against
+/// SBOM once, when the commit is checked out.
+fn check_db(component: &Component, repo: &Path, commit: &str) -> anyhow::Result<()> {
+    if !repo.join(COMPLETE_MARKER_NAME).exists() {
+        anyhow::bail!("Clone of {} is incomplete", component.name);
     }
+    git::resolve_commit(repo, commit)?;
+    Ok(())
+}
+
+/// Checks the commit out of the bare clone to `dest`, the checkout must match
+/// the hashes from SBOM if any, see [`check_source_hashes`]
+fn checkout(
+    component: &Component,
+    clone_dir: &Path,
+    commit: &str,
+    dest: &Path,
+    context: &LoadContext,
+) -> anyhow::Result<()> {
+    let options = context.options;
+    git::checkout(
+        &clone_dir.to_string_lossy(),
+        commit,
+        dest,
+        &options.credentials,
+        &options.rewrites,
+    )?;
+    check_source_hashes(
+        component,
+        || git::archive(clone_dir, commit),
+        || nar::dump_checkout(dest),
+    )?;
+    tracing::trace!("Create a cargo-ok file: {:?}", dest);
+    File::create(dest.join(CARGO_OK_FILE_NAME))?;
+    Ok(())
 }
 
-pub fn git_archive(repo: impl AsRef<Path>, commit: impl AsRef<str>) -> anyhow::Result<Vec<u8>> {
-    let git_archive_output = Command::new("git")
-        .arg("archive")
-        .arg("--format=tar")
-        .arg(commit.as_ref())
-        .current_dir(repo.as_ref())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()?;
-
-    Ok(git_archive_output.stdout)
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use super::*;
+
+    #[test]
+    fn test_check_refs_with_abbreviated_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let origin = dir.path().join("origin");
+        let git = |args: &[&str]| {
+            let output = Command::new("git")
+                .arg("-C")
+                .arg(dir.path())
+                .args(["-c", "user.name=test", "-c", "user.email=test@localhost"])
+                .args(args)
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+            String::from_utf8(output.stdout).unwrap().trim().to_string()
+        };
+        git(&["init", "-q", "-b", "main", "origin"]);
+        git(&["-C", "origin", "commit", "--allow-empty", "-m", "pinned"]);
+        git(&["-C", "origin", "tag", "-a", "v1", "-m", "v1"]);
+        let full = git(&["-C", "origin", "rev-parse", "HEAD"]);
+        git(&["-C", "origin", "commit", "--allow-empty", "-m", "next"]);
+        let url = format!("file://{}", origin.display());
+        let clone = dir.path().join("db");
+        clone_db(&url, &full, &clone, &Credentials::default()).unwrap();
+
+        let commit = git::resolve_commit(&clone, &full[..7]).unwrap();
+        assert_eq!(full, commit);
+        let (tag, branch) = (Some("v1".to_string()), Some("main".to_string()));
+        let history = dir.path().join("history");
+        let check = |tag: Option<&String>, commit: &str| {
+            check_refs(tag, branch.as_ref(), &history, &url, commit, &Credentials::default())
+        };
+        check(tag.as_ref(), &commit).unwrap();
+        // the tag target is compared as is
+        assert!(check(tag.as_ref(), &full[..7]).is_err());
+        check(None, &full[..7]).unwrap();
+        assert!(check(Some(&"v2".to_string()), &commit).is_err());
+    }
 }
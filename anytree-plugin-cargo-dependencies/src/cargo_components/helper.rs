@@ -1,12 +1,17 @@
-use std::collections::HashMap;
-use std::fs::File;
+use std::collections::{HashMap, HashSet};
 #[allow(deprecated)]
 use std::hash::SipHasher;
 use std::hash::{Hash, Hasher};
-use std::io::Write;
 use std::path::Path;
 
 use anytree_sbom::Component;
+use serde::Deserialize;
+
+use crate::store::write_atomic;
+
+/// Marks an entry that has no marker of its own in the cargo layout as
+/// completely fetched and verified
+pub const COMPLETE_MARKER_NAME: &str = ".anytree-ok";
 
 /// https://doc.rust-lang.org/cargo/reference/registry-index.html#index-files
 /// Packages with 1 character names are placed in a directory named 1.
@@ -41,36 +46,119 @@ pub fn name_to_index_path(crate_name: &str) -> String {
     }
 }
 
+/// A single line of the crates.io index. Only the fields anytree needs are
+/// parsed, the line itself is stored in the cache verbatim.
+#[derive(Debug, Deserialize)]
+struct IndexLine {
+    vers: String,
+    /// SHA-256 of the `.crate` file
+    cksum: Option<String>,
+}
+
+/// Header of the cargo index cache file: `[cache_version] [index_v_max]`
+const INDEX_CACHE_HEADER: [u8; 5] = [3, 2, 0, 0, 0];
+
 // Cargo stores index in cache with special format
 // https://github.com/rust-lang/cargo/blob/04c94d90b69617a1d744cc141deebea4ebdfd886/src/cargo/sources/registry/index.rs#L835
+//
+// Only the versions pinned in the SBOM are kept, so cargo can't silently
+// resolve to a version that wasn't reviewed.
 pub fn convert_index_to_cache(
     index_str: &str,
+    pinned_versions: &[&str],
     output_path: impl AsRef<Path>,
 ) -> anyhow::Result<()> {
+    let res_bytes = index_to_cache_bytes(index_str, pinned_versions)?;
+    write_atomic(output_path, res_bytes)
+}
+
+fn index_to_cache_bytes(index_str: &str, pinned_versions: &[&str]) -> anyhow::Result<Vec<u8>> {
     // start with headers  [cache_version] [index_v_max]
-    let mut res_bytes = vec![3_u8, 2_u8, 0, 0, 0];
+    let mut res_bytes = INDEX_CACHE_HEADER.to_vec();
 
     // than etag, which we do not generate but use a random value
     let etag = "etag: W/\"bbbf8771a5922743c5e0b466d90e7ab6\"";
     res_bytes.append(&mut etag.as_bytes().to_vec());
     res_bytes.push(0);
 
+    for (version, line) in pinned_index_lines(index_str, pinned_versions)? {
+        res_bytes.append(&mut version.into_bytes());
+        res_bytes.push(0);
+        res_bytes.append(&mut line.as_bytes().to_vec());
+        res_bytes.push(0);
+    }
+    Ok(res_bytes)
+}
+
+/// Index lines stored in the cargo cache file, in the index file format
+pub fn cache_to_index(cache: &[u8]) -> anyhow::Result<String> {
+    let Some(entries) = cache.strip_prefix(&INDEX_CACHE_HEADER) else {
+        anyhow::bail!("Unsupported index cache format");
+    };
+    // etag goes first, then pairs of version and index line
+    let mut lines = vec![];
+    for (i, part) in entries.split(|byte| *byte == 0).skip(1).enumerate() {
+        if i % 2 == 1 {
+            lines.push(std::str::from_utf8(part)?);
+        }
+    }
+    let mut index = lines.join("\n");
+    index.push('\n');
+    Ok(index)
+}
+
+/// SHA-256 checksum of the `.crate` file of `version` from the index, if the
+/// registry provides it
+pub fn index_checksum(index_str: &str, version: &str) -> anyhow::Result<Option<String>> {
+    for line in index_str.split('\n').filter(|line| !line.is_empty()) {
+        let index_line: IndexLine = serde_json::from_str(line)
+            .map_err(|e| anyhow::format_err!("Failed to parse index line: {e}"))?;
+        if index_line.vers == version {
+            return Ok(index_line.cksum);
+        }
+    }
+    anyhow::bail!("Version {version} is missing in the index")
+}
+
+/// Lines of the index file for the versions pinned in the SBOM together with
+/// their parsed versions. Fails if any pinned version is missing in the index.
+pub fn pinned_index_lines<'a>(
+    index_str: &'a str,
+    pinned_versions: &[&str],
+) -> anyhow::Result<Vec<(String, &'a str)>> {
+    let mut res = vec![];
+    let mut found_versions = HashSet::new();
     for line in index_str.split('\n') {
         if line.is_empty() {
             break;
         }
-        let object: serde_json::Value = serde_json::from_str(line)?;
-        let version = object.as_object().unwrap()["vers"].as_str().unwrap();
-        res_bytes.append(&mut version.as_bytes().to_vec());
-        res_bytes.push(0);
-        res_bytes.append(&mut line.as_bytes().to_vec());
-        res_bytes.push(0);
+        let index_line: IndexLine = serde_json::from_str(line)
+            .map_err(|e| anyhow::format_err!("Failed to parse index line: {e}"))?;
+        if !pinned_versions.contains(&index_line.vers.as_str()) {
+            continue;
+        }
+        found_versions.insert(index_line.vers.clone());
+        res.push((index_line.vers, line));
     }
 
-    // Save to the file
-    let mut ofile = File::create(output_path)?;
-    ofile.write_all(res_bytes.as_slice())?;
-    Ok(())
+    let missing = pinned_versions
+        .iter()
+        .filter(|version| !found_versions.contains(**version))
+        .copied()
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        anyhow::bail!("Versions pinned in SBOM are missing in the index: {}", missing.join(", "));
+    }
+    Ok(res)
+}
+
+/// Key of the component content in the store: its first hash from SBOM, e.g.
+/// `sha-256-<hex>`, or `fallback` if SBOM has no hashes for it
+pub fn store_key(component: &Component, fallback: impl FnOnce() -> String) -> String {
+    match component.hashes.as_ref().and_then(|hashes| hashes.first()) {
+        Some(hash) => format!("{}-{}", hash.alg.to_lowercase(), hash.content.to_lowercase()),
+        None => fallback(),
+    }
 }
 
 pub fn get_component_properties(component: &Component) -> anyhow::Result<HashMap<String, String>> {
@@ -84,6 +172,37 @@ pub fn get_component_properties(component: &Component) -> anyhow::Result<HashMap
     Ok(result)
 }
 
+/// Value of the qualifier `key` of a package url, percent-decoded
+/// https://github.com/package-url/purl-spec/blob/master/PURL-SPECIFICATION.rst
+pub fn purl_qualifier(purl: &str, key: &str) -> Option<String> {
+    let qualifiers = purl.split_once('?')?.1;
+    let qualifiers = qualifiers.split('#').next().unwrap_or_default();
+    qualifiers
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(name, _)| *name == key)
+        .map(|(_, value)| percent_decode(value))
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut res = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or_default();
+            if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                res.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        res.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&res).to_string()
+}
+
 /// Slightly trimmed struct from cargo
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum SourceKind {
@@ -92,7 +211,7 @@ pub enum SourceKind {
     /// A local path.
     _Path,
     /// A remote registry.
-    _Registry,
+    Registry,
     /// A sparse registry.
     SparseRegistry,
     /// A local filesystem-based registry.
@@ -128,13 +247,47 @@ mod tests {
         assert_eq!("se/rd/serde", name_to_index_path("serde"));
     }
 
+    #[test]
+    fn test_index_keeps_only_pinned_versions() {
+        let index = concat!(
+            r#"{"name":"ryu","vers":"1.0.1","deps":[],"cksum":"aa","features":{},"yanked":false}"#,
+            "\n",
+            r#"{"name":"ryu","vers":"1.0.14","deps":[],"cksum":"bb","features":{},"yanked":false}"#,
+            "\n",
+            r#"{"name":"ryu","vers":"1.0.15","deps":[],"cksum":"cc","features":{},"yanked":false}"#,
+            "\n",
+        );
+        let bytes = index_to_cache_bytes(index, &["1.0.1"]).unwrap();
+        let content = String::from_utf8_lossy(&bytes);
+        assert!(content.contains("\"vers\":\"1.0.1\""));
+        assert!(!content.contains("1.0.14"));
+        assert!(!content.contains("1.0.15"));
+
+        let err = index_to_cache_bytes(index, &["1.0.14", "2.0.0"]).unwrap_err();
+        assert!(err.to_string().contains("2.0.0"));
+
+        let cache = index_to_cache_bytes(index, &["1.0.1", "1.0.15"]).unwrap();
+        let index = cache_to_index(&cache).unwrap();
+        assert_eq!(2, index.lines().count());
+        assert_eq!(Some("cc".to_string()), index_checksum(&index, "1.0.15").unwrap());
+        assert!(index_checksum(&index, "1.0.14").is_err());
+    }
+
+    #[test]
+    fn test_purl_qualifier() {
+        let purl = "pkg:cargo/foo@1.0.0?arch=x86&repository_url=sparse%2Bhttps%3A%2F%2Fa.b%2F#sub";
+        assert_eq!(Some("sparse+https://a.b/".to_string()), purl_qualifier(purl, "repository_url"));
+        assert_eq!(Some("x86".to_string()), purl_qualifier(purl, "arch"));
+        assert_eq!(None, purl_qualifier("pkg:cargo/foo@1.0.0", "repository_url"));
+    }
+
     #[test]
     fn test_suffix() {
         assert_eq!(
             "1ecc6299db9ec823",
             get_suffix_hash(
                 "https://github.com/rust-lang/crates.io-index",
-                Some(SourceKind::_Registry)
+                Some(SourceKind::Registry)
             )
         );
         assert_eq!(
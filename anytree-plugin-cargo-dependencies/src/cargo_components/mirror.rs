@@ -0,0 +1,382 @@
+//! Mirrors of the registry crates and the git dependencies of SBOM in git
+//! repos, e.g. GOSH ones, and SBOM fetching them from there.
+//!
+//! A git dependency is mirrored with the history of its pinned commit, so the
+//! commit, the hashes, the tree hashes and the signatures from SBOM hold for
+//! the mirror. A crate is committed as its original `.crate` file, so it still
+//! matches the checksums of the index and `Cargo.lock`, together with its line
+//! of the registry index, so loading it needs no registry at all.
+
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anytree_sbom::{Component, CycloneDXBom};
+use anytree_utils::credentials::Credentials;
+use anytree_utils::crypto::hash::check_hashes;
+use anytree_utils::git;
+use anytree_utils::http::{HttpClient, HttpConfig};
+use anytree_utils::rewrite::UrlRewrites;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::cargo_components::git::constants::{BRANCH_PROPERTY, TAG_PROPERTY};
+use crate::cargo_components::helper::{get_component_properties, pinned_index_lines};
+use crate::cargo_components::registry::constants::{
+    COMMIT_PROPERTY, INDEX_CONFIG_NAME, MIRROR_INDEX_NAME,
+};
+use crate::cargo_components::registry::source::{CargoRegistry, IndexProtocol};
+use crate::cargo_components::{git as git_component, registry};
+
+/// Branch of the mirror the pinned commit is pushed to when SBOM names none
+const MIRROR_BRANCH: &str = "main";
+/// External reference type of the mirror repos
+const MIRROR_REFERENCE_TYPE: &str = "vcs";
+const CRATE_COMMIT_MESSAGE: &str = "Crate mirrored by anytree";
+
+pub struct MirrorOptions {
+    /// Base URL of the mirror repos, a component goes to
+    /// `<dest>/<name>-<version>`. Local bare repos are created, remote ones
+    /// must exist.
+    pub dest: String,
+    /// Journal of the mirrored components. The ones already in it are not
+    /// pushed again, so an interrupted mirror resumes where it stopped.
+    pub journal: PathBuf,
+    pub http: HttpConfig,
+    /// Credentials for the git remotes, the sources and the mirrors
+    pub credentials: Credentials,
+    /// Rewrite rules applied to the URLs of the sources before fetching them
+    pub rewrites: UrlRewrites,
+}
+
+/// Line of the journal
+#[derive(Debug, Serialize, Deserialize)]
+struct JournalEntry {
+    /// `<url>#<commit>` of a git dependency, the download URL of a crate
+    source: String,
+    /// Mirror repo
+    url: String,
+    /// Commit of the mirror repo SBOM pins
+    commit: String,
+}
+
+/// Mirrors every registry crate and git dependency of `sbom`, the JSON of a
+/// CycloneDX SBOM, and returns the SBOM with them pointing at the mirrors.
+/// The rest of the SBOM is kept as it is.
+pub fn mirror(sbom: &Value, options: &MirrorOptions) -> anyhow::Result<Value> {
+    let bom = CycloneDXBom::deserialize(sbom)
+        .map_err(|e| anyhow::format_err!("Failed to parse SBOM: {e}"))?;
+    let http = HttpClient::new(options.http.clone())?.with_rewrites(options.rewrites.clone());
+    let crates_io_protocol = IndexProtocol::for_crates_io(&bom)?;
+    let mut journal = read_journal(&options.journal)?;
+    let mut repos = HashMap::new();
+    let mut res = sbom.clone();
+    for (i, component) in bom.components.iter().enumerate() {
+        let is_crate = match component.mime_type.as_deref() {
+            Some(registry::LIBRARY_TYPE) => true,
+            Some(git_component::LIBRARY_TYPE) => false,
+            _ => continue,
+        };
+        let source_url = component
+            .external_references
+            .iter()
+            .flatten()
+            .next()
+            .ok_or(anyhow::format_err!("Failed to get url for component: {}", component.name))?
+            .url
+            .as_str();
+        let properties = get_component_properties(component)?;
+        let source = if is_crate {
+            source_url.to_string()
+        } else {
+            let commit = properties
+                .get(COMMIT_PROPERTY)
+                .ok_or(anyhow::format_err!("Git component {} has no commit", component.name))?;
+            format!("{source_url}#{commit}")
+        };
+        let name = repo_name(component);
+        if let Some(other) = repos.insert(name.clone(), source.clone()) {
+            anyhow::bail!("{source} and {other} would be mirrored to the same repo {name}");
+        }
+        let url = format!("{}/{name}", options.dest.trim_end_matches('/'));
+
+        let mirrored = journal.iter().find(|entry| entry.source == source && entry.url == url);
+        let commit = match mirrored {
+            Some(entry) => {
+                tracing::info!("{source} is mirrored to {url} already");
+                entry.commit.clone()
+            }
+            None => {
+                tracing::info!("Mirroring {source} to {url}");
+                if let Some(path) = local_path(&url) {
+                    git::init_bare(path)?;
+                }
+                let commit = if is_crate {
+                    let registry = CargoRegistry::from_component(component, crates_io_protocol)?;
+                    mirror_crate(component, &registry, &http, &url, options)
+                } else {
+                    mirror_git(component, source_url, &properties, &url, options)
+                }
+                .map_err(|e| anyhow::format_err!("Failed to mirror {source} to {url}: {e}"))?;
+                let entry = JournalEntry { source, url: url.clone(), commit };
+                append_journal(&options.journal, &entry)?;
+                let commit = entry.commit.clone();
+                journal.push(entry);
+                commit
+            }
+        };
+
+        let component = &mut res["components"][i];
+        component["externalReferences"] = json!([{ "url": url, "type": MIRROR_REFERENCE_TYPE }]);
+        if is_crate {
+            let properties = &mut component["properties"];
+            if !properties.is_array() {
+                *properties = json!([]);
+            }
+            if let Some(properties) = properties.as_array_mut() {
+                properties.retain(|property| property["name"] != COMMIT_PROPERTY);
+                properties.push(json!({ "name": COMMIT_PROPERTY, "value": commit }));
+            }
+        }
+    }
+    Ok(res)
+}
+
+/// Commits the `.crate` file with its index line and the config of its
+/// alternate registry. The commit has the fixed author and date of
+/// [`git::commit_all`], so mirroring the crate again gives the same commit
+/// while its index line stays the same.
+fn mirror_crate(
+    component: &Component,
+    registry: &CargoRegistry,
+    http: &HttpClient,
+    url: &str,
+    options: &MirrorOptions,
+) -> anyhow::Result<String> {
+    let version = component
+        .version
+        .as_deref()
+        .ok_or(anyhow::format_err!("Component {} does not contain version", component.name))?;
+    let urls = component
+        .external_references
+        .iter()
+        .flatten()
+        .map(|reference| reference.url.as_str())
+        .collect::<Vec<_>>();
+    let data = http.get(&urls)?;
+    match &component.hashes {
+        Some(hashes) if !hashes.is_empty() => check_hashes(hashes, &data)?,
+        _ => tracing::warn!("Crate {} has no hashes in SBOM to check", component.name),
+    }
+
+    let index_urls = registry.index_file_urls(&component.name);
+    let index = http
+        .get(&index_urls.iter().map(String::as_str).collect::<Vec<_>>())
+        .map_err(|e| anyhow::format_err!("Failed to download index of {}: {e}", component.name))?;
+    let index = String::from_utf8(index)?;
+    let mut lines = pinned_index_lines(&index, &[version])?
+        .into_iter()
+        .map(|(_, line)| line)
+        .collect::<Vec<_>>();
+    lines.push("");
+
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join(format!("{}-{version}.crate", component.name)), data)?;
+    std::fs::write(dir.path().join(MIRROR_INDEX_NAME), lines.join("\n"))?;
+    if !registry.is_crates_io() {
+        std::fs::write(dir.path().join(INDEX_CONFIG_NAME), registry.index_config(http)?)?;
+    }
+    let reference = format!("refs/heads/{MIRROR_BRANCH}");
+    let commit = git::commit_all(dir.path(), CRATE_COMMIT_MESSAGE, &[], &reference)?;
+    git::push(dir.path(), url, &[&format!("{reference}:{reference}")], &options.credentials)?;
+    Ok(commit)
+}
+
+/// Pushes the pinned commit with its history to the branch from SBOM, and the
+/// tag from SBOM if any
+fn mirror_git(
+    component: &Component,
+    source_url: &str,
+    properties: &HashMap<String, String>,
+    url: &str,
+    options: &MirrorOptions,
+) -> anyhow::Result<String> {
+    let commit = properties
+        .get(COMMIT_PROPERTY)
+        .ok_or(anyhow::format_err!("Git component {} has no commit", component.name))?;
+    let branch = properties.get(BRANCH_PROPERTY).map_or(MIRROR_BRANCH, String::as_str);
+    let dir = tempfile::tempdir()?;
+    let source_url = options.rewrites.rewrite(source_url);
+    git::clone_history(&source_url, commit, branch, dir.path(), &options.credentials)?;
+    let mut refspecs = vec![format!("refs/heads/{branch}:refs/heads/{branch}")];
+    if let Some(tag) = properties.get(TAG_PROPERTY) {
+        git::check_tag_name(tag)?;
+        refspecs.push(format!("refs/tags/{tag}:refs/tags/{tag}"));
+    }
+    let refspecs = refspecs.iter().map(String::as_str).collect::<Vec<_>>();
+    git::push(dir.path(), url, &refspecs, &options.credentials)?;
+    Ok(commit.clone())
+}
+
+/// `<name>-<version>` with the build metadata dropped and dots replaced, as
+/// GOSH repo names allow neither
+fn repo_name(component: &Component) -> String {
+    let version = component.version.as_deref().unwrap_or_default();
+    let version = version.split('+').next().unwrap_or_default();
+    format!("{}-{}", component.name, version).trim_end_matches('-').replace('.', "-")
+}
+
+/// Path of a mirror repo on the local file system
+fn local_path(url: &str) -> Option<&Path> {
+    match url.strip_prefix("file://") {
+        Some(path) => Some(Path::new(path)),
+        None => (!url.contains(':')).then(|| Path::new(url)),
+    }
+}
+
+fn read_journal(path: &Path) -> anyhow::Result<Vec<JournalEntry>> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let data = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::format_err!("Failed to read journal {:?}: {e}", path))?;
+    let mut entries = vec![];
+    for line in data.lines().filter(|line| !line.trim().is_empty()) {
+        match serde_json::from_str(line) {
+            Ok(entry) => entries.push(entry),
+            // the line being written when the mirror was interrupted
+            Err(e) => tracing::warn!("Skipping broken line of journal {:?}: {e}", path),
+        }
+    }
+    Ok(entries)
+}
+
+fn append_journal(path: &Path, entry: &JournalEntry) -> anyhow::Result<()> {
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .map_err(|e| anyhow::format_err!("Failed to write journal {:?}: {e}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use sha2::Digest;
+
+    use super::*;
+    use crate::cargo_components::registry::tests::{crate_file, serve};
+
+    /// Sparse registry with the crate `foo` 1.0.0, serving each of its files
+    /// once. Returns the registry URL and the crate component.
+    fn registry() -> (String, Value) {
+        let data = crate_file("foo", "1.0.0");
+        let cksum = hex::encode(sha2::Sha256::digest(&data));
+        let index = format!(
+            "{{\"name\":\"foo\",\"vers\":\"1.0.0\",\"deps\":[],\"cksum\":\"{cksum}\",\
+             \"features\":{{}},\"yanked\":false}}\n"
+        );
+        let url = serve(vec![
+            (INDEX_CONFIG_NAME.to_string(), b"{\"dl\": \"https://example.invalid\"}".to_vec()),
+            ("3/f/foo".to_string(), index.into_bytes()),
+            ("foo-1.0.0.crate".to_string(), data),
+        ]);
+        let component = json!({
+            "type": "library",
+            "name": "foo",
+            "version": "1.0.0",
+            "mime-type": "cargo/registry",
+            "externalReferences": [{ "url": format!("{url}foo-1.0.0.crate"), "type": "distribution" }],
+            "properties": [{ "name": "registry_url", "value": url }]
+        });
+        (url, component)
+    }
+
+    fn options(dir: &Path) -> MirrorOptions {
+        MirrorOptions {
+            dest: dir.join("mirrors").to_string_lossy().to_string(),
+            journal: dir.join("journal"),
+            http: HttpConfig::default(),
+            credentials: Credentials::default(),
+            rewrites: UrlRewrites::default(),
+        }
+    }
+
+    #[test]
+    fn test_mirror_and_resume() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source");
+        std::fs::create_dir(&source).unwrap();
+        std::fs::write(source.join("lib.rs"), "fn main() {}").unwrap();
+        let commit = git::commit_all(&source, "init", &[], "refs/heads/main").unwrap();
+        let (_, foo) = registry();
+        let sbom = json!({
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.5",
+            "version": 1,
+            "components": [
+                foo,
+                {
+                    "type": "library",
+                    "name": "lib",
+                    "version": "0.1.0",
+                    "mime-type": "cargo/git",
+                    "externalReferences": [
+                        { "url": format!("file://{}", source.display()), "type": "distribution" }
+                    ],
+                    "properties": [{ "name": "commit", "value": commit }]
+                }
+            ],
+            "dependencies": [{ "ref": "lib" }]
+        });
+        let dest = dir.path().join("mirrors");
+        let options = options(dir.path());
+
+        let mirrored = mirror(&sbom, &options).unwrap();
+        let crate_url = dest.join("foo-1-0-0").to_string_lossy().to_string();
+        let crate_commit = mirrored["components"][0]["properties"][1]["value"].as_str().unwrap();
+        assert_eq!(crate_url, mirrored["components"][0]["externalReferences"][0]["url"]);
+        let data =
+            git::read_file(&crate_url, crate_commit, "foo-1.0.0.crate", &options.credentials);
+        assert!(data.unwrap().starts_with(b"\x1f\x8b"));
+        let data =
+            git::read_file(&crate_url, crate_commit, MIRROR_INDEX_NAME, &options.credentials);
+        assert!(String::from_utf8(data.unwrap()).unwrap().contains("\"vers\":\"1.0.0\""));
+        let lib_url = dest.join("lib-0-1-0").to_string_lossy().to_string();
+        assert_eq!(lib_url, mirrored["components"][1]["externalReferences"][0]["url"]);
+        assert_eq!(sbom["components"][1]["properties"], mirrored["components"][1]["properties"]);
+        let data = git::read_file(&lib_url, &commit, "lib.rs", &options.credentials);
+        assert_eq!(b"fn main() {}".to_vec(), data.unwrap());
+        assert_eq!(sbom["dependencies"], mirrored["dependencies"]);
+
+        // the crate server is gone, so the components come from the journal
+        assert_eq!(mirrored, mirror(&sbom, &options).unwrap());
+    }
+
+    /// Crate of the mirrored SBOM loads with the registry gone, see
+    /// [`registry::CargoRegistryComponent::save`]
+    #[test]
+    fn test_load_mirrored_crate() {
+        let dir = tempfile::tempdir().unwrap();
+        let (url, foo) = registry();
+        let sbom = json!({
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.5",
+            "version": 1,
+            "components": [foo]
+        });
+        // the files of the registry are served once, to the mirror
+        let mirrored = mirror(&sbom, &options(dir.path())).unwrap();
+        let mirrored = CycloneDXBom::deserialize(&mirrored).unwrap();
+        let cargo_dir = dir.path().join("cargo");
+        crate::load_dependencies(&mirrored, &cargo_dir, &crate::LoadOptions::default()).unwrap();
+        let src_dir = std::fs::read_dir(cargo_dir.join("registry/src")).unwrap().next().unwrap();
+        let manifest = src_dir.unwrap().path().join("foo-1.0.0/Cargo.toml");
+        assert!(std::fs::read_to_string(manifest).unwrap().contains("name = \"foo\""));
+        let config = std::fs::read_to_string(cargo_dir.join(crate::CARGO_CONFIG_NAME)).unwrap();
+        assert!(config.contains(&format!("sparse+{url}")), "{config}");
+    }
+}
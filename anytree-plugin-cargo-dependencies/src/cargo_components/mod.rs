@@ -1,31 +1,24 @@
-use std::path::Path;
-
-use anytree_sbom::Component;
-
 use crate::cargo_components::git::CargoGitComponent;
+use crate::cargo_components::path::CargoPathComponent;
 use crate::cargo_components::registry::CargoRegistryComponent;
+use crate::loader::Loaders;
 
 mod git;
 mod helper;
+mod mirror;
+mod path;
 mod registry;
 
-pub fn parse_component(component: &Component, cargo_root: impl AsRef<Path>) -> anyhow::Result<()> {
-    // parse dependency properties
-    let component_type = component
-        .mime_type
-        .as_ref()
-        .ok_or(anyhow::format_err!("Component {} does not contain mime-type.", component.name))?;
-    tracing::trace!("Component type: {}", &component_type);
-    match component_type.as_str() {
-        registry::LIBRARY_TYPE => {
-            CargoRegistryComponent::save(cargo_root.as_ref(), component)?;
-        }
-        git::LIBRARY_TYPE => {
-            CargoGitComponent::save(cargo_root.as_ref(), component)?;
-        }
-        other => {
-            panic!("Unsupported type of component library: {}", other);
-        }
-    }
-    Ok(())
+pub(crate) use git::constants::TAG_PROPERTY;
+pub use mirror::{mirror, MirrorOptions};
+pub use path::constants::PATH_PROPERTY;
+pub use path::{path_dependencies, PathDependency};
+pub use registry::constants::COMMIT_PROPERTY;
+pub use registry::finalize as finalize_registries;
+
+/// Registers loaders of the cargo ecosystem
+pub fn register(loaders: &mut Loaders) {
+    loaders.register(CargoRegistryComponent {});
+    loaders.register(CargoGitComponent {});
+    loaders.register(CargoPathComponent {});
 }
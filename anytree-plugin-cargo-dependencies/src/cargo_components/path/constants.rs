@@ -0,0 +1,6 @@
+pub const CARGO_PATH_SUBFOLDER: &str = "path";
+
+pub const PATH_PROPERTY: &str = "path";
+pub const STRIP_COMPONENTS_PROPERTY: &str = "strip_components";
+
+pub const PATH_STORE_KIND: &str = "path";
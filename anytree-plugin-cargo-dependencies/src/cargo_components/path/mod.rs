@@ -0,0 +1,231 @@
+pub(super) mod constants;
+
+use std::collections::HashMap;
+use std::path::{Component as PathComponent, Path, PathBuf};
+
+use anytree_sbom::{Component, CycloneDXBom};
+use anytree_utils::archive::{extract_tar_gz, ExtractOptions};
+use anytree_utils::crypto::hash::{check_hashes, check_source_hashes, check_tree_hashes};
+use anytree_utils::{git, nar};
+
+use crate::cargo_components::helper::{get_component_properties, get_suffix_hash};
+use crate::cargo_components::path::constants::*;
+use crate::cargo_components::registry::constants::COMMIT_PROPERTY;
+use crate::loader::{DependencyLoader, LoadContext};
+
+pub const LIBRARY_TYPE: &str = "cargo/path";
+
+/// Dependency that the project refers to by a relative path, e.g. a sibling
+/// repository or a submodule. Its source is fetched either from a git commit
+/// (`commit` property) or from an archive checked against the component
+/// hashes, and is mounted at `path` relative to the project manifest.
+pub struct CargoPathComponent {}
+
+/// Fetched path dependency ready to be mounted into the build container
+#[derive(Debug, Clone)]
+pub struct PathDependency {
+    /// Directory with the dependency sources
+    pub source_dir: PathBuf,
+    /// Path relative to the project manifest dir the project expects the
+    /// dependency at
+    pub path: PathBuf,
+}
+
+impl DependencyLoader for CargoPathComponent {
+    fn mime_type(&self) -> &'static str {
+        LIBRARY_TYPE
+    }
+
+    fn load(&self, component: &Component, context: &LoadContext) -> anyhow::Result<()> {
+        Self::save(context, component)
+    }
+}
+
+impl CargoPathComponent {
+    pub fn save(context: &LoadContext, component: &Component) -> anyhow::Result<()> {
+        tracing::info!("Loading cargo path component {}", component.name);
+        let dependency = path_dependency(context.deps_root, component)?;
+        let _lock = context.locks.lock(dependency.source_dir.to_string_lossy());
+        let properties = get_component_properties(component)?;
+        let urls = component
+            .external_references
+            .iter()
+            .flatten()
+            .map(|reference| reference.url.as_str())
+            .collect::<Vec<_>>();
+        if urls.is_empty() {
+            anyhow::bail!("Failed to get url for component: {}", component.name);
+        }
+
+        // source dir name already identifies the content: url with commit or
+        // archive hash
+        let key = dependency.source_dir.file_name().unwrap_or_default().to_string_lossy();
+        context.fetch_entry(
+            PATH_STORE_KIND,
+            &key,
+            &dependency.source_dir,
+            // archives are verified before extraction, git sources can be
+            // checked once more as it needs no network
+            |path| match properties.get(COMMIT_PROPERTY) {
+                Some(commit) => check_git(component, path, commit),
+                None => Ok(()),
+            },
+            |staging| {
+                std::fs::create_dir_all(staging)?;
+                match properties.get(COMMIT_PROPERTY) {
+                    Some(commit) => {
+                        let options = context.options;
+                        let url = options.rewrites.rewrite(urls[0]);
+                        git::checkout(
+                            &url,
+                            commit,
+                            staging,
+                            &options.credentials,
+                            &options.rewrites,
+                        )
+                    }
+                    None => fetch_archive(context, component, &urls, &properties, staging),
+                }
+            },
+        )?;
+        Ok(())
+    }
+}
+
+fn check_git(component: &Component, dir: &Path, commit: &str) -> anyhow::Result<()> {
+    check_source_hashes(component, || git::archive(dir, commit), || nar::dump_checkout(dir))
+}
+
+fn fetch_archive(
+    context: &LoadContext,
+    component: &Component,
+    urls: &[&str],
+    properties: &HashMap<String, String>,
+    dir: &Path,
+) -> anyhow::Result<()> {
+    // archive is accepted only when its content is pinned
+    let hashes = component.hashes.as_ref().filter(|hashes| !hashes.is_empty()).ok_or(
+        anyhow::format_err!("Path component {} from archive must have hashes", component.name),
+    )?;
+
+    tracing::trace!("Downloading path dependency archive. urls: {:?}", urls);
+    let archive = context
+        .http
+        .get(urls)
+        .map_err(|e| anyhow::format_err!("Failed to download {}: {e}", component.name))?;
+    check_hashes(hashes, &archive)?;
+
+    let strip_components = properties
+        .get(STRIP_COMPONENTS_PROPERTY)
+        .map(|value| value.parse::<usize>())
+        .transpose()
+        .map_err(|e| anyhow::format_err!("Wrong {STRIP_COMPONENTS_PROPERTY} property: {e}"))?
+        .unwrap_or(0);
+
+    tracing::trace!("Extracting the path dependency archive.");
+    extract_tar_gz(archive.as_slice(), dir, ExtractOptions { prefix: None, strip_components })
+        .map_err(|e| anyhow::format_err!("Failed to extract archive of {}: {e}", component.name))?;
+    check_tree_hashes(component, || nar::dump_path(dir))
+}
+
+fn path_dependency(cargo_root: &Path, component: &Component) -> anyhow::Result<PathDependency> {
+    let properties = get_component_properties(component)?;
+    let path = properties
+        .get(PATH_PROPERTY)
+        .ok_or(anyhow::format_err!("Path component {} does not contain path", component.name))?;
+    let path = PathBuf::from(path);
+    // `..` is fine for siblings of the manifest dir, the builder checks the
+    // mount target stays in the project
+    if path
+        .components()
+        .any(|part| matches!(part, PathComponent::RootDir | PathComponent::Prefix(_)))
+    {
+        anyhow::bail!("Path of component {} must be relative: {:?}", component.name, path);
+    }
+    if path.components().all(|part| part == PathComponent::CurDir) {
+        anyhow::bail!("Path of component {} must not be empty: {:?}", component.name, path);
+    }
+
+    let url = component
+        .external_references
+        .as_ref()
+        .and_then(|v| v.first())
+        .map(|reference| reference.url.as_str())
+        .unwrap_or_default();
+    let version = properties
+        .get(COMMIT_PROPERTY)
+        .cloned()
+        .or_else(|| component.hashes.as_ref().and_then(|h| h.first()).map(|h| h.content.clone()))
+        .unwrap_or_default();
+
+    let mut source_dir = PathBuf::from(cargo_root);
+    source_dir.push(CARGO_PATH_SUBFOLDER);
+    source_dir.push(format!(
+        "{}-{}",
+        component.name,
+        get_suffix_hash(&format!("{url}#{version}"), None)
+    ));
+    Ok(PathDependency { source_dir, path })
+}
+
+/// Path dependencies of the SBOM with the dirs they are fetched to
+pub fn path_dependencies(
+    sbom: &CycloneDXBom,
+    cargo_root: &Path,
+) -> anyhow::Result<Vec<PathDependency>> {
+    sbom.components
+        .iter()
+        .filter(|component| component.mime_type.as_deref() == Some(LIBRARY_TYPE))
+        .map(|component| path_dependency(cargo_root, component))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use anytree_sbom::{ExternalReference, Property};
+
+    use super::*;
+
+    fn component(path: &str, commit: &str) -> Component {
+        let property = |name: &str, value: &str| Property {
+            name: name.to_string(),
+            value: value.to_string(),
+            ..Default::default()
+        };
+        Component {
+            name: "sibling".to_string(),
+            mime_type: Some(LIBRARY_TYPE.to_string()),
+            properties: Some(vec![
+                property(PATH_PROPERTY, path),
+                property(COMMIT_PROPERTY, commit),
+            ]),
+            external_references: Some(vec![ExternalReference {
+                url: "https://example.com/sibling.git".to_string(),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_path_dependency() {
+        let root = Path::new("/cargo");
+        let dependency = path_dependency(root, &component("../sibling", "a1")).unwrap();
+        assert_eq!(Path::new("../sibling"), dependency.path);
+        assert_eq!(root.join(CARGO_PATH_SUBFOLDER), dependency.source_dir.parent().unwrap());
+        let name = dependency.source_dir.file_name().unwrap().to_string_lossy().into_owned();
+        assert!(name.starts_with("sibling-"), "{name}");
+
+        // another commit is another source dir
+        let other = path_dependency(root, &component("../sibling", "b2")).unwrap();
+        assert_ne!(dependency.source_dir, other.source_dir);
+
+        for path in ["/etc", "", "."] {
+            let err = path_dependency(root, &component(path, "a1")).unwrap_err();
+            assert!(err.to_string().contains("Path of component sibling"), "{path}: {err}");
+        }
+        let mut missing = component("../sibling", "a1");
+        missing.properties.as_mut().unwrap().retain(|p| p.name != PATH_PROPERTY);
+        assert!(path_dependency(root, &missing).is_err());
+    }
+}
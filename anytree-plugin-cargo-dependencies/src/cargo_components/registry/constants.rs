@@ -1,5 +1,7 @@
 pub const CARGO_REGISTRY_URL: &str = "sparse+https://index.crates.io/";
-pub const CARGO_REGISTRY_PREFIX: &str = "index.crates.io";
+pub const CARGO_GIT_REGISTRY_URL: &str = "https://github.com/rust-lang/crates.io-index";
+pub const SPARSE_PREFIX: &str = "sparse+";
+pub const GIT_REGISTRY_PREFIX: &str = "registry+";
 pub const CRATE_INDEX_URL: &str = "https://github.com/rust-lang/crates.io-index/raw/master/";
 
 pub const CARGO_REGISTRY_SUBFOLDER: &str = "registry";
@@ -16,3 +18,29 @@ pub const DEFAULT_INDEX_CONFIG: &str = "{
   \"api\": \"https://crates.io\"
 }";
 pub const CARGO_OK_CONTENT: &str = "ok";
+
+/// Algorithm of the `cksum` field of the index lines
+pub const INDEX_CHECKSUM_ALG: &str = "SHA-256";
+
+pub const CRATE_STORE_KIND: &str = "crate";
+pub const SRC_STORE_KIND: &str = "crate-src";
+
+pub const REGISTRY_URL_PROPERTY: &str = "registry_url";
+/// Commit of the git repo of the external reference with the `.crate` file
+pub const COMMIT_PROPERTY: &str = "commit";
+/// Index lines of the crate committed next to the mirrored `.crate` file. The
+/// mirror of a crate of an alternate registry also has its `config.json`.
+pub const MIRROR_INDEX_NAME: &str = "index";
+pub const REGISTRY_NAME_PROPERTY: &str = "registry";
+pub const PURL_REPOSITORY_URL_QUALIFIER: &str = "repository_url";
+
+pub const TARGET_PROPERTY: &str = "target";
+pub const CARGO_PROJECT_TARGET: &str = "cargo/project";
+pub const REGISTRY_PROTOCOL_PROPERTY: &str = "registry_protocol";
+pub const BASE_IMAGE_PROPERTY: &str = "base_image";
+/// Cargo 1.70 is the first one that uses sparse protocol for crates.io by
+/// default
+pub const FIRST_SPARSE_BY_DEFAULT_MINOR: u32 = 70;
+
+pub const GIT_INDEX_REF: &str = "refs/remotes/origin/HEAD";
+pub const GIT_INDEX_COMMIT_MESSAGE: &str = "Index pinned by anytree";
@@ -1,177 +1,602 @@
-mod constants;
+pub(super) mod constants;
+pub(super) mod source;
 
 use std::fs::File;
-use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
-use std::sync::Once;
 
-use anytree_sbom::Component;
+use anytree_sbom::{Component, CycloneDXBom, Hash};
+use anytree_utils::archive::{extract_tar_gz, ExtractOptions};
 use anytree_utils::crypto::hash::check_hashes;
+use anytree_utils::git;
 
-use crate::cargo_components::helper::SourceKind::SparseRegistry;
 use crate::cargo_components::helper::{
-    convert_index_to_cache, get_suffix_hash, name_to_index_path,
+    cache_to_index, convert_index_to_cache, get_component_properties, get_suffix_hash,
+    index_checksum, name_to_index_path, pinned_index_lines, store_key,
 };
 use crate::cargo_components::registry::constants::*;
+use crate::cargo_components::registry::source::{registries_config, CargoRegistry, IndexProtocol};
+use crate::loader::{DependencyLoader, LoadContext};
+use crate::store::{remove_entry, write_atomic};
 
-static INIT: Once = Once::new();
 pub const LIBRARY_TYPE: &str = "cargo/registry";
 
 pub struct CargoRegistryComponent {}
 
-fn init(cargo_root: impl AsRef<Path>) {
-    tracing::trace!("Init cargo registry dir");
-    // create default crates.io config
-    let mut index_path = PathBuf::from(cargo_root.as_ref());
+/// Prepares the index dir of `registry`, with the config from the mirror of
+/// `component` if it is mirrored
+fn init(
+    context: &LoadContext,
+    registry: &CargoRegistry,
+    component: &Component,
+) -> anyhow::Result<()> {
+    let mut index_path = PathBuf::from(context.deps_root);
     index_path.push(CARGO_REGISTRY_SUBFOLDER);
     index_path.push(CARGO_INDEX_SUBFOLDER);
-    index_path.push(get_registry_path());
-    index_path.push(CARGO_INDEX_CACHE_SUBFOLDER);
+    index_path.push(registry.dir_name());
+    let index_config_path = index_path.join(INDEX_CONFIG_NAME);
+    if index_config_path.exists() {
+        return Ok(());
+    }
+    tracing::trace!("Init cargo registry dir for {}", registry.index_url);
+    // git index is read by cargo from the repository and cached by itself
+    if registry.protocol == IndexProtocol::Sparse {
+        index_path.push(CARGO_INDEX_CACHE_SUBFOLDER);
+    }
     std::fs::create_dir_all(&index_path)
-        .expect("Failed to create directory for cargo crates registry");
-    let mut index_config_path = index_path.clone();
-    index_config_path.pop();
-    index_config_path.push(INDEX_CONFIG_NAME);
-    let mut config_file = File::create(index_config_path.to_str().unwrap())
-        .expect("Failed to create default cargo registry config");
-    config_file
-        .write_all(DEFAULT_INDEX_CONFIG.as_bytes())
-        .expect("Failed to write default cargo registry config");
+        .map_err(|e| anyhow::format_err!("Failed to create directory for cargo registry: {e}"))?;
+    let mirrored = match registry.is_crates_io() {
+        true => None,
+        false => read_mirrored(context, component, INDEX_CONFIG_NAME)?,
+    };
+    let config = match mirrored {
+        Some(config) => String::from_utf8(config)?,
+        None => registry.index_config(&context.http)?,
+    };
+    // create the registry config, its existence marks the dir as initialized
+    write_atomic(index_config_path, config)
+        .map_err(|e| anyhow::format_err!("Failed to write cargo registry config: {e}"))
 }
 
-fn get_registry_path() -> String {
-    format!(
-        "{}-{}",
-        CARGO_REGISTRY_PREFIX,
-        get_suffix_hash(CARGO_REGISTRY_URL, Some(SparseRegistry))
-    )
+impl DependencyLoader for CargoRegistryComponent {
+    fn mime_type(&self) -> &'static str {
+        LIBRARY_TYPE
+    }
+
+    fn prepare(&self, components: &[&Component], context: &LoadContext) -> anyhow::Result<()> {
+        let crates_io_protocol = IndexProtocol::for_crates_io(context.sbom)?;
+        let mut registries = vec![];
+        for component in components {
+            let registry = CargoRegistry::from_component(component, crates_io_protocol)?;
+            if !registries.contains(&registry) {
+                init(context, &registry, component)?;
+                registries.push(registry);
+            }
+        }
+        Ok(())
+    }
+
+    fn load(&self, component: &Component, context: &LoadContext) -> anyhow::Result<()> {
+        Self::save(context, component)
+    }
 }
 
 impl CargoRegistryComponent {
-    pub fn save(cargo_root: &Path, component: &Component) -> anyhow::Result<()> {
+    pub fn save(context: &LoadContext, component: &Component) -> anyhow::Result<()> {
+        let cargo_root = context.deps_root;
+        let sbom = context.sbom;
         let version = component
             .version
             .as_ref()
             .ok_or(anyhow::format_err!("Component {} does not contain version", component.name))?;
         tracing::info!("Loading cargo registry component {}.{}", component.name, version,);
-        INIT.call_once(|| init(cargo_root));
+        let registry =
+            CargoRegistry::from_component(component, IndexProtocol::for_crates_io(sbom)?)?;
+        // all versions of the crate share the index file
+        let _lock = context.locks.lock(format!("{}/{}", registry.dir_name(), component.name));
         let mut path = PathBuf::from(cargo_root);
         path.push(CARGO_REGISTRY_SUBFOLDER);
 
         let name = format!("{}-{}", component.name, version);
-        let url = &component
-            .external_references
-            .as_ref()
-            .and_then(|ext_refs| ext_refs.get(0))
-            .map(|reference| reference.url.as_str())
-            .ok_or(anyhow::format_err!(
-                "Component {} does not contain external references",
-                component.name
-            ))?;
+
+        // Prepare index file first, it has the checksum of the crate
+        let mut index_path = path.clone();
+        index_path.push(CARGO_INDEX_SUBFOLDER);
+        index_path.push(registry.dir_name());
+        if registry.protocol == IndexProtocol::Sparse {
+            index_path.push(CARGO_INDEX_CACHE_SUBFOLDER);
+        }
+        index_path.push(name_to_index_path(&component.name.to_string()));
+        let index = Self::load_index(context, &registry, component, &index_path)?;
+        let cksum = index_checksum(&index, version)?;
+        let has_hashes = component.hashes.as_ref().map_or(false, |hashes| !hashes.is_empty());
+        if cksum.is_none() && !has_hashes {
+            context.options.missing_checksum.apply(Err(anyhow::format_err!(
+                "Crate {name} can't be verified: neither SBOM nor the index has its checksum"
+            )))?;
+        }
 
         // load dependency as archive with specified commit
         let mut cache_path = path.clone();
         cache_path.push(CARGO_CACHE_SUBFOLDER);
-        cache_path.push(get_registry_path());
+        cache_path.push(registry.dir_name());
         std::fs::create_dir_all(&cache_path)?;
-        let cache_dir = cache_path.clone();
-        let cache_dir = cache_dir.to_str().unwrap();
-        let cache_name = format!("{name}.crate");
-        cache_path.push(&cache_name);
+        cache_path.push(format!("{name}.crate"));
+        let key = store_key(component, || match &cksum {
+            Some(cksum) => format!("sha-256-{cksum}"),
+            None => format!("{name}-{}", get_suffix_hash(&registry.index_url, None)),
+        });
+        let downloaded = context.fetch_entry(
+            CRATE_STORE_KIND,
+            &key,
+            &cache_path,
+            |path| Self::check_crate(component, cksum.as_deref(), path),
+            |staging| Self::download_crate(context, component, &name, staging),
+        )?;
 
-        if cache_path.exists() {
-            return Ok(());
+        // prepare dir for dependency source files
+        let mut src_dir = path.clone();
+        src_dir.push(CARGO_SRC_SUBFOLDER);
+        src_dir.push(registry.dir_name());
+        std::fs::create_dir_all(&src_dir)?;
+        src_dir.push(&name);
+        if downloaded {
+            // sources may come from the previous, broken archive
+            remove_entry(&src_dir)?;
         }
+        context.fetch_entry(
+            SRC_STORE_KIND,
+            &key,
+            &src_dir,
+            |path| {
+                // Cargo writes .cargo-ok last, so do we
+                if !path.join(CARGO_OK_FILE_NAME).exists() {
+                    anyhow::bail!("Sources of {name} are incomplete");
+                }
+                Ok(())
+            },
+            |staging| Self::extract_crate(&cache_path, &name, staging),
+        )?;
+        Ok(())
+    }
 
-        // Download crate as archive
-        tracing::trace!("Downloading crate as an archive. url: {}", &url);
-        let status = Command::new("curl")
-            .arg("-L")
-            .arg(url)
-            .arg("--output")
-            .arg(cache_name)
-            .current_dir(cache_dir)
-            .stderr(Stdio::piped())
-            .status()?;
-        if !status.success() {
-            anyhow::bail!("Failed to download archive: {}", url);
+    /// Index lines of the crate. The index file already prepared for the
+    /// component is reused, otherwise the index is downloaded and stored with
+    /// the versions pinned in the SBOM.
+    fn load_index(
+        context: &LoadContext,
+        registry: &CargoRegistry,
+        component: &Component,
+        index_path: &Path,
+    ) -> anyhow::Result<String> {
+        let version = component.version.as_deref().unwrap_or_default();
+        if index_path.exists() {
+            let index = std::fs::read(index_path)?;
+            let index = match registry.protocol {
+                IndexProtocol::Sparse => cache_to_index(&index),
+                IndexProtocol::Git => String::from_utf8(index).map_err(anyhow::Error::from),
+            };
+            match index {
+                Ok(index) if index_checksum(&index, version).is_ok() => return Ok(index),
+                Ok(_) => tracing::trace!("Index {:?} lacks version {version}", index_path),
+                Err(e) => {
+                    tracing::warn!("Index {:?} is broken, downloading it again: {e}", index_path)
+                }
+            }
         }
 
-        if let Some(hashes) = &component.hashes {
-            let data = std::fs::read(&cache_path)?;
-            tracing::info!("Check hash for {}", &name);
-            check_hashes(hashes, data)?;
+        let mut index_dir = index_path.to_path_buf();
+        index_dir.pop();
+        std::fs::create_dir_all(index_dir)?;
+        let index = match Self::mirrored_index(context, registry, component)? {
+            Some(index) => index,
+            None => {
+                let index_urls = registry.index_file_urls(&component.name);
+                // Download index file
+                tracing::trace!("Downloading the index. urls: {:?}", index_urls);
+                let urls = index_urls.iter().map(String::as_str).collect::<Vec<_>>();
+                context.http.get(&urls).map_err(|e| {
+                    anyhow::format_err!("Failed to download index of {}: {e}", component.name)
+                })?
+            }
+        };
+        let lines = String::from_utf8(index)?;
+        // keep only the versions of this crate that are pinned in the SBOM
+        let pinned_versions = pinned_versions(context.sbom, registry, &component.name)?;
+        match registry.protocol {
+            // convert index to cargo cache and save to file
+            IndexProtocol::Sparse => convert_index_to_cache(&lines, &pinned_versions, index_path),
+            // store plain index file, it is committed to the index repo later
+            IndexProtocol::Git => pinned_index_lines(&lines, &pinned_versions).and_then(|lines| {
+                let mut content =
+                    lines.into_iter().map(|(_, line)| line).collect::<Vec<_>>().join("\n");
+                content.push('\n');
+                write_atomic(index_path, content)
+            }),
         }
+        .map_err(|e| anyhow::format_err!("Failed to prepare index of {}: {e}", component.name))?;
+        Ok(lines)
+    }
 
-        // prepare dir for dependency source files
-        let mut src_path = path.clone();
-        src_path.push(CARGO_SRC_SUBFOLDER);
-        src_path.push(get_registry_path());
-        std::fs::create_dir_all(&src_path)?;
+    /// Index lines of the crate gathered from the mirrors of its versions
+    /// pinned in SBOM, `None` when `component` is not mirrored
+    fn mirrored_index(
+        context: &LoadContext,
+        registry: &CargoRegistry,
+        component: &Component,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        if !get_component_properties(component)?.contains_key(COMMIT_PROPERTY) {
+            return Ok(None);
+        }
+        let crates_io_protocol = IndexProtocol::for_crates_io(context.sbom)?;
+        let mut index = vec![];
+        for other in registry_components(context.sbom).filter(|c| c.name == component.name) {
+            if &CargoRegistry::from_component(other, crates_io_protocol)? != registry {
+                continue;
+            }
+            if let Some(lines) = read_mirrored(context, other, MIRROR_INDEX_NAME)? {
+                index.extend(lines);
+            }
+        }
+        Ok(Some(index))
+    }
+
+    fn download_crate(
+        context: &LoadContext,
+        component: &Component,
+        name: &str,
+        dest: &Path,
+    ) -> anyhow::Result<()> {
+        let urls = component
+            .external_references
+            .iter()
+            .flatten()
+            .map(|reference| reference.url.as_str())
+            .collect::<Vec<_>>();
+        if urls.is_empty() {
+            anyhow::bail!("Component {} does not contain external references", component.name);
+        }
+
+        if let Some(data) = read_mirrored(context, component, &format!("{name}.crate"))? {
+            return std::fs::write(dest, data)
+                .map_err(|e| anyhow::format_err!("Failed to write {:?}: {e}", dest));
+        }
+
+        // Download crate as archive
+        tracing::trace!("Downloading crate as an archive. urls: {:?}", &urls);
+        context
+            .http
+            .download(&urls, dest)
+            .map_err(|e| anyhow::format_err!("Failed to download crate {name}: {e}"))
+    }
+
+    /// Checks the crate against the hashes from SBOM and `cksum` from the index
+    fn check_crate(
+        component: &Component,
+        cksum: Option<&str>,
+        crate_path: &Path,
+    ) -> anyhow::Result<()> {
+        let data = std::fs::read(crate_path)?;
+        if let Some(hashes) = &component.hashes {
+            tracing::info!("Check hash for {:?}", crate_path);
+            check_hashes(hashes, &data)?;
+        }
+        if let Some(cksum) = cksum {
+            tracing::trace!("Check index checksum for {:?}", crate_path);
+            let index_hash = Hash {
+                alg: INDEX_CHECKSUM_ALG.to_string(),
+                content: cksum.to_string(),
+                ..Default::default()
+            };
+            check_hashes(&vec![index_hash], &data)
+                .map_err(|e| anyhow::format_err!("Crate doesn't match the index checksum: {e}"))?;
+        }
+        Ok(())
+    }
 
-        // tar -xzf itoa-1.0.8.crate -o itoa-1.0.8
-        // extract the source directory
+    /// Extracts sources of the crate `name` into `dest`
+    fn extract_crate(cache_path: &Path, name: &str, dest: &Path) -> anyhow::Result<()> {
+        // all files of the crate are in the `name` dir, extract its content
         tracing::trace!("Extracting the crate archive.");
-        let mut src_dir = src_path.clone();
-        src_dir.pop();
-        let status = Command::new("tar")
-            .arg("-xzf")
-            .arg(cache_path)
-            .arg("-o")
-            .arg(&name)
-            .current_dir(&src_path)
-            .status()?;
-        if !status.success() {
-            anyhow::bail!("Failed to compress sources: {}", status);
-        }
-
-        // Prepare index file
-        let mut index_path = path.clone();
-        index_path.push(CARGO_INDEX_SUBFOLDER);
-        index_path.push(get_registry_path());
-        index_path.push(CARGO_INDEX_CACHE_SUBFOLDER);
-        index_path.push(name_to_index_path(&component.name.to_string()));
-        let mut index_dir = index_path.clone();
-        index_dir.pop();
-        std::fs::create_dir_all(index_dir)?;
-        let indexed_name = name_to_index_path(&component.name);
-        let index_url = format!("{}{}", CRATE_INDEX_URL, indexed_name,);
-        // Download index file
-        tracing::trace!("Downloading the index. url: {}", index_url);
-        let status =
-            Command::new("curl").arg("-L").arg(&index_url).stderr(Stdio::piped()).output()?;
-        if !status.status.success() {
-            anyhow::bail!("Failed to download crate index: {}", index_url);
-        }
-        // let mut index_str = None;
-        let lines = std::str::from_utf8(status.stdout.as_slice())?;
-        // for line in lines.split('\n') {
-        //     if line.contains(version) {
-        //         index_str = Some(line.to_string());
-        //     }
-        // }
-        // let index_str = match index_str {
-        //     Some(s) => s,
-        //     None => {
-        //         anyhow::bail!("Failed to get index for specified version");
-        //     }
-        // };
-        // convert index to cargo cache and save to file
-        convert_index_to_cache(lines, index_path)?;
+        let options = ExtractOptions { prefix: Some(name), strip_components: 1 };
+        extract_tar_gz(File::open(cache_path)?, dest, options)
+            .map_err(|e| anyhow::format_err!("Failed to extract {name}: {e}"))?;
 
         // Cargo writes .cargo-ok file into src dir but mount in Dockerfile in read only
-        // so we create this file if it doesn't exist
-        let mut cargo_ok_path = src_path.clone();
-        cargo_ok_path.push(&name);
-        cargo_ok_path.push(CARGO_OK_FILE_NAME);
+        // so we create this file
+        let cargo_ok_path = dest.join(CARGO_OK_FILE_NAME);
+        tracing::trace!("Adding cargo-ok to {:?}.", cargo_ok_path);
+        std::fs::write(&cargo_ok_path, CARGO_OK_CONTENT)?;
+        Ok(())
+    }
+}
 
-        if !cargo_ok_path.exists() {
-            tracing::trace!("Adding cargo-ok to {:?}.", cargo_ok_path);
-            let mut file = File::create(&cargo_ok_path)?;
-            file.write_all(CARGO_OK_CONTENT.as_bytes())?;
+/// File at `path` of the git repo the crate is mirrored to, see
+/// `crate::mirror`. `None` when the component has no commit, so it is not
+/// mirrored.
+fn read_mirrored(
+    context: &LoadContext,
+    component: &Component,
+    path: &str,
+) -> anyhow::Result<Option<Vec<u8>>> {
+    let properties = get_component_properties(component)?;
+    let Some(commit) = properties.get(COMMIT_PROPERTY) else { return Ok(None) };
+    let url = component.external_references.iter().flatten().next().ok_or(anyhow::format_err!(
+        "Component {} does not contain external references",
+        component.name
+    ))?;
+    let options = context.options;
+    let url = options.rewrites.rewrite(&url.url);
+    tracing::trace!("Reading {} from {}#{}", path, url, commit);
+    git::read_file(&url, commit, path, &options.credentials).map(Some).map_err(|e| {
+        anyhow::format_err!("Failed to read {path} of {} from {url}: {e}", component.name)
+    })
+}
+
+/// All registry components of the SBOM
+fn registry_components(sbom: &CycloneDXBom) -> impl Iterator<Item = &Component> {
+    sbom.components.iter().filter(|component| component.mime_type.as_deref() == Some(LIBRARY_TYPE))
+}
+
+/// All versions of the registry crate `name` listed in the SBOM. A crate can be
+/// pinned in several versions at once, and all of them have to stay in the
+/// index because cargo resolves the whole lock file against it.
+fn pinned_versions<'a>(
+    sbom: &'a CycloneDXBom,
+    registry: &CargoRegistry,
+    name: &str,
+) -> anyhow::Result<Vec<&'a str>> {
+    let crates_io_protocol = IndexProtocol::for_crates_io(sbom)?;
+    let mut versions = vec![];
+    for component in registry_components(sbom).filter(|component| component.name == name) {
+        if &CargoRegistry::from_component(component, crates_io_protocol)? != registry {
+            continue;
+        }
+        if let Some(version) = component.version.as_deref() {
+            versions.push(version);
         }
+    }
+    Ok(versions)
+}
 
-        Ok(())
+/// Distinct registries used by the SBOM
+fn registries(sbom: &CycloneDXBom) -> anyhow::Result<Vec<CargoRegistry>> {
+    let crates_io_protocol = IndexProtocol::for_crates_io(sbom)?;
+    let mut registries = vec![];
+    for component in registry_components(sbom) {
+        let registry = CargoRegistry::from_component(component, crates_io_protocol)?;
+        if !registries.contains(&registry) {
+            registries.push(registry);
+        }
+    }
+    Ok(registries)
+}
+
+/// Finishes registries layout once all the components are saved:
+/// - commits git indexes so cargo can read them
+/// - writes cargo config with `[registries]` for every alternate registry used
+///   in the SBOM, so cargo in the container can resolve them. Nothing is
+///   written when all crates come from crates.io.
+pub fn finalize(sbom: &CycloneDXBom, cargo_root: &Path) -> anyhow::Result<()> {
+    let registries = registries(sbom)?;
+    for registry in registries.iter().filter(|registry| registry.protocol == IndexProtocol::Git) {
+        let mut index_path = PathBuf::from(cargo_root);
+        index_path.push(CARGO_REGISTRY_SUBFOLDER);
+        index_path.push(CARGO_INDEX_SUBFOLDER);
+        index_path.push(registry.dir_name());
+        if index_path.exists() {
+            commit_git_index(&index_path)?;
+        }
+    }
+
+    if registries.iter().all(CargoRegistry::is_crates_io) {
+        return Ok(());
+    }
+    let config_path = cargo_root.join(crate::CARGO_CONFIG_NAME);
+    tracing::trace!("Writing registries config: {:?}", config_path);
+    std::fs::write(config_path, registries_config(&registries))
+        .map_err(|e| anyhow::format_err!("Failed to write registries config: {e}"))
+}
+
+/// Turns the index dir into the minimal git checkout cargo expects: a repo
+/// with all the index files committed and `refs/remotes/origin/HEAD` pointing
+/// to that commit.
+fn commit_git_index(index_path: &Path) -> anyhow::Result<()> {
+    tracing::trace!("Committing git index: {:?}", index_path);
+    // cargo keeps its own cache inside of the index dir
+    git::commit_all(
+        index_path,
+        GIT_INDEX_COMMIT_MESSAGE,
+        &[CARGO_INDEX_CACHE_SUBFOLDER],
+        GIT_INDEX_REF,
+    )
+    .map_err(|e| anyhow::format_err!("Failed to prepare git index {:?}: {e}", index_path))?;
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+    use sha2::Digest;
+
+    use super::*;
+    use crate::{load_dependencies, LoadOptions, Policy, CARGO_CONFIG_NAME};
+
+    /// Serves each of `files` to a single request for its path
+    pub(crate) fn serve(files: Vec<(String, Vec<u8>)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::Builder::new()
+            .name("test-registry-server".to_string())
+            .spawn(move || {
+                for _ in 0..files.len() {
+                    let (mut stream, _) = listener.accept().unwrap();
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let path = line.split(' ').nth(1).unwrap_or_default().to_string();
+                    while reader.read_line(&mut line).unwrap() > 2 {
+                        line.clear();
+                    }
+                    let body = files.iter().find(|(name, _)| format!("/{name}") == path);
+                    let (status, body) = match body {
+                        Some((_, body)) => ("200 OK", body.as_slice()),
+                        None => ("404 Not Found", &[][..]),
+                    };
+                    let head = format!(
+                        "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        body.len()
+                    );
+                    stream.write_all(head.as_bytes()).unwrap();
+                    stream.write_all(body).unwrap();
+                }
+            })
+            .unwrap();
+        format!("http://{addr}/")
+    }
+
+    /// `.crate` file of `name` with just its manifest
+    pub(crate) fn crate_file(name: &str, version: &str) -> Vec<u8> {
+        let mut header = tar::Header::new_gnu();
+        let manifest = format!("[package]\nname = \"{name}\"\nversion = \"{version}\"\n");
+        header.set_size(manifest.len() as u64);
+        header.set_mode(0o644);
+        let mut tar = tar::Builder::new(flate2::write::GzEncoder::new(
+            vec![],
+            flate2::Compression::default(),
+        ));
+        tar.append_data(&mut header, format!("{name}-{version}/Cargo.toml"), manifest.as_bytes())
+            .unwrap();
+        tar.into_inner().unwrap().finish().unwrap()
+    }
+
+    fn index_line(name: &str, version: &str, cksum: Option<&str>) -> String {
+        let mut line = json!({ "name": name, "vers": version, "deps": [], "features": {} });
+        if let Some(cksum) = cksum {
+            line["cksum"] = json!(cksum);
+        }
+        format!("{line}\n")
+    }
+
+    fn sbom(components: Vec<Value>) -> CycloneDXBom {
+        let sbom = json!({
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.5",
+            "version": 1,
+            "components": components
+        });
+        CycloneDXBom::deserialize(&sbom).unwrap()
+    }
+
+    /// Sparse registry serving `data` as the crate `name` 0.1.0, whose index
+    /// line has `cksum`. Returns the crate component.
+    fn sparse_registry(name: &str, data: Vec<u8>, cksum: Option<&str>) -> Value {
+        let url = serve(vec![
+            (INDEX_CONFIG_NAME.to_string(), b"{\"dl\": \"https://example.invalid\"}".to_vec()),
+            (name_to_index_path(name), index_line(name, "0.1.0", cksum).into_bytes()),
+            (format!("{name}-0.1.0.crate"), data),
+        ]);
+        json!({
+            "type": "library",
+            "name": name,
+            "version": "0.1.0",
+            "mime-type": LIBRARY_TYPE,
+            "externalReferences": [
+                { "url": format!("{url}{name}-0.1.0.crate"), "type": "distribution" }
+            ],
+            "properties": [{ "name": REGISTRY_URL_PROPERTY, "value": url }]
+        })
+    }
+
+    #[test]
+    fn test_index_checksum() {
+        let data = crate_file("foo", "0.1.0");
+        let cksum = hex::encode(sha2::Sha256::digest(&data));
+        let wrong = hex::encode(sha2::Sha256::digest(b"other"));
+        let load = |cksum: Option<&str>, missing_checksum: Policy| {
+            let dir = tempfile::tempdir().unwrap();
+            let sbom = sbom(vec![sparse_registry("foo", data.clone(), cksum)]);
+            let options = LoadOptions { missing_checksum, ..Default::default() };
+            load_dependencies(&sbom, dir.path(), &options).map_err(|e| e.to_string())?;
+            let src = std::fs::read_dir(dir.path().join("registry/src")).unwrap().next().unwrap();
+            assert!(src.unwrap().path().join("foo-0.1.0/Cargo.toml").exists());
+            Ok::<_, String>(())
+        };
+
+        load(Some(&cksum), Policy::Require).unwrap();
+        // the policy is about the missing checksum, a wrong one always fails
+        for policy in [Policy::Require, Policy::Warn, Policy::Ignore] {
+            let err = load(Some(&wrong), policy).unwrap_err();
+            assert!(err.contains("doesn't match the index checksum"), "{err}");
+        }
+        let err = load(None, Policy::Require).unwrap_err();
+        assert!(err.contains("neither SBOM nor the index has its checksum"), "{err}");
+        load(None, Policy::Warn).unwrap();
+        load(None, Policy::Ignore).unwrap();
+    }
+
+    /// Crates.io crate loaded with the git protocol of rust < 1.70 from its
+    /// mirror, along with a crate of an alternate sparse registry
+    #[test]
+    fn test_git_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = crate_file("foo", "1.0.0");
+        let cksum = hex::encode(sha2::Sha256::digest(&data));
+        let mirror = dir.path().join("mirror");
+        std::fs::create_dir(&mirror).unwrap();
+        std::fs::write(mirror.join("foo-1.0.0.crate"), &data).unwrap();
+        let line = index_line("foo", "1.0.0", Some(&cksum));
+        std::fs::write(mirror.join(MIRROR_INDEX_NAME), &line).unwrap();
+        let commit = git::commit_all(&mirror, "mirror", &[], "refs/heads/main").unwrap();
+        let bar = crate_file("bar", "0.1.0");
+        let bar_cksum = hex::encode(sha2::Sha256::digest(&bar));
+        let sbom = sbom(vec![
+            json!({
+                "type": "application",
+                "name": "project",
+                "properties": [
+                    { "name": TARGET_PROPERTY, "value": CARGO_PROJECT_TARGET },
+                    { "name": BASE_IMAGE_PROPERTY, "value": "rust:1.69-slim" }
+                ]
+            }),
+            json!({
+                "type": "library",
+                "name": "foo",
+                "version": "1.0.0",
+                "mime-type": LIBRARY_TYPE,
+                "externalReferences": [{ "url": mirror.to_str().unwrap(), "type": "vcs" }],
+                "properties": [{ "name": COMMIT_PROPERTY, "value": commit }]
+            }),
+            sparse_registry("bar", bar, Some(&bar_cksum)),
+        ]);
+        let cargo_dir = dir.path().join("cargo");
+        load_dependencies(&sbom, &cargo_dir, &LoadOptions::default()).unwrap();
+
+        let registry = CargoRegistry::crates_io(IndexProtocol::Git);
+        let index_dir = cargo_dir.join("registry/index").join(registry.dir_name());
+        let git = |args: &[&str]| {
+            let output =
+                std::process::Command::new("git").arg("-C").arg(&index_dir).args(args).output();
+            let output = output.unwrap();
+            assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+            String::from_utf8(output.stdout).unwrap()
+        };
+        assert_eq!(line, git(&["show", &format!("{GIT_INDEX_REF}:3/f/foo")]));
+        assert!(git(&["show", &format!("{GIT_INDEX_REF}:{INDEX_CONFIG_NAME}")]).contains("dl"));
+        assert_eq!("", git(&["status", "--porcelain"]));
+        let src = cargo_dir.join("registry/src").join(registry.dir_name());
+        assert!(src.join("foo-1.0.0/Cargo.toml").exists());
+
+        let config = std::fs::read_to_string(cargo_dir.join(CARGO_CONFIG_NAME)).unwrap();
+        let bar_registry = CargoRegistry::from_component(
+            sbom.components.iter().find(|c| c.name == "bar").unwrap(),
+            IndexProtocol::Git,
+        )
+        .unwrap();
+        assert_eq!(registries_config([&bar_registry]), config);
+        assert!(config.starts_with("[registries.127-0-0-1]\nindex = \"sparse+http://127.0.0.1:"));
     }
 }
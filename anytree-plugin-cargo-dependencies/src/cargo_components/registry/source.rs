@@ -0,0 +1,300 @@
+use anytree_sbom::{Component, CycloneDXBom};
+use anytree_utils::http::HttpClient;
+
+use crate::cargo_components::helper::SourceKind::{Registry, SparseRegistry};
+use crate::cargo_components::helper::{
+    get_component_properties, get_suffix_hash, name_to_index_path, purl_qualifier,
+};
+use crate::cargo_components::registry::constants::*;
+
+/// Protocol cargo uses to read a registry index
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IndexProtocol {
+    /// `sparse+https://` index, default for crates.io since cargo 1.70
+    Sparse,
+    /// Git repository with the whole index, used by older toolchains
+    Git,
+}
+
+impl IndexProtocol {
+    /// Protocol the project toolchain uses for crates.io.
+    ///
+    /// It is taken from the `registry_protocol` property of the cargo project
+    /// component or derived from the version of its `rust` base image.
+    pub fn for_crates_io(sbom: &CycloneDXBom) -> anyhow::Result<Self> {
+        let Some(project) = sbom.components.iter().find(|component| {
+            component.properties.as_ref().map_or(false, |properties| {
+                properties.iter().any(|property| {
+                    property.name == TARGET_PROPERTY && property.value == CARGO_PROJECT_TARGET
+                })
+            })
+        }) else {
+            return Ok(Self::Sparse);
+        };
+        let properties = get_component_properties(project)?;
+        if let Some(protocol) = properties.get(REGISTRY_PROTOCOL_PROPERTY) {
+            return match protocol.as_str() {
+                "sparse" => Ok(Self::Sparse),
+                "git" => Ok(Self::Git),
+                other => anyhow::bail!("Unsupported registry protocol: {other}"),
+            };
+        }
+        match properties.get(BASE_IMAGE_PROPERTY).and_then(|image| rust_image_minor(image)) {
+            Some(minor) if minor < FIRST_SPARSE_BY_DEFAULT_MINOR => Ok(Self::Git),
+            _ => Ok(Self::Sparse),
+        }
+    }
+}
+
+/// Minor version of the rust toolchain in the official `rust:1.xx` image
+fn rust_image_minor(image: &str) -> Option<u32> {
+    let image = image.split('@').next()?;
+    let (repository, tag) = image.rsplit_once(':')?;
+    if repository != "rust" && !repository.ends_with("/rust") {
+        return None;
+    }
+    let mut parts = tag.split(|c: char| !c.is_ascii_digit());
+    let major: u32 = parts.next()?.parse().ok()?;
+    let minor: u32 = parts.next()?.parse().ok()?;
+    (major == 1).then_some(minor)
+}
+
+/// Registry a `cargo/registry` component is published to.
+///
+/// The registry is taken from the `registry_url` property or from the
+/// `repository_url` qualifier of the component purl. Components without
+/// either come from crates.io.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CargoRegistry {
+    /// Name of the registry in `[registries]`, `None` for crates.io
+    pub name: Option<String>,
+    /// Index url in the form cargo writes it to Cargo.lock
+    pub index_url: String,
+    pub protocol: IndexProtocol,
+}
+
+impl CargoRegistry {
+    pub fn crates_io(protocol: IndexProtocol) -> Self {
+        let index_url = match protocol {
+            IndexProtocol::Sparse => CARGO_REGISTRY_URL,
+            IndexProtocol::Git => CARGO_GIT_REGISTRY_URL,
+        };
+        Self { name: None, index_url: index_url.to_string(), protocol }
+    }
+
+    /// `crates_io_protocol` is used for components without explicit registry
+    pub fn from_component(
+        component: &Component,
+        crates_io_protocol: IndexProtocol,
+    ) -> anyhow::Result<Self> {
+        let properties = get_component_properties(component)?;
+        let url = properties.get(REGISTRY_URL_PROPERTY).cloned().or_else(|| {
+            component
+                .purl
+                .as_deref()
+                .and_then(|purl| purl_qualifier(purl, PURL_REPOSITORY_URL_QUALIFIER))
+        });
+        let Some(url) = url else {
+            return Ok(Self::crates_io(crates_io_protocol));
+        };
+        let url = url.trim_start_matches(GIT_REGISTRY_PREFIX);
+        if url.trim_end_matches('/') == CARGO_GIT_REGISTRY_URL {
+            return Ok(Self::crates_io(IndexProtocol::Git));
+        }
+
+        let index_url = if url.starts_with(SPARSE_PREFIX) {
+            url.to_string()
+        } else if url.starts_with("https://") || url.starts_with("http://") {
+            format!("{SPARSE_PREFIX}{url}")
+        } else {
+            anyhow::bail!(
+                "Component {} has unsupported registry url: {url}. Only sparse registries are \
+                 supported",
+                component.name
+            );
+        };
+        // cargo always refers to sparse index with the trailing slash
+        let index_url = if index_url.ends_with('/') { index_url } else { format!("{index_url}/") };
+
+        if index_url == CARGO_REGISTRY_URL {
+            return Ok(Self::crates_io(IndexProtocol::Sparse));
+        }
+
+        let name = match properties.get(REGISTRY_NAME_PROPERTY) {
+            Some(name) => name.clone(),
+            None => host(&index_url).replace('.', "-"),
+        };
+        Ok(Self { name: Some(name), index_url, protocol: IndexProtocol::Sparse })
+    }
+
+    pub fn is_crates_io(&self) -> bool {
+        self.name.is_none()
+    }
+
+    /// Directory name cargo uses for this registry inside `index`, `cache` and
+    /// `src`, e.g. `index.crates.io-6f17d22bba15001f`
+    pub fn dir_name(&self) -> String {
+        let kind = match self.protocol {
+            IndexProtocol::Sparse => SparseRegistry,
+            IndexProtocol::Git => Registry,
+        };
+        format!("{}-{}", host(&self.index_url), get_suffix_hash(&self.index_url, Some(kind)))
+    }
+
+    /// Urls of the index file of the crate, the first one is preferred and the
+    /// rest are mirrors
+    pub fn index_file_urls(&self, crate_name: &str) -> Vec<String> {
+        let indexed_name = name_to_index_path(&crate_name.to_lowercase());
+        if self.is_crates_io() {
+            vec![
+                format!("{}{}", CRATE_INDEX_URL, indexed_name),
+                format!("{}{}", CARGO_REGISTRY_URL.trim_start_matches(SPARSE_PREFIX), indexed_name),
+            ]
+        } else {
+            vec![format!("{}{}", self.http_url(), indexed_name)]
+        }
+    }
+
+    /// Content of the `config.json` stored in the registry index dir
+    pub fn index_config(&self, http: &HttpClient) -> anyhow::Result<String> {
+        if self.is_crates_io() {
+            return Ok(DEFAULT_INDEX_CONFIG.to_string());
+        }
+        let config_url = format!("{}{}", self.http_url(), INDEX_CONFIG_NAME);
+        tracing::trace!("Downloading the registry config. url: {}", config_url);
+        let config = http
+            .get(&[&config_url])
+            .map_err(|e| anyhow::format_err!("Failed to download registry config: {e}"))?;
+        Ok(String::from_utf8(config)?)
+    }
+
+    fn http_url(&self) -> &str {
+        self.index_url.trim_start_matches(SPARSE_PREFIX)
+    }
+}
+
+fn host(url: &str) -> &str {
+    let without_scheme = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    let authority = without_scheme.split('/').next().unwrap_or_default();
+    let host_port = authority.rsplit_once('@').map(|(_, host)| host).unwrap_or(authority);
+    host_port.split(':').next().unwrap_or_default()
+}
+
+/// `[registries]` section of the cargo config for all alternate registries
+pub fn registries_config<'a>(registries: impl IntoIterator<Item = &'a CargoRegistry>) -> String {
+    let mut config = String::new();
+    for registry in registries {
+        if let Some(name) = &registry.name {
+            config
+                .push_str(&format!("[registries.{name}]\nindex = \"{}\"\n\n", registry.index_url));
+        }
+    }
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use anytree_sbom::Property;
+
+    use super::*;
+
+    fn component(purl: Option<&str>, properties: Vec<(&str, &str)>) -> Component {
+        Component {
+            name: "internal_crate".to_string(),
+            version: Some("0.1.0".to_string()),
+            purl: purl.map(str::to_string),
+            properties: Some(
+                properties
+                    .into_iter()
+                    .map(|(name, value)| Property {
+                        name: name.to_string(),
+                        value: value.to_string(),
+                        ..Default::default()
+                    })
+                    .collect(),
+            ),
+            mime_type: Some(super::super::LIBRARY_TYPE.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_crates_io_by_default() {
+        let registry =
+            CargoRegistry::from_component(&component(None, vec![]), IndexProtocol::Sparse).unwrap();
+        assert!(registry.is_crates_io());
+        assert_eq!("index.crates.io-6f17d22bba15001f", registry.dir_name());
+
+        let registry =
+            CargoRegistry::from_component(&component(None, vec![]), IndexProtocol::Git).unwrap();
+        assert!(registry.is_crates_io());
+        assert_eq!("github.com-1ecc6299db9ec823", registry.dir_name());
+    }
+
+    #[test]
+    fn test_git_crates_io_from_purl() {
+        let registry = CargoRegistry::from_component(
+            &component(
+                Some(
+                    "pkg:cargo/internal_crate@0.1.0?repository_url=registry%2Bhttps%3A%2F%2Fgithub.\
+                     com%2Frust-lang%2Fcrates.io-index",
+                ),
+                vec![],
+            ),
+            IndexProtocol::Sparse,
+        )
+        .unwrap();
+        assert_eq!(CargoRegistry::crates_io(IndexProtocol::Git), registry);
+    }
+
+    #[test]
+    fn test_rust_image_minor() {
+        assert_eq!(Some(65), rust_image_minor("rust:1.65"));
+        assert_eq!(Some(69), rust_image_minor("docker.io/library/rust:1.69.0-slim@sha256:00"));
+        assert_eq!(None, rust_image_minor("rust@sha256:00"));
+        assert_eq!(None, rust_image_minor("ubuntu:22.04"));
+        assert_eq!(None, rust_image_minor("localhost:5000/rust"));
+    }
+
+    #[test]
+    fn test_registry_from_purl() {
+        let registry = CargoRegistry::from_component(
+            &component(
+                Some(
+                    "pkg:cargo/internal_crate@0.1.0?repository_url=sparse%2Bhttps%3A%2F%2Fcrates.\
+                     example.com%2Findex",
+                ),
+                vec![],
+            ),
+            IndexProtocol::Sparse,
+        )
+        .unwrap();
+        assert_eq!(Some("crates-example-com".to_string()), registry.name);
+        assert_eq!("sparse+https://crates.example.com/index/", registry.index_url);
+        assert!(registry.dir_name().starts_with("crates.example.com-"));
+        assert_eq!(
+            vec!["https://crates.example.com/index/in/te/internal_crate".to_string()],
+            registry.index_file_urls("internal_crate")
+        );
+    }
+
+    #[test]
+    fn test_registry_from_properties() {
+        let registry = CargoRegistry::from_component(
+            &component(
+                None,
+                vec![
+                    ("registry_url", "https://crates.example.com/index/"),
+                    ("registry", "internal"),
+                ],
+            ),
+            IndexProtocol::Sparse,
+        )
+        .unwrap();
+        assert_eq!(Some("internal".to_string()), registry.name);
+        assert_eq!(
+            "[registries.internal]\nindex = \"sparse+https://crates.example.com/index/\"\n\n",
+            registries_config([&registry, &CargoRegistry::crates_io(IndexProtocol::Sparse)])
+        );
+    }
+}
@@ -1,24 +1,224 @@
 mod cargo_components;
+pub mod loader;
+pub mod signature;
+pub mod store;
 
-use std::path::Path;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-use anytree_sbom::{ComponentType, CycloneDXBom};
+use anytree_sbom::CycloneDXBom;
+use anytree_utils::credentials::Credentials;
+use anytree_utils::http::{HttpClient, HttpConfig};
+use anytree_utils::rewrite::UrlRewrites;
 use anytree_utils::tracing::{increase_progress, start_progress};
+use parking_lot::Mutex;
 
-use crate::cargo_components::parse_component;
+use crate::cargo_components::finalize_registries;
+pub use crate::cargo_components::{
+    mirror, path_dependencies, MirrorOptions, PathDependency, COMMIT_PROPERTY, PATH_PROPERTY,
+};
+use crate::loader::{LoadContext, LoadErrors, Loaders, NamedLocks, UnknownComponentsError};
+use crate::store::Store;
 
-pub fn load_dependencies(sbom: &CycloneDXBom, cargo_dir: impl AsRef<Path>) -> anyhow::Result<()> {
-    // prepare project dependencies
-    let length = sbom.components.len();
-    let span = start_progress(length as u64);
-    let span_enter = span.enter();
-    for component in &sbom.components {
-        if component.component_type == ComponentType::Library {
-            parse_component(component, cargo_dir.as_ref())?;
-            increase_progress();
+/// Name of the cargo config written next to the dependencies when the SBOM
+/// uses alternate registries
+pub const CARGO_CONFIG_NAME: &str = "config.toml";
+
+/// Mime type of the library components describing the git submodules of the
+/// project, with the [`PATH_PROPERTY`] of the submodule in the project and its
+/// [`COMMIT_PROPERTY`]. They are checked out with the project, not loaded as
+/// dependencies.
+pub const SUBMODULE_TYPE: &str = "git/submodule";
+
+/// How to treat a check that fails or can't be made, e.g. a crate without a
+/// checksum
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Policy {
+    /// Fail loading
+    #[default]
+    Require,
+    /// Log a warning and go on
+    Warn,
+    /// Go on silently
+    Ignore,
+}
+
+impl Policy {
+    /// Turns the failed check into an error, a warning or nothing
+    pub fn apply(self, res: anyhow::Result<()>) -> anyhow::Result<()> {
+        match (self, res) {
+            (_, Ok(())) | (Self::Ignore, Err(_)) => Ok(()),
+            (Self::Require, Err(e)) => Err(e),
+            (Self::Warn, Err(e)) => {
+                tracing::warn!("{e:#}");
+                Ok(())
+            }
+        }
+    }
+}
+
+impl FromStr for Policy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "require" => Ok(Self::Require),
+            "warn" => Ok(Self::Warn),
+            "ignore" => Ok(Self::Ignore),
+            other => Err(format!("unknown policy {other}, expected require, warn or ignore")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LoadOptions {
+    /// Skip library components no loader is registered for instead of failing.
+    /// Useful for SBOMs that mix in informational components.
+    pub skip_unknown: bool,
+    /// Number of components loaded in parallel, `0` means number of CPUs
+    pub jobs: usize,
+    pub http: HttpConfig,
+    /// Credentials for the git remotes
+    pub credentials: Credentials,
+    /// Rewrite rules applied to every URL of the sources before fetching it
+    pub rewrites: UrlRewrites,
+    /// Root of the [`Store`] shared by the builds. Without it every
+    /// dependencies dir gets its own copy of the dependencies.
+    pub store: Option<PathBuf>,
+    /// Registry crates are checked against the hashes from SBOM and the
+    /// checksum from the index. This is what to do when there is neither.
+    pub missing_checksum: Policy,
+    /// What to do when a tag or a branch of a git dependency from SBOM does
+    /// not point at its pinned commit on the remote
+    pub ref_mismatch: Policy,
+    /// Signature policy of the git components that do not set their own,
+    /// see [`signature::check_signatures`]. Signatures are not checked when
+    /// neither sets one.
+    pub signatures: Option<Policy>,
+    /// Keyrings with the keys trusted to sign git commits and tags in addition
+    /// to the keys from SBOM, see [`TrustedKeys::add_keyring`]
+    ///
+    /// [`TrustedKeys::add_keyring`]: anytree_utils::crypto::signature::TrustedKeys::add_keyring
+    pub keyrings: Vec<PathBuf>,
+}
+
+pub fn load_dependencies(
+    sbom: &CycloneDXBom,
+    cargo_dir: impl AsRef<Path>,
+    options: &LoadOptions,
+) -> anyhow::Result<()> {
+    let mut loaders = Loaders::default();
+    cargo_components::register(&mut loaders);
+
+    let (components, unknown) = loaders.partition(sbom);
+    if !unknown.is_empty() {
+        if !options.skip_unknown {
+            return Err(UnknownComponentsError::new(unknown).into());
+        }
+        for component in unknown {
+            tracing::warn!(
+                "Skipping component {} of unsupported type: {}",
+                component.name,
+                component.mime_type.as_deref().unwrap_or("<no mime-type>")
+            );
         }
     }
-    drop(span_enter);
+
+    let store = options.store.as_ref().map(Store::new);
+    // keeps `cache gc` from removing the entries until they are recorded
+    let _store_lock = store.as_ref().map(Store::lock_shared).transpose()?;
+    let context = LoadContext {
+        sbom,
+        deps_root: cargo_dir.as_ref(),
+        options,
+        http: HttpClient::new(options.http.clone())?.with_rewrites(options.rewrites.clone()),
+        locks: NamedLocks::default(),
+        store,
+    };
+    loaders.prepare(&components, &context)?;
+
+    // prepare project dependencies
+    let jobs = match options.jobs {
+        0 => std::thread::available_parallelism().map(NonZeroUsize::get).unwrap_or(1),
+        jobs => jobs,
+    }
+    .min(components.len())
+    .max(1);
+    tracing::trace!("Loading {} components with {} workers", components.len(), jobs);
+    let span = start_progress(components.len() as u64);
+    let next = AtomicUsize::new(0);
+    let errors = Mutex::new(vec![]);
+    std::thread::scope(|s| -> anyhow::Result<()> {
+        for worker in 0..jobs {
+            std::thread::Builder::new().name(format!("anytree-loader-{worker}")).spawn_scoped(
+                s,
+                || {
+                    let _span_enter = span.enter();
+                    while let Some((component, loader)) =
+                        components.get(next.fetch_add(1, Ordering::Relaxed))
+                    {
+                        tracing::trace!("Component type: {}", loader.mime_type());
+                        if let Err(e) = loader.load(component, &context) {
+                            tracing::error!("Failed to load {}: {e:#}", component.name);
+                            errors.lock().push((
+                                component.name.clone(),
+                                component.version.clone(),
+                                e,
+                            ));
+                        }
+                        increase_progress();
+                    }
+                },
+            )?;
+        }
+        Ok(())
+    })?;
     drop(span);
+    if let Some(store) = &context.store {
+        // failed builds leave the links of the loaded components too
+        store.record_run(cargo_dir.as_ref())?;
+    }
+
+    let mut errors = errors.into_inner();
+    if !errors.is_empty() {
+        errors.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+        return Err(LoadErrors { errors }.into());
+    }
+    finalize_registries(sbom, cargo_dir.as_ref())?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use anytree_sbom::Component;
+
+    use super::*;
+
+    #[test]
+    fn test_all_failures_are_reported() {
+        // path components without `path` property fail before any fetching
+        let component = |name: &str| Component {
+            name: name.to_string(),
+            version: Some("0.1.0".to_string()),
+            mime_type: Some("cargo/path".to_string()),
+            ..Default::default()
+        };
+        let sbom = CycloneDXBom {
+            bom_format: "CycloneDX".to_string(),
+            spec_version: "1.5".to_string(),
+            version: 1,
+            components: vec![component("first"), component("second"), component("third")],
+            ..Default::default()
+        };
+        let dir = tempfile::tempdir().unwrap();
+
+        let err =
+            load_dependencies(&sbom, dir.path(), &LoadOptions { jobs: 2, ..Default::default() })
+                .unwrap_err();
+        let err = err.downcast::<LoadErrors>().unwrap();
+        let names = err.errors.iter().map(|(name, _, _)| name.as_str()).collect::<Vec<_>>();
+        assert_eq!(vec!["first", "second", "third"], names);
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use anytree_sbom::{Component, ComponentType, CycloneDXBom};
+use anytree_utils::http::HttpClient;
+use parking_lot::{ArcMutexGuard, Mutex, RawMutex};
+
+use crate::store::{link_tree, place, Store};
+use crate::{LoadOptions, SUBMODULE_TYPE};
+
+/// State shared by the loaders during one `load_dependencies` run
+pub struct LoadContext<'a> {
+    pub sbom: &'a CycloneDXBom,
+    /// Root of the dependencies dir, mounted as cargo home in the container
+    pub deps_root: &'a Path,
+    pub options: &'a LoadOptions,
+    pub http: HttpClient,
+    /// Components are loaded in parallel, loaders take these locks to avoid
+    /// writing the same files at once
+    pub locks: NamedLocks,
+    /// Store shared with other builds, `None` when entries are fetched right
+    /// into the dependencies dir
+    pub store: Option<Store>,
+}
+
+impl LoadContext<'_> {
+    /// Makes sure a verified entry is at `dest` inside the dependencies dir.
+    ///
+    /// With the store the entry is taken from it, or fetched into it first,
+    /// and linked to `dest`. `key` identifies the entry content within
+    /// `kind`, e.g. the hash of a crate. Returns whether `dest` was replaced.
+    pub fn fetch_entry(
+        &self,
+        kind: &str,
+        key: &str,
+        dest: &Path,
+        verify: impl Fn(&Path) -> anyhow::Result<()>,
+        fetch: impl FnOnce(&Path) -> anyhow::Result<()>,
+    ) -> anyhow::Result<bool> {
+        let Some(store) = &self.store else {
+            return place(dest, verify, fetch);
+        };
+        place(dest, &verify, |staging| {
+            let entry = store.entry(kind, key, &verify, fetch)?;
+            tracing::trace!("Linking {:?} from the store to {:?}", entry, dest);
+            link_tree(&entry, staging)
+        })
+    }
+}
+
+/// Loads SBOM components of one mime type into the dependencies dir
+pub trait DependencyLoader: Send + Sync {
+    /// Mime type of the components handled by the loader, e.g. `cargo/git`
+    fn mime_type(&self) -> &'static str;
+
+    /// Prepares shared state for the components of the loader. Called once
+    /// before any component is loaded, only if there are such components.
+    fn prepare(&self, _components: &[&Component], _context: &LoadContext) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Loads a single component. Can be called from several threads at once.
+    fn load(&self, component: &Component, context: &LoadContext) -> anyhow::Result<()>;
+}
+
+/// Mutexes created on demand by name
+#[derive(Default)]
+pub struct NamedLocks {
+    locks: Mutex<HashMap<String, Arc<Mutex<()>>>>,
+}
+
+impl NamedLocks {
+    pub fn lock(&self, name: impl Into<String>) -> ArcMutexGuard<RawMutex, ()> {
+        let lock = self.locks.lock().entry(name.into()).or_default().clone();
+        lock.lock_arc()
+    }
+}
+
+/// Dependency loaders keyed by mime type
+#[derive(Default)]
+pub struct Loaders {
+    loaders: HashMap<&'static str, Box<dyn DependencyLoader>>,
+}
+
+impl Loaders {
+    pub fn register(&mut self, loader: impl DependencyLoader + 'static) {
+        self.loaders.insert(loader.mime_type(), Box::new(loader));
+    }
+
+    pub fn get(&self, component: &Component) -> Option<&dyn DependencyLoader> {
+        component.mime_type.as_deref().and_then(|mime| self.loaders.get(mime)).map(Box::as_ref)
+    }
+
+    /// Splits library components of the SBOM into the ones that have a loader
+    /// and the unknown ones
+    pub fn partition<'a>(
+        &self,
+        sbom: &'a CycloneDXBom,
+    ) -> (Vec<(&'a Component, &dyn DependencyLoader)>, Vec<&'a Component>) {
+        let mut known = vec![];
+        let mut unknown = vec![];
+        for component in &sbom.components {
+            if component.component_type != ComponentType::Library
+                || component.mime_type.as_deref() == Some(SUBMODULE_TYPE)
+            {
+                continue;
+            }
+            match self.get(component) {
+                Some(loader) => known.push((component, loader)),
+                None => unknown.push(component),
+            }
+        }
+        (known, unknown)
+    }
+
+    /// Calls [`DependencyLoader::prepare`] of every loader that has components
+    pub fn prepare(
+        &self,
+        components: &[(&Component, &dyn DependencyLoader)],
+        context: &LoadContext,
+    ) -> anyhow::Result<()> {
+        for (mime_type, loader) in &self.loaders {
+            let loader_components = components
+                .iter()
+                .filter(|(component, _)| component.mime_type.as_deref() == Some(*mime_type))
+                .map(|(component, _)| *component)
+                .collect::<Vec<_>>();
+            if !loader_components.is_empty() {
+                loader.prepare(&loader_components, context)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// SBOM contains library components none of the loaders can handle
+#[derive(Debug)]
+pub struct UnknownComponentsError {
+    /// `(name, version, mime type)` of the offending components
+    pub components: Vec<(String, Option<String>, Option<String>)>,
+}
+
+impl UnknownComponentsError {
+    pub fn new<'a>(components: impl IntoIterator<Item = &'a Component>) -> Self {
+        Self {
+            components: components
+                .into_iter()
+                .map(|component| {
+                    (component.name.clone(), component.version.clone(), component.mime_type.clone())
+                })
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for UnknownComponentsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Unsupported dependency types in {} component(s) (use --skip-unknown to ignore them):",
+            self.components.len()
+        )?;
+        for (name, version, mime_type) in &self.components {
+            write!(f, "  {name}")?;
+            if let Some(version) = version {
+                write!(f, "@{version}")?;
+            }
+            writeln!(f, ": {}", mime_type.as_deref().unwrap_or("<no mime-type>"))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for UnknownComponentsError {}
+
+/// Some of the components failed to load
+#[derive(Debug)]
+pub struct LoadErrors {
+    /// `(name, version, error)` of the failed components
+    pub errors: Vec<(String, Option<String>, anyhow::Error)>,
+}
+
+impl fmt::Display for LoadErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Failed to load {} dependencies:", self.errors.len())?;
+        for (name, version, error) in &self.errors {
+            write!(f, "  {name}")?;
+            if let Some(version) = version {
+                write!(f, "@{version}")?;
+            }
+            writeln!(f, ": {error:#}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for LoadErrors {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestLoader;
+
+    impl DependencyLoader for TestLoader {
+        fn mime_type(&self) -> &'static str {
+            "test/known"
+        }
+
+        fn load(&self, _: &Component, _: &LoadContext) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn component(name: &str, mime_type: Option<&str>) -> Component {
+        Component {
+            name: name.to_string(),
+            version: Some("1.0.0".to_string()),
+            mime_type: mime_type.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_unknown_components() {
+        let mut loaders = Loaders::default();
+        loaders.register(TestLoader);
+        let sbom = CycloneDXBom {
+            bom_format: "CycloneDX".to_string(),
+            spec_version: "1.5".to_string(),
+            version: 1,
+            components: vec![
+                component("known", Some("test/known")),
+                component("npm", Some("npm/registry")),
+                component("info", None),
+            ],
+            ..Default::default()
+        };
+
+        let (known, unknown) = loaders.partition(&sbom);
+        assert_eq!(1, known.len());
+        assert_eq!("known", known[0].0.name);
+
+        let err = UnknownComponentsError::new(unknown);
+        let message = err.to_string();
+        assert!(message.contains("npm@1.0.0: npm/registry"));
+        assert!(message.contains("info@1.0.0: <no mime-type>"));
+    }
+}
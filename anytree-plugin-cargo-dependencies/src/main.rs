@@ -1,7 +1,7 @@
 use std::fs::File;
 use std::process::Command;
 
-use anytree_plugin_cargo_dependencies::load_dependencies;
+use anytree_plugin_cargo_dependencies::{load_dependencies, LoadOptions};
 use anytree_sbom::CycloneDXBom;
 
 fn main() -> anyhow::Result<()> {
@@ -12,6 +12,6 @@ fn main() -> anyhow::Result<()> {
 
     let path = "/tmp/anytree-test-project/.gosh/cargo";
     Command::new("rm").arg("-rf").arg(path).status()?;
-    load_dependencies(&sbom, path)?;
+    load_dependencies(&sbom, path, &LoadOptions::default())?;
     Ok(())
 }
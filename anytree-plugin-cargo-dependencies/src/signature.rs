@@ -0,0 +1,208 @@
+//! Signatures of the git commits and tags SBOM components are pinned to
+
+use std::path::Path;
+use std::str::FromStr;
+
+use anytree_sbom::Component;
+use anytree_utils::crypto::signature::TrustedKeys;
+use anytree_utils::git;
+
+use crate::cargo_components::TAG_PROPERTY;
+use crate::{LoadOptions, Policy};
+
+/// Component property overriding [`LoadOptions::signatures`]: require, warn
+/// or ignore
+pub const SIGNATURE_POLICY_PROPERTY: &str = "signature_policy";
+/// Component property with a key trusted to sign it, an armored OpenPGP
+/// public key or an SSH public key. May be repeated.
+pub const SIGNING_KEY_PROPERTY: &str = "signing_key";
+
+/// Checks that `commit` of `repo` fetched from `url` from SBOM, or the tag of
+/// the component pointing to it, is signed by a key from the component or the
+/// keyrings of `options`, following the signature policy of the component
+pub fn check_signatures(
+    component: &Component,
+    repo: &Path,
+    url: &str,
+    commit: &str,
+    options: &LoadOptions,
+) -> anyhow::Result<()> {
+    let properties = component.properties.as_deref().unwrap_or_default();
+    let policy = match properties.iter().find(|p| p.name == SIGNATURE_POLICY_PROPERTY) {
+        Some(property) => Policy::from_str(&property.value).map_err(|e| {
+            anyhow::format_err!("Wrong signature policy of {}: {e}", component.name)
+        })?,
+        None => options.signatures.unwrap_or(Policy::Ignore),
+    };
+    if policy == Policy::Ignore {
+        return Ok(());
+    }
+    let tag = properties.iter().find(|p| p.name == TAG_PROPERTY).map(|p| p.value.as_str());
+    policy.apply(verify(component, repo, url, commit, tag, options))
+}
+
+fn verify(
+    component: &Component,
+    repo: &Path,
+    url: &str,
+    commit: &str,
+    tag: Option<&str>,
+    options: &LoadOptions,
+) -> anyhow::Result<()> {
+    let mut keys = TrustedKeys::default();
+    for property in component.properties.iter().flatten() {
+        if property.name == SIGNING_KEY_PROPERTY {
+            keys.add(&property.value)
+                .map_err(|e| anyhow::format_err!("Wrong signing key of {}: {e}", component.name))?;
+        }
+    }
+    for keyring in &options.keyrings {
+        keys.add_keyring(keyring)?;
+    }
+    if keys.is_empty() {
+        anyhow::bail!("No trusted keys to check the signature of {}", component.name);
+    }
+
+    let commit_error = match git::commit_signature(repo, commit)? {
+        Some((signature, data)) => match keys.verify(&signature, &data) {
+            Ok(signer) => {
+                tracing::info!("Commit {commit} of {} is signed by {signer}", component.name);
+                return Ok(());
+            }
+            Err(e) => format!("commit {commit}: {e}"),
+        },
+        None => format!("commit {commit} is not signed"),
+    };
+    let Some(tag) = tag else {
+        anyhow::bail!("Failed to verify the signature of {}: {commit_error}", component.name);
+    };
+    let url = options.rewrites.rewrite(url);
+    let tag_error = match git::tag_signature(repo, &url, tag, commit, &options.credentials)? {
+        Some((signature, data)) => match keys.verify(&signature, &data) {
+            Ok(signer) => {
+                tracing::info!("Tag {tag} of {} is signed by {signer}", component.name);
+                return Ok(());
+            }
+            Err(e) => format!("tag {tag}: {e}"),
+        },
+        None => format!("tag {tag} is not signed"),
+    };
+    anyhow::bail!(
+        "Failed to verify the signature of {}: {commit_error}, {tag_error}",
+        component.name
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use anytree_sbom::Property;
+
+    use super::*;
+
+    /// Repo with a signed commit and an unsigned one on top of it, both tagged
+    /// with signed tags, `v0` and `v1`
+    struct SignedRepo {
+        dir: tempfile::TempDir,
+        signed: String,
+        unsigned: String,
+        public_key: String,
+    }
+
+    impl SignedRepo {
+        fn new() -> Self {
+            let dir = tempfile::tempdir().unwrap();
+            let key = dir.path().join("key");
+            let status = Command::new("ssh-keygen")
+                .args(["-q", "-t", "ed25519", "-N", "", "-f"])
+                .arg(&key)
+                .status()
+                .unwrap();
+            assert!(status.success());
+            let git = |args: &[&str]| {
+                let output = Command::new("git")
+                    .arg("-C")
+                    .arg(dir.path())
+                    .args(["-c", "user.name=test", "-c", "user.email=test@localhost"])
+                    .args(["-c", "gpg.format=ssh", "-c"])
+                    .arg(format!("user.signingkey={}", key.display()))
+                    .args(args)
+                    .output()
+                    .unwrap();
+                assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+                String::from_utf8(output.stdout).unwrap().trim().to_string()
+            };
+            git(&["init", "-q"]);
+            git(&["commit", "--allow-empty", "-S", "-m", "signed"]);
+            git(&["tag", "-s", "v0", "-m", "v0"]);
+            let signed = git(&["rev-parse", "HEAD"]);
+            git(&["commit", "--allow-empty", "-m", "unsigned"]);
+            git(&["tag", "-s", "v1", "-m", "v1"]);
+            let unsigned = git(&["rev-parse", "HEAD"]);
+            let public_key = std::fs::read_to_string(key.with_extension("pub")).unwrap();
+            Self { dir, signed, unsigned, public_key }
+        }
+
+        fn check(
+            &self,
+            commit: &str,
+            properties: &[(&str, &str)],
+            options: &LoadOptions,
+        ) -> anyhow::Result<()> {
+            let properties = properties
+                .iter()
+                .map(|(name, value)| Property {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                    ..Default::default()
+                })
+                .collect();
+            let component = Component {
+                name: "signed".to_string(),
+                properties: Some(properties),
+                ..Default::default()
+            };
+            let url = format!("file://{}", self.dir.path().display());
+            check_signatures(&component, self.dir.path(), &url, commit, options)
+        }
+    }
+
+    #[test]
+    fn test_signature_policies() {
+        let repo = SignedRepo::new();
+        let key = repo.public_key.as_str();
+        let default = LoadOptions::default();
+        let require = LoadOptions { signatures: Some(Policy::Require), ..Default::default() };
+
+        // not checked unless a policy asks for it
+        repo.check(&repo.unsigned, &[], &default).unwrap();
+        let err = repo.check(&repo.unsigned, &[], &require).unwrap_err();
+        assert!(err.to_string().contains("No trusted keys"), "{err}");
+
+        let signed_by = [(SIGNING_KEY_PROPERTY, key)];
+        repo.check(&repo.signed, &signed_by, &require).unwrap();
+        repo.check(&repo.signed[..10], &signed_by, &require).unwrap();
+        let err = repo.check(&repo.unsigned, &signed_by, &require).unwrap_err();
+        assert!(err.to_string().contains("is not signed"), "{err}");
+        let warn = [(SIGNING_KEY_PROPERTY, key), (SIGNATURE_POLICY_PROPERTY, "warn")];
+        repo.check(&repo.unsigned, &warn, &require).unwrap();
+        let ignore = [(SIGNATURE_POLICY_PROPERTY, "ignore")];
+        repo.check(&repo.unsigned, &ignore, &require).unwrap();
+        let wrong = [(SIGNATURE_POLICY_PROPERTY, "always")];
+        let err = repo.check(&repo.signed, &wrong, &require).unwrap_err();
+        assert!(err.to_string().contains("Wrong signature policy"), "{err}");
+
+        // the signed tag vouches for the unsigned commit
+        let tagged = [(SIGNING_KEY_PROPERTY, key), (TAG_PROPERTY, "v1")];
+        repo.check(&repo.unsigned, &tagged, &require).unwrap();
+        let wrong_tag = [(SIGNING_KEY_PROPERTY, key), (TAG_PROPERTY, "v0")];
+        let err = repo.check(&repo.unsigned, &wrong_tag, &require).unwrap_err();
+        assert!(err.to_string().contains("does not point to the pinned commit"), "{err}");
+
+        let keyring = repo.dir.path().join("allowed_signers");
+        std::fs::write(&keyring, format!("test@localhost {key}")).unwrap();
+        let options = LoadOptions { keyrings: vec![keyring], ..require };
+        repo.check(&repo.signed, &[], &options).unwrap();
+    }
+}
@@ -0,0 +1,472 @@
+//! Dependency store shared by all the builds of the user.
+//!
+//! Entries are fetched and verified once, then linked into the dependencies
+//! dir of every run that needs them. Entries are keyed by the content hash
+//! from SBOM when there is one, or by the source url and commit otherwise.
+//!
+//! Layout: `<root>/<kind>/<key>` for entries, `<root>/.locks/<kind>/<key>`
+//! for the lock files that let concurrent anytree processes populate the
+//! store and `<root>/.runs/<id>.json` for the [`RunManifest`]s telling which
+//! entries every dependencies dir uses.
+//!
+//! Builds hold the store lock shared while they use the store, cleanups take
+//! it exclusively, see [`Store::lock_shared`].
+
+use std::collections::{BTreeSet, HashSet};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use fs4::FileExt;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Extension of the sibling path an entry is prepared at before it is moved
+/// into place
+const STAGING_EXTENSION: &str = "tmp";
+const LOCKS_SUBFOLDER: &str = ".locks";
+const RUNS_SUBFOLDER: &str = ".runs";
+/// Lock of the whole store, in the locks subfolder
+const STORE_LOCK_NAME: &str = "store";
+
+pub struct Store {
+    root: PathBuf,
+    /// `<kind>/<key>` of the entries taken by this process, see
+    /// [`Store::record_run`]
+    used: Mutex<BTreeSet<String>>,
+}
+
+/// Store entries linked into a dependencies dir. Entries may be reflinked or
+/// copied, so the links themselves can't tell whether an entry is in use.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RunManifest {
+    pub dir: PathBuf,
+    /// `<kind>/<key>` of the entries
+    pub entries: BTreeSet<String>,
+}
+
+/// Entry of the store, see [`Store::entries`]
+#[derive(Debug)]
+pub struct StoreEntry {
+    pub kind: String,
+    pub key: String,
+    pub path: PathBuf,
+}
+
+impl StoreEntry {
+    fn id(&self) -> String {
+        format!("{}/{}", self.kind, self.key)
+    }
+}
+
+impl Store {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into(), used: Default::default() }
+    }
+
+    /// `~/.cache/anytree/store` or `.cache/anytree/store` if there is no user
+    /// cache dir
+    pub fn default_root() -> PathBuf {
+        dirs::cache_dir().unwrap_or_else(|| PathBuf::from(".cache")).join("anytree").join("store")
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// All committed entries, staging leftovers are not included
+    pub fn entries(&self) -> anyhow::Result<Vec<StoreEntry>> {
+        let mut entries = vec![];
+        if !self.root.exists() {
+            return Ok(entries);
+        }
+        for kind in std::fs::read_dir(&self.root)? {
+            let kind = kind?;
+            let kind_name = kind.file_name().to_string_lossy().into_owned();
+            if kind_name.starts_with('.') || !kind.file_type()?.is_dir() {
+                continue;
+            }
+            for entry in std::fs::read_dir(kind.path())? {
+                let entry = entry?;
+                let key = entry.file_name().to_string_lossy().into_owned();
+                if entry.path().extension().map_or(false, |ext| ext == STAGING_EXTENSION) {
+                    continue;
+                }
+                entries.push(StoreEntry { kind: kind_name.clone(), key, path: entry.path() });
+            }
+        }
+        entries.sort_by(|a, b| (&a.kind, &a.key).cmp(&(&b.kind, &b.key)));
+        Ok(entries)
+    }
+
+    /// Removes the entry once no other process is fetching it
+    pub fn remove(&self, entry: &StoreEntry) -> anyhow::Result<()> {
+        let _lock = self.lock(&entry.kind, &entry.key)?;
+        remove_entry(&entry.path)
+    }
+
+    fn lock(&self, kind: &str, key: &str) -> anyhow::Result<File> {
+        let lock = self.lock_file(&Path::new(kind).join(key))?;
+        lock.lock_exclusive()
+            .map_err(|e| anyhow::format_err!("Failed to lock {kind}/{key}: {e}"))?;
+        // the lock is released when the file is closed
+        Ok(lock)
+    }
+
+    fn lock_file(&self, name: &Path) -> anyhow::Result<File> {
+        let lock_path = self.root.join(LOCKS_SUBFOLDER).join(name);
+        std::fs::create_dir_all(lock_path.parent().unwrap_or(&self.root))?;
+        File::create(&lock_path)
+            .map_err(|e| anyhow::format_err!("Failed to create lock {:?}: {e}", lock_path))
+    }
+
+    /// Locks the store for a build, entries are not removed until the lock is
+    /// dropped
+    pub fn lock_shared(&self) -> anyhow::Result<File> {
+        let lock = self.lock_file(Path::new(STORE_LOCK_NAME))?;
+        FileExt::lock_shared(&lock)
+            .map_err(|e| anyhow::format_err!("Failed to lock the store: {e}"))?;
+        Ok(lock)
+    }
+
+    /// Locks the store for a cleanup, waits for the running builds
+    pub fn lock_exclusive(&self) -> anyhow::Result<File> {
+        let lock = self.lock_file(Path::new(STORE_LOCK_NAME))?;
+        if lock.try_lock_exclusive().is_err() {
+            tracing::info!("Waiting for the running builds to release the store");
+            lock.lock_exclusive()
+                .map_err(|e| anyhow::format_err!("Failed to lock the store: {e}"))?;
+        }
+        Ok(lock)
+    }
+
+    /// Adds the entries taken so far to the manifest of the dependencies dir
+    /// `dir`. Entries of the previous builds in the dir are kept, their links
+    /// stay there too.
+    pub fn record_run(&self, dir: &Path) -> anyhow::Result<()> {
+        let dir = dir.canonicalize()?;
+        let path = self.manifest_path(&dir);
+        let mut manifest = read_manifest(&path).unwrap_or_default();
+        manifest.dir = dir;
+        manifest.entries.extend(self.used.lock().iter().cloned());
+        std::fs::create_dir_all(self.root.join(RUNS_SUBFOLDER))?;
+        write_atomic(&path, serde_json::to_vec_pretty(&manifest)?)
+    }
+
+    fn manifest_path(&self, dir: &Path) -> PathBuf {
+        let id = hex::encode(Sha256::digest(dir.to_string_lossy().as_bytes()));
+        self.root.join(RUNS_SUBFOLDER).join(id).with_extension("json")
+    }
+
+    /// Entries no existing dependencies dir uses, the dirs `is_removed` tells
+    /// about count as removed. Take [`Store::lock_exclusive`] first.
+    pub fn unused_entries(
+        &self,
+        is_removed: impl Fn(&Path) -> bool,
+    ) -> anyhow::Result<Vec<StoreEntry>> {
+        let mut used = HashSet::new();
+        for (_, manifest) in self.manifests()? {
+            if manifest.dir.exists() && !is_removed(&manifest.dir) {
+                used.extend(manifest.entries);
+            }
+        }
+        Ok(self.entries()?.into_iter().filter(|entry| !used.contains(&entry.id())).collect())
+    }
+
+    /// Removes the manifests of the dependencies dirs that no longer exist.
+    /// Take [`Store::lock_exclusive`] first.
+    pub fn remove_stale_manifests(&self) -> anyhow::Result<()> {
+        for (path, manifest) in self.manifests()? {
+            if !manifest.dir.exists() {
+                tracing::trace!("Dropping manifest of removed {:?}", manifest.dir);
+                remove_entry(&path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Paths and contents of the run manifests
+    fn manifests(&self) -> anyhow::Result<Vec<(PathBuf, RunManifest)>> {
+        let runs = self.root.join(RUNS_SUBFOLDER);
+        if !runs.exists() {
+            return Ok(vec![]);
+        }
+        let mut manifests = vec![];
+        for manifest in std::fs::read_dir(runs)? {
+            let path = manifest?.path();
+            if path.extension().map_or(false, |ext| ext == STAGING_EXTENSION) {
+                continue;
+            }
+            let Some(manifest) = read_manifest(&path) else {
+                anyhow::bail!("Failed to read run manifest {:?}", path);
+            };
+            manifests.push((path, manifest));
+        }
+        Ok(manifests)
+    }
+
+    /// Path of the entry, fetched with `fetch` unless the store already has it
+    /// and it passes `verify`
+    pub fn entry(
+        &self,
+        kind: &str,
+        key: &str,
+        verify: impl Fn(&Path) -> anyhow::Result<()>,
+        fetch: impl FnOnce(&Path) -> anyhow::Result<()>,
+    ) -> anyhow::Result<PathBuf> {
+        let entry = self.root.join(kind).join(key);
+        std::fs::create_dir_all(self.root.join(kind))?;
+        tracing::trace!("Locking store entry {:?}", entry);
+        let _lock = self.lock(kind, key)?;
+        self.used.lock().insert(format!("{kind}/{key}"));
+        if place(&entry, verify, fetch)? {
+            // entry files are shared by the run dirs, so they must not be
+            // changed in place
+            set_read_only(&entry)?;
+        }
+        Ok(entry)
+    }
+}
+
+/// Makes sure a verified entry is at `dest`.
+///
+/// An existing entry is reused if it passes `verify`. Otherwise the entry is
+/// fetched to the staging path, verified and only then moved to `dest`, so an
+/// interrupted fetch never leaves a partial entry behind. Returns whether the
+/// entry was fetched.
+pub(crate) fn place(
+    dest: &Path,
+    verify: impl Fn(&Path) -> anyhow::Result<()>,
+    fetch: impl FnOnce(&Path) -> anyhow::Result<()>,
+) -> anyhow::Result<bool> {
+    if std::fs::symlink_metadata(dest).is_ok() {
+        match verify(dest) {
+            Ok(()) => return Ok(false),
+            Err(e) => tracing::warn!("{:?} is broken, fetching it again: {e:#}", dest),
+        }
+    }
+    let staging = staging_path(dest);
+    remove_entry(&staging)?;
+    let res = fetch(&staging).and_then(|()| verify(&staging));
+    if let Err(e) = res {
+        remove_entry(&staging)?;
+        return Err(e);
+    }
+    commit_entry(&staging, dest)?;
+    Ok(true)
+}
+
+/// Links a file or a dir tree from the store to `dest`. Files are reflinked
+/// where the filesystem supports it, hard linked otherwise and copied when the
+/// store is on another device.
+pub(crate) fn link_tree(src: &Path, dest: &Path) -> anyhow::Result<()> {
+    let metadata = std::fs::symlink_metadata(src)?;
+    if metadata.is_dir() {
+        std::fs::create_dir_all(dest)?;
+        for entry in std::fs::read_dir(src)? {
+            let entry = entry?;
+            link_tree(&entry.path(), &dest.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else if metadata.file_type().is_symlink() {
+        link_symlink(src, dest)
+    } else {
+        reflink_copy::reflink(src, dest)
+            .or_else(|_| std::fs::hard_link(src, dest))
+            .or_else(|_| std::fs::copy(src, dest).map(|_| ()))
+            .map_err(|e| anyhow::format_err!("Failed to link {:?} to {:?}: {e}", src, dest))
+    }
+}
+
+/// Apparent size of the file or the dir tree, `0` if it doesn't exist
+pub fn disk_usage(path: &Path) -> anyhow::Result<u64> {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return Ok(0);
+    };
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+    let mut size = 0;
+    for entry in std::fs::read_dir(path)? {
+        size += disk_usage(&entry?.path())?;
+    }
+    Ok(size)
+}
+
+fn read_manifest(path: &Path) -> Option<RunManifest> {
+    serde_json::from_slice(&std::fs::read(path).ok()?).ok()
+}
+
+fn set_read_only(path: &Path) -> anyhow::Result<()> {
+    let metadata = std::fs::symlink_metadata(path)?;
+    if metadata.is_dir() {
+        for entry in std::fs::read_dir(path)? {
+            set_read_only(&entry?.path())?;
+        }
+    } else if metadata.is_file() {
+        let mut permissions = metadata.permissions();
+        permissions.set_readonly(true);
+        std::fs::set_permissions(path, permissions)?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn link_symlink(src: &Path, dest: &Path) -> anyhow::Result<()> {
+    std::os::unix::fs::symlink(std::fs::read_link(src)?, dest)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn link_symlink(src: &Path, dest: &Path) -> anyhow::Result<()> {
+    std::fs::copy(src, dest)?;
+    Ok(())
+}
+
+/// Sibling of `path` that an entry is prepared at, e.g. `serde-1.0.0.crate.tmp`
+pub(crate) fn staging_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".");
+    file_name.push(STAGING_EXTENSION);
+    path.with_file_name(file_name)
+}
+
+/// Writes the file via its staging path, so an interrupted write never leaves a
+/// truncated file at `path`
+pub fn write_atomic(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    let staging = staging_path(path);
+    std::fs::write(&staging, contents)
+        .map_err(|e| anyhow::format_err!("Failed to write {:?}: {e}", staging))?;
+    std::fs::rename(&staging, path)
+        .map_err(|e| anyhow::format_err!("Failed to move {:?} to {:?}: {e}", staging, path))
+}
+
+/// Moves a prepared staging file or dir to `dest`, replacing whatever an
+/// interrupted run left there
+pub(crate) fn commit_entry(staging: &Path, dest: &Path) -> anyhow::Result<()> {
+    remove_entry(dest)?;
+    std::fs::rename(staging, dest)
+        .map_err(|e| anyhow::format_err!("Failed to move {:?} to {:?}: {e}", staging, dest))
+}
+
+/// Removes a file or a dir if it exists
+pub(crate) fn remove_entry(path: &Path) -> anyhow::Result<()> {
+    let res = match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => std::fs::remove_dir_all(path),
+        Ok(_) => std::fs::remove_file(path),
+        Err(_) => return Ok(()),
+    };
+    res.map_err(|e| anyhow::format_err!("Failed to remove {:?}: {e}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    #[test]
+    fn test_write_atomic_replaces_leftovers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("index");
+        std::fs::write(staging_path(&path), b"partial").unwrap();
+        write_atomic(&path, b"full").unwrap();
+        assert_eq!(b"full".to_vec(), std::fs::read(&path).unwrap());
+        assert!(!staging_path(&path).exists());
+
+        let staging = dir.path().join("src.tmp");
+        std::fs::create_dir(&staging).unwrap();
+        std::fs::write(staging.join("lib.rs"), b"").unwrap();
+        let dest = dir.path().join("src");
+        std::fs::create_dir(&dest).unwrap();
+        std::fs::write(dest.join("partial.rs"), b"").unwrap();
+        commit_entry(&staging, &dest).unwrap();
+        assert!(dest.join("lib.rs").exists());
+        assert!(!dest.join("partial.rs").exists());
+        assert!(!staging.exists());
+    }
+
+    #[test]
+    fn test_store_entry_is_fetched_once_and_refetched_when_broken() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path().join("store"));
+        let fetches = Cell::new(0);
+        let verify = |path: &Path| {
+            if std::fs::read(path.join("lib.rs"))? != b"fn main() {}" {
+                anyhow::bail!("wrong content");
+            }
+            Ok(())
+        };
+        let fetch = |path: &Path| {
+            fetches.set(fetches.get() + 1);
+            std::fs::create_dir_all(path.join("src"))?;
+            std::fs::write(path.join("lib.rs"), b"fn main() {}")?;
+            Ok(())
+        };
+
+        let entry = store.entry("crate", "sha-256-00", verify, fetch).unwrap();
+        let entry_again = store.entry("crate", "sha-256-00", verify, fetch).unwrap();
+        assert_eq!(entry, entry_again);
+        assert_eq!(1, fetches.get());
+
+        let run_dir = dir.path().join("run").join("serde-1.0.0");
+        link_tree(&entry, &run_dir).unwrap();
+        assert!(run_dir.join("src").is_dir());
+        assert_eq!(b"fn main() {}".to_vec(), std::fs::read(run_dir.join("lib.rs")).unwrap());
+
+        let lib_path = entry.join("lib.rs");
+        let mut permissions = std::fs::metadata(&lib_path).unwrap().permissions();
+        assert!(permissions.readonly());
+        #[allow(clippy::permissions_set_readonly_false)]
+        permissions.set_readonly(false);
+        std::fs::set_permissions(&lib_path, permissions).unwrap();
+        std::fs::write(&lib_path, b"broken").unwrap();
+        store.entry("crate", "sha-256-00", verify, fetch).unwrap();
+        assert_eq!(2, fetches.get());
+
+        let entries = store.entries().unwrap();
+        assert_eq!(1, entries.len());
+        assert_eq!("sha-256-00", entries[0].key);
+        store.remove(&entries[0]).unwrap();
+        assert!(store.entries().unwrap().is_empty());
+
+        let err = store.entry("crate", "sha-256-01", verify, |_| Ok(())).unwrap_err();
+        assert!(err.to_string().contains("No such file"));
+        assert!(!dir.path().join("store").join("crate").join("sha-256-01").exists());
+    }
+
+    #[test]
+    fn test_unused_entries_of_copied_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let fetch = |path: &Path| Ok(std::fs::write(path, b"crate")?);
+        let run = |name: &str, key: &str| {
+            // a separate process for every build
+            let store = Store::new(dir.path().join("store"));
+            let _lock = store.lock_shared().unwrap();
+            let entry = store.entry("crate", key, |_| Ok(()), fetch).unwrap();
+            let deps = dir.path().join(name);
+            std::fs::create_dir_all(&deps).unwrap();
+            // reflinked or copied files have a single link
+            std::fs::copy(entry, deps.join("serde.crate")).unwrap();
+            store.record_run(&deps).unwrap();
+            deps
+        };
+        let first = run("first", "sha-256-00");
+        run("second", "sha-256-01");
+        run("second", "sha-256-02");
+
+        let store = Store::new(dir.path().join("store"));
+        let _lock = store.lock_exclusive().unwrap();
+        assert!(store.unused_entries(|_| false).unwrap().is_empty());
+        let second = dir.path().join("second").canonicalize().unwrap();
+        assert_eq!(2, store.unused_entries(|dir| dir == second).unwrap().len());
+        std::fs::remove_dir_all(first).unwrap();
+        let unused = store.unused_entries(|_| false).unwrap();
+        assert_eq!(vec!["sha-256-00"], unused.iter().map(|e| e.key.as_str()).collect::<Vec<_>>());
+        let manifests = || std::fs::read_dir(dir.path().join("store/.runs")).unwrap().count();
+        assert_eq!(2, manifests());
+        store.remove_stale_manifests().unwrap();
+        assert_eq!(1, manifests());
+    }
+}
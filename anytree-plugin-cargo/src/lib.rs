@@ -1,12 +1,18 @@
+use std::collections::{HashMap, HashSet};
 use std::ffi::{OsStr, OsString};
-use std::path::{Path, PathBuf};
+use std::path::{Component as PathComponent, Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::vec;
 
-use anytree_plugin_cargo_dependencies::load_dependencies;
+use anytree_plugin_cargo_dependencies::signature::check_signatures;
+use anytree_plugin_cargo_dependencies::{
+    load_dependencies, path_dependencies, LoadOptions, CARGO_CONFIG_NAME, COMMIT_PROPERTY,
+    PATH_PROPERTY, SUBMODULE_TYPE,
+};
 use anytree_sbom::{Component, CycloneDXBom};
-use anytree_utils::crypto::hash::check_hashes;
+use anytree_utils::crypto::hash::{check_hashes, check_source_hashes};
 use anytree_utils::tracing::wrap_cmd_with_tracing;
+use anytree_utils::{git, nar};
 
 const PROJECT_DIR: &str = "src";
 const DEPENDENCIES_DIR: &str = "cargo";
@@ -24,11 +30,16 @@ pub fn build(
     sbom: &CycloneDXBom,
     container_name: &str,
     run_dir: impl AsRef<Path>,
+    options: &LoadOptions,
 ) -> anyhow::Result<()> {
     let src_dir = run_dir.as_ref().join(PROJECT_DIR);
     if !src_dir.exists() {
         std::fs::create_dir_all(&src_dir)?;
-        checkout_project(project, &src_dir)?;
+        // a partial checkout must not be taken for the project next time
+        if let Err(e) = checkout_project(project, sbom, &src_dir, options) {
+            let _ = std::fs::remove_dir_all(&src_dir);
+            return Err(e);
+        }
     }
 
     let src_sub_path = project
@@ -46,7 +57,7 @@ pub fn build(
     let mut deps_dir = PathBuf::from(run_dir.as_ref());
     deps_dir.push(DEPENDENCIES_DIR);
     std::fs::create_dir_all(&deps_dir)?;
-    checkout_dependencies(sbom, &deps_dir)?;
+    checkout_dependencies(sbom, &deps_dir, options)?;
 
     // prepare dir for artifact
     let mut target_dir = PathBuf::from(run_dir.as_ref());
@@ -98,11 +109,38 @@ pub fn build(
         });
     }
 
+    // mount config with alternate registries
+    let cargo_config = deps_dir.join(CARGO_CONFIG_NAME);
+    if cargo_config.exists() {
+        docker_cmd.arg("--mount").arg({
+            let mut s = OsString::from("type=bind,source=");
+            s.push(cargo_config.into_os_string());
+            s.push(",target=");
+            s.push(CONTAINER_REGISTRY_ROOT);
+            s.push(CARGO_CONFIG_NAME);
+            s.push(",readonly");
+            s
+        });
+    }
+
     let mut workdir = PathBuf::from(CONTAINER_PROJECT_DIR);
     if let Some(sub_path) = &src_sub_path {
         workdir.push(sub_path);
     }
 
+    // mount path dependencies where the project manifest expects them
+    for dependency in path_dependencies(sbom, &deps_dir)? {
+        let target = mount_target(&workdir, &dependency.path)?;
+        docker_cmd.arg("--mount").arg({
+            let mut s = OsString::from("type=bind,source=");
+            s.push(dependency.source_dir.into_os_string());
+            s.push(",target=");
+            s.push(target.into_os_string());
+            s.push(",readonly");
+            s
+        });
+    }
+
     docker_cmd.arg("--workdir").arg(&workdir);
     if let Some(base) = base_image {
         docker_cmd.arg(base);
@@ -119,8 +157,10 @@ pub fn build(
         .map(|property| property.value.clone());
 
     // By default build with standard command.
+    // `--locked` makes cargo fail instead of re-resolving to a version that is
+    // not in the SBOM (the index only contains SBOM-pinned versions).
     // TODO: add ability to specify build command in config
-    let mut build_cmd = "cargo build --offline --release".to_string();
+    let mut build_cmd = "cargo build --offline --locked --release".to_string();
     if let Some(prerun) = prerun {
         build_cmd = format!("{} && {}", prerun, build_cmd);
     }
@@ -187,67 +227,236 @@ pub fn build(
     Ok(())
 }
 
-fn checkout_project(project: &Component, src_dir: impl AsRef<Path>) -> anyhow::Result<()> {
+fn checkout_project(
+    project: &Component,
+    sbom: &CycloneDXBom,
+    src_dir: impl AsRef<Path>,
+    options: &LoadOptions,
+) -> anyhow::Result<()> {
     tracing::trace!("Load project to {:?}", src_dir.as_ref());
-    let url = &project
+    let url = component_url(project)?;
+    let commit = component_property(project, COMMIT_PROPERTY)?;
+
+    // submodules are fetched from SBOM, so each of them must be described
+    // there and pinned at the commit the project records
+    let submodules = sbom
+        .components
+        .iter()
+        .filter(|component| component.mime_type.as_deref() == Some(SUBMODULE_TYPE))
+        .map(|component| Ok((component_property(component, PATH_PROPERTY)?, component)))
+        .collect::<anyhow::Result<HashMap<_, _>>>()?;
+    let mut checked_out = HashSet::new();
+    tracing::info!("Checking out project {url}#{commit}");
+    let rewrites = &options.rewrites;
+    git::checkout_with_submodules(
+        &rewrites.rewrite(url),
+        commit,
+        src_dir.as_ref(),
+        &options.credentials,
+        &mut |path, recorded| {
+            let component = submodules.get(path).ok_or(anyhow::format_err!(
+                "Submodule {path} of {} is not in SBOM",
+                project.name
+            ))?;
+            let pinned = component_property(component, COMMIT_PROPERTY)?;
+            if recorded != pinned {
+                anyhow::bail!("Submodule {path} is at {recorded}, but SBOM pins {pinned}");
+            }
+            checked_out.insert(path.to_string());
+            Ok(rewrites.rewrite(component_url(component)?).into_owned())
+        },
+    )?;
+
+    check_source(project, src_dir.as_ref(), url, commit, options)?;
+    for (path, component) in &submodules {
+        if !checked_out.contains(*path) {
+            anyhow::bail!("Submodule {path} from SBOM is not in {}", project.name);
+        }
+        tracing::info!("Checking submodule {path}");
+        let dir = src_dir.as_ref().join(path);
+        let commit = component_property(component, COMMIT_PROPERTY)?;
+        check_source(component, &dir, component_url(component)?, commit, options)?;
+    }
+    Ok(())
+}
+
+/// Checks the signatures, the hashes and the tree hashes of the checkout of
+/// `component`
+fn check_source(
+    component: &Component,
+    dir: &Path,
+    url: &str,
+    commit: &str,
+    options: &LoadOptions,
+) -> anyhow::Result<()> {
+    check_signatures(component, dir, url, commit, options)?;
+    tracing::info!("Checking {} hashes", component.name);
+    check_source_hashes(component, || git::archive(dir, commit), || nar::dump_checkout(dir))
+}
+
+fn component_url(component: &Component) -> anyhow::Result<&str> {
+    component
         .external_references
         .as_ref()
-        .and_then(|v| v.get(0))
-        .ok_or(anyhow::format_err!("Failed to get url for component: {}", project.name))?
-        .url;
-    let commit = &project
+        .and_then(|v| v.first())
+        .map(|reference| reference.url.as_str())
+        .ok_or(anyhow::format_err!("Failed to get url for component: {}", component.name))
+}
+
+fn component_property<'a>(component: &'a Component, name: &str) -> anyhow::Result<&'a str> {
+    component
         .properties
         .as_ref()
-        .and_then(|properties| properties.iter().find(|property| property.name == "commit"))
-        .ok_or(anyhow::format_err!("Failed to get commit for component: {}", project.name))?
-        .value;
+        .and_then(|properties| properties.iter().find(|property| property.name == name))
+        .map(|property| property.value.as_str())
+        .ok_or(anyhow::format_err!("Failed to get {name} for component: {}", component.name))
+}
 
-    tracing::info!("Checking out project {url}#{commit}");
+fn checkout_dependencies(
+    sbom: &CycloneDXBom,
+    deps_dir: impl AsRef<Path>,
+    options: &LoadOptions,
+) -> anyhow::Result<()> {
+    tracing::trace!("Load cargo dependencies to {:?}", deps_dir.as_ref());
+    load_dependencies(sbom, deps_dir, options)
+}
 
-    // Clone the repo
-    if !Command::new("git")
-        .arg("clone")
-        .arg(url)
-        .arg(src_dir.as_ref())
-        // inherit all output because it's sort of user output via our bin
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit())
-        .status()?
-        .success()
-    {
-        anyhow::bail!("Failed to clone repo: {url}");
-    }
-
-    // checkout commit specified in SBOM, do not delete
-    // TODO: check status
-    Command::new("git")
-        .arg("checkout")
-        .arg(commit)
-        .current_dir(src_dir.as_ref())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .status()?;
-
-    if let Some(hashes) = &project.hashes {
-        // To check hash get archive bytes of the repo
-        let git_archive_data = Command::new("git")
-            .arg("archive")
-            .arg("--format=tar")
-            .arg(commit)
-            .current_dir(src_dir.as_ref())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .output()?
-            .stdout;
-
-        tracing::info!("Checking project hashes");
-        check_hashes(hashes, git_archive_data)?;
+/// Container path of the path dependency at `path` relative to `workdir`. It
+/// must stay inside the project dir, so a dependency can't be mounted over
+/// the system dirs of the container or over the project itself.
+fn mount_target(workdir: &Path, path: &Path) -> anyhow::Result<PathBuf> {
+    let target = normalize_path(&workdir.join(path));
+    let project_dir = Path::new(CONTAINER_PROJECT_DIR);
+    if !target.starts_with(project_dir) || target == project_dir || workdir.starts_with(&target) {
+        anyhow::bail!("Path dependency {path:?} is outside of the project dir");
     }
+    Ok(target)
+}
 
-    Ok(())
+/// Resolves `.` and `..` of the container path without touching the host fs
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut res = PathBuf::new();
+    for part in path.components() {
+        match part {
+            PathComponent::CurDir => {}
+            PathComponent::ParentDir => {
+                res.pop();
+            }
+            other => res.push(other),
+        }
+    }
+    res
 }
 
-fn checkout_dependencies(sbom: &CycloneDXBom, deps_dir: impl AsRef<Path>) -> anyhow::Result<()> {
-    tracing::trace!("Load cargo dependencies to {:?}", deps_dir.as_ref());
-    load_dependencies(sbom, deps_dir)
+#[cfg(test)]
+mod tests {
+    use anytree_sbom::{ExternalReference, Hash, Property};
+
+    use super::*;
+
+    fn git(dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@localhost", "-C"])
+            .arg(dir)
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    }
+
+    fn component(dir: &Path, mime_type: Option<&str>, properties: &[(&str, &str)]) -> Component {
+        Component {
+            name: dir.file_name().unwrap().to_string_lossy().into_owned(),
+            mime_type: mime_type.map(str::to_string),
+            external_references: Some(vec![ExternalReference {
+                url: format!("file://{}", dir.display()),
+                ..Default::default()
+            }]),
+            properties: Some(
+                properties
+                    .iter()
+                    .map(|(name, value)| Property {
+                        name: name.to_string(),
+                        value: value.to_string(),
+                        ..Default::default()
+                    })
+                    .collect(),
+            ),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_checkout_project_submodules() {
+        let dir = tempfile::tempdir().unwrap();
+        let sub = dir.path().join("sub");
+        std::fs::create_dir(&sub).unwrap();
+        git(&sub, &["init", "-q"]);
+        std::fs::write(sub.join("lib.c"), "int x;").unwrap();
+        git(&sub, &["add", "lib.c"]);
+        git(&sub, &["commit", "-q", "-m", "sub"]);
+        let sub_commit = git(&sub, &["rev-parse", "HEAD"]);
+
+        let project = dir.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+        git(&project, &["init", "-q"]);
+        let gitlink = format!("160000,{sub_commit},vendor/sub");
+        git(&project, &["update-index", "--add", "--cacheinfo", &gitlink]);
+        git(&project, &["commit", "-q", "-m", "project"]);
+        let commit = git(&project, &["rev-parse", "HEAD"]);
+
+        let project = component(&project, None, &[(COMMIT_PROPERTY, &commit)]);
+        let submodule = |path: &str, commit: &str| {
+            let properties = [(PATH_PROPERTY, path), (COMMIT_PROPERTY, commit)];
+            component(&sub, Some(SUBMODULE_TYPE), &properties)
+        };
+        let checkout = |name: &str, submodules: Vec<Component>| {
+            let sbom = CycloneDXBom { components: submodules, ..Default::default() };
+            let src_dir = dir.path().join(name);
+            checkout_project(&project, &sbom, &src_dir, &LoadOptions::default()).map(|()| src_dir)
+        };
+
+        let src_dir = checkout("pinned", vec![submodule("vendor/sub", &sub_commit)]).unwrap();
+        assert_eq!("int x;", std::fs::read_to_string(src_dir.join("vendor/sub/lib.c")).unwrap());
+
+        let err = checkout("moved", vec![submodule("vendor/sub", &commit)]).unwrap_err();
+        assert!(err.to_string().contains("but SBOM pins"), "{err}");
+
+        let err = checkout("missing", vec![]).unwrap_err();
+        assert!(
+            err.to_string().contains("Submodule vendor/sub of project is not in SBOM"),
+            "{err}"
+        );
+
+        let extra = vec![submodule("vendor/sub", &sub_commit), submodule("other", &sub_commit)];
+        let err = checkout("extra", extra).unwrap_err();
+        assert!(err.to_string().contains("Submodule other from SBOM is not in"), "{err}");
+
+        let mut wrong_hash = submodule("vendor/sub", &sub_commit);
+        wrong_hash.hashes = Some(vec![Hash {
+            alg: "SHA-256".to_string(),
+            content: "0".repeat(64),
+            ..Default::default()
+        }]);
+        let err = checkout("wrong_hash", vec![wrong_hash]).unwrap_err();
+        assert!(err.to_string().contains("Wrong hash"), "{err}");
+    }
+
+    #[test]
+    fn test_mount_target() {
+        let root = Path::new(CONTAINER_PROJECT_DIR);
+        let workdir = root.join("app");
+        assert_eq!(root.join("lib"), mount_target(&workdir, Path::new("../lib")).unwrap());
+        assert_eq!(
+            workdir.join("vendor/dep"),
+            mount_target(&workdir, Path::new("./vendor/../vendor/dep")).unwrap()
+        );
+        assert_eq!(root.join("lib"), mount_target(root, Path::new("lib")).unwrap());
+
+        for path in ["../../usr/bin", "../../../../etc", "..", "../app", "../lib/../.."] {
+            assert!(mount_target(&workdir, Path::new(path)).is_err(), "{path}");
+        }
+        assert!(mount_target(root, Path::new("../proj2")).is_err());
+    }
 }
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use crate::{
+    Component, Extra, OrganizationalContact, OrganizationalEntity, Service, Signature, Timestamp,
+};
+
+/// Comment on parts of the BOM
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Annotation {
+    #[serde(rename = "bom-ref")]
+    pub bom_ref: Option<String>,
+    /// `bom-ref`s of the annotated objects
+    pub subjects: Vec<String>,
+    pub annotator: Annotator,
+    pub timestamp: Timestamp,
+    pub text: String,
+    pub signature: Option<Signature>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// Only one of the fields is set
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Annotator {
+    pub organization: Option<OrganizationalEntity>,
+    pub individual: Option<OrganizationalContact>,
+    pub component: Option<Box<Component>>,
+    pub service: Option<Box<Service>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
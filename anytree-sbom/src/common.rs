@@ -0,0 +1,181 @@
+//! Types shared by the components, services and the rest of the BOM
+
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+/// Date and time keeping the offset they were written with
+pub type Timestamp = DateTime<FixedOffset>;
+
+/// Fields unknown to the model, written back as they were read
+pub type Extra = serde_json::Map<String, serde_json::Value>;
+
+/// JSF signature, see https://cyberphone.github.io/doc/security/jsf.html
+pub type Signature = serde_json::Value;
+
+/// List that older SBOMs have as a single value, written back in the shape it
+/// was read in
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T> OneOrMany<T> {
+    pub fn as_slice(&self) -> &[T] {
+        match self {
+            Self::One(value) => std::slice::from_ref(value),
+            Self::Many(values) => values,
+        }
+    }
+}
+
+impl<T> From<Vec<T>> for OneOrMany<T> {
+    fn from(values: Vec<T>) -> Self {
+        Self::Many(values)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Hash {
+    /// MD5, SHA-1, SHA-256, SHA-384, SHA-512, SHA3-256, SHA3-384, SHA3-512,
+    /// BLAKE2b-256, BLAKE2b-384, BLAKE2b-512 or BLAKE3
+    pub alg: String,
+    pub content: String,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Property {
+    pub name: String,
+    pub value: String,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct ExternalReference {
+    pub url: String,
+    pub comment: Option<String>,
+    /// vcs, issue-tracker, website, advisories, bom, distribution, ...
+    #[serde(rename = "type")]
+    pub ref_type: String,
+    pub hashes: Option<Vec<Hash>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// Inline content, e.g. a license text or a diff
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    pub content_type: Option<String>,
+    /// `base64` when `content` is encoded
+    pub encoding: Option<String>,
+    pub content: String,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct OrganizationalEntity {
+    #[serde(rename = "bom-ref")]
+    pub bom_ref: Option<String>,
+    pub name: Option<String>,
+    pub url: Option<Vec<String>>,
+    pub contact: Option<Vec<OrganizationalContact>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct OrganizationalContact {
+    #[serde(rename = "bom-ref")]
+    pub bom_ref: Option<String>,
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// Organization or individual, only one of them is set
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct OrganizationOrIndividual {
+    pub organization: Option<OrganizationalEntity>,
+    pub individual: Option<OrganizationalContact>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// Who did something and when, e.g. authored a commit
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct IdentifiableAction {
+    pub timestamp: Option<Timestamp>,
+    pub name: Option<String>,
+    pub email: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Issue {
+    /// defect, enhancement or security
+    #[serde(rename = "type")]
+    pub issue_type: String,
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub source: Option<IssueSource>,
+    pub references: Option<Vec<String>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct IssueSource {
+    pub name: Option<String>,
+    pub url: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseNotes {
+    /// major, minor, patch, pre-release or internal
+    #[serde(rename = "type")]
+    pub release_type: String,
+    pub title: Option<String>,
+    pub featured_image: Option<String>,
+    pub social_image: Option<String>,
+    pub description: Option<String>,
+    pub timestamp: Option<Timestamp>,
+    pub aliases: Option<Vec<String>>,
+    pub tags: Option<Vec<String>>,
+    pub resolves: Option<Vec<Issue>>,
+    pub notes: Option<Vec<Note>>,
+    pub properties: Option<Vec<Property>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Note {
+    pub locale: Option<String>,
+    pub text: Attachment,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
@@ -0,0 +1,287 @@
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use crate::{
+    Attachment, ExternalReference, Extra, Hash, IdentifiableAction, Issue, LicenseChoice,
+    ModelCard, OrganizationalContact, OrganizationalEntity, Property, ReleaseNotes, Signature,
+};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub enum ComponentType {
+    #[serde(rename = "application")]
+    Application,
+    #[serde(rename = "framework")]
+    Framework,
+    #[default]
+    #[serde(rename = "library")]
+    Library,
+    #[serde(rename = "container")]
+    Container,
+    #[serde(rename = "platform")]
+    Platform,
+    #[serde(rename = "operating-system")]
+    OperatingSystem,
+    #[serde(rename = "device")]
+    Device,
+    #[serde(rename = "device-driver")]
+    DeviceDriver,
+    #[serde(rename = "firmware")]
+    Firmware,
+    #[serde(rename = "file")]
+    File,
+    #[serde(rename = "machine-learning-model")]
+    MachineLearningModel,
+    #[serde(rename = "data")]
+    Data,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Component {
+    #[serde(rename = "type")]
+    pub component_type: ComponentType,
+    #[serde(rename = "mime-type")]
+    pub mime_type: Option<String>,
+    #[serde(rename = "bom-ref")]
+    pub bom_ref: Option<String>,
+    pub supplier: Option<OrganizationalEntity>,
+    pub author: Option<String>,
+    pub publisher: Option<String>,
+    pub group: Option<String>,
+    pub name: String,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    /// required, optional or excluded
+    pub scope: Option<String>,
+    pub hashes: Option<Vec<Hash>>,
+    pub licenses: Option<Vec<LicenseChoice>>,
+    pub copyright: Option<String>,
+    pub cpe: Option<String>,
+    pub purl: Option<String>,
+    pub swid: Option<Swid>,
+    /// Deprecated in 1.3, `pedigree` describes the modifications
+    pub modified: Option<bool>,
+    pub pedigree: Option<Pedigree>,
+    pub external_references: Option<Vec<ExternalReference>>,
+    pub properties: Option<Vec<Property>>,
+    pub components: Option<Vec<Component>>,
+    pub evidence: Option<ComponentEvidence>,
+    pub release_notes: Option<ReleaseNotes>,
+    pub model_card: Option<ModelCard>,
+    pub data: Option<Vec<ComponentData>>,
+    pub signature: Option<Signature>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// ISO/IEC 19770-2 software identification tag
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Swid {
+    pub tag_id: String,
+    pub name: String,
+    pub version: Option<String>,
+    pub tag_version: Option<i32>,
+    pub patch: Option<bool>,
+    pub text: Option<Attachment>,
+    pub url: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// Where a component comes from and how it was changed
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Pedigree {
+    pub ancestors: Option<Vec<Component>>,
+    pub descendants: Option<Vec<Component>>,
+    pub variants: Option<Vec<Component>>,
+    pub commits: Option<Vec<Commit>>,
+    pub patches: Option<Vec<Patch>>,
+    pub notes: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Commit {
+    pub uid: Option<String>,
+    pub url: Option<String>,
+    pub author: Option<IdentifiableAction>,
+    pub committer: Option<IdentifiableAction>,
+    pub message: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Patch {
+    /// unofficial, monkey, backport or cherry-pick
+    #[serde(rename = "type")]
+    pub patch_type: String,
+    pub diff: Option<Diff>,
+    pub resolves: Option<Vec<Issue>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Diff {
+    pub text: Option<Attachment>,
+    pub url: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// How the component was found and identified
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct ComponentEvidence {
+    pub identity: Option<Identity>,
+    pub occurrences: Option<Vec<Occurrence>>,
+    pub callstack: Option<Callstack>,
+    pub licenses: Option<Vec<LicenseChoice>>,
+    pub copyright: Option<Vec<Copyright>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Identity {
+    /// group, name, version, purl, cpe, swid or hash
+    pub field: String,
+    pub confidence: Option<f64>,
+    pub methods: Option<Vec<IdentityMethod>>,
+    /// `bom-ref`s of the components or services used as tools
+    pub tools: Option<Vec<String>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct IdentityMethod {
+    /// source-code-analysis, binary-analysis, manifest-analysis,
+    /// ast-fingerprint, hash-comparison, instrumentation, dynamic-analysis,
+    /// filename, attestation or other
+    pub technique: String,
+    pub confidence: f64,
+    pub value: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Occurrence {
+    #[serde(rename = "bom-ref")]
+    pub bom_ref: Option<String>,
+    pub location: String,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Callstack {
+    pub frames: Option<Vec<Frame>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Frame {
+    pub package: Option<String>,
+    pub module: String,
+    pub function: Option<String>,
+    pub parameters: Option<Vec<String>>,
+    pub line: Option<i32>,
+    pub column: Option<i32>,
+    pub full_filename: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Copyright {
+    pub text: String,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// Data of a `data` component or a dataset of a model
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComponentData {
+    #[serde(rename = "bom-ref")]
+    pub bom_ref: Option<String>,
+    /// source-code, configuration, dataset, definition or other
+    #[serde(rename = "type")]
+    pub data_type: String,
+    pub name: Option<String>,
+    pub contents: Option<DataContents>,
+    pub classification: Option<String>,
+    pub sensitive_data: Option<Vec<String>>,
+    pub graphics: Option<GraphicsCollection>,
+    pub description: Option<String>,
+    pub governance: Option<DataGovernance>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct DataContents {
+    pub attachment: Option<Attachment>,
+    pub url: Option<String>,
+    pub properties: Option<Vec<Property>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct GraphicsCollection {
+    pub description: Option<String>,
+    pub collection: Option<Vec<Graphic>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Graphic {
+    pub name: Option<String>,
+    pub image: Option<Attachment>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct DataGovernance {
+    pub custodians: Option<Vec<DataGovernanceParty>>,
+    pub stewards: Option<Vec<DataGovernanceParty>>,
+    pub owners: Option<Vec<DataGovernanceParty>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// Organization or contact, only one of them is set
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct DataGovernanceParty {
+    pub organization: Option<OrganizationalEntity>,
+    pub contact: Option<OrganizationalContact>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use crate::{Extra, OneOrMany, Signature};
+
+/// Components or services `reference` directly depends on
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Dependency {
+    /// `bom-ref` of the dependent component or service
+    #[serde(rename = "ref")]
+    pub reference: String,
+    pub depends_on: Option<Vec<String>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// How complete the listed assemblies, dependencies and vulnerabilities are
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Composition {
+    #[serde(rename = "bom-ref")]
+    pub bom_ref: Option<String>,
+    /// complete, incomplete, incomplete_first_party_only,
+    /// incomplete_first_party_proprietary_only,
+    /// incomplete_first_party_opensource_only, incomplete_third_party_only,
+    /// incomplete_third_party_proprietary_only,
+    /// incomplete_third_party_opensource_only, unknown or not_specified
+    pub aggregate: String,
+    pub assemblies: Option<OneOrMany<String>>,
+    pub dependencies: Option<Vec<String>>,
+    pub vulnerabilities: Option<Vec<String>>,
+    pub signature: Option<Signature>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
@@ -0,0 +1,262 @@
+//! XML and protobuf encodings of CycloneDX, converted from and to the JSON
+//! form of the model with the field mapping in [`schema`]
+
+mod protobuf;
+mod schema;
+mod xml;
+
+use serde_json::{Map, Value};
+
+use crate::{CycloneDXBom, KeyOrder};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Xml,
+    Protobuf,
+}
+
+impl Format {
+    /// Guesses the encoding from the first characters: JSON and XML start with
+    /// `{` and `<`. Anything else is protobuf only if it is a protobuf message
+    /// with the spec version, `None` otherwise.
+    pub fn detect(data: &[u8]) -> Option<Format> {
+        let text = data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(data);
+        match text.iter().find(|b| !b.is_ascii_whitespace()) {
+            Some(b'{') => Some(Format::Json),
+            Some(b'<') => Some(Format::Xml),
+            _ => protobuf::is_bom(data).then_some(Format::Protobuf),
+        }
+    }
+}
+
+impl CycloneDXBom {
+    /// Parses the SBOM in any of the encodings, see [`Format::detect`]
+    pub fn parse(data: &[u8]) -> anyhow::Result<Self> {
+        let format = Format::detect(data).ok_or_else(|| {
+            anyhow::format_err!("Unknown format, expected CycloneDX JSON, XML or protobuf")
+        })?;
+        Self::parse_as(data, format)
+    }
+
+    pub fn parse_as(data: &[u8], format: Format) -> anyhow::Result<Self> {
+        let value = match format {
+            Format::Json => {
+                let mut sbom = serde_json::from_slice::<Self>(data)?;
+                sbom.key_order = KeyOrder::record(&serde_json::from_slice(data)?);
+                return Ok(sbom);
+            }
+            Format::Xml => xml::read(data)?,
+            Format::Protobuf => protobuf::read(data)?,
+        };
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Encodes the SBOM. Protobuf has no place for unknown fields and
+    /// signatures, they are only written to JSON and XML. Empty arrays may
+    /// be left out of both.
+    pub fn write(&self, format: Format) -> anyhow::Result<Vec<u8>> {
+        let value = self.to_json()?;
+        match format {
+            Format::Json => {
+                let mut data = serde_json::to_vec_pretty(&value)?;
+                data.push(b'\n');
+                Ok(data)
+            }
+            Format::Xml => xml::write(&value),
+            Format::Protobuf => protobuf::write(&value),
+        }
+    }
+
+    /// JSON of the SBOM with the keys in the order they were parsed in
+    pub fn to_json(&self) -> serde_json::Result<Value> {
+        let mut value = serde_json::to_value(self)?;
+        self.key_order.apply(&mut value);
+        Ok(value)
+    }
+}
+
+/// Protobuf number of the enum value in JSON
+fn enum_number(values: &[(&str, i32)], name: &str) -> anyhow::Result<i32> {
+    values
+        .iter()
+        .find(|(value, _)| *value == name)
+        .map(|(_, number)| *number)
+        .ok_or_else(|| anyhow::format_err!("{name:?} has no protobuf value"))
+}
+
+fn enum_name(values: &[(&'static str, i32)], number: i32) -> anyhow::Result<&'static str> {
+    values
+        .iter()
+        .find(|(_, value)| *value == number)
+        .map(|(name, _)| *name)
+        .ok_or_else(|| anyhow::format_err!("Unknown enum value {number}"))
+}
+
+fn object(value: &Value) -> anyhow::Result<&Map<String, Value>> {
+    value.as_object().ok_or_else(|| anyhow::format_err!("Expected object, got {value}"))
+}
+
+/// Items of a list, a single string is a list of one, see [`crate::OneOrMany`]
+fn array(value: &Value) -> anyhow::Result<&[Value]> {
+    match value {
+        Value::Array(values) => Ok(values),
+        Value::String(_) => Ok(std::slice::from_ref(value)),
+        _ => Err(anyhow::format_err!("Expected array, got {value}")),
+    }
+}
+
+fn string(value: &Value) -> anyhow::Result<&str> {
+    value.as_str().ok_or_else(|| anyhow::format_err!("Expected string, got {value}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{assert_same, FIXTURES};
+
+    /// No empty repeated fields in protobuf and e.g. no empty `dependsOn` in
+    /// XML
+    fn remove_empty_arrays(value: &mut Value) {
+        match value {
+            Value::Object(object) => {
+                object
+                    .retain(|_, value| !matches!(value, Value::Array(values) if values.is_empty()));
+                object.values_mut().for_each(remove_empty_arrays);
+            }
+            Value::Array(values) => values.iter_mut().for_each(remove_empty_arrays),
+            _ => {}
+        }
+    }
+
+    /// Only the fields of the proto, no unknown ones or signatures
+    fn retain_proto_fields(value: &mut Value, ty: &schema::Type) {
+        let Value::Object(object) = value else { return };
+        object.retain(|key, _| ty.field(key).map_or(false, |field| field.tag != 0));
+        for (key, value) in object {
+            let Some(schema::Field { kind: schema::Kind::Msg(ty), .. }) = ty.field(key) else {
+                continue;
+            };
+            match value {
+                Value::Array(items) => {
+                    items.iter_mut().for_each(|item| retain_proto_fields(item, ty))
+                }
+                value => retain_proto_fields(value, ty),
+            }
+        }
+    }
+
+    /// JSON of the SBOM without what `format` can't keep
+    fn encodable(sbom: &CycloneDXBom, format: Format) -> Value {
+        let mut sbom = sbom.clone();
+        if format != Format::Json {
+            // a single value is read back as a list
+            for composition in sbom.compositions.iter_mut().flatten() {
+                if let Some(assemblies) = &mut composition.assemblies {
+                    *assemblies = assemblies.as_slice().to_vec().into();
+                }
+            }
+        }
+        let mut value = serde_json::to_value(sbom).unwrap();
+        let object = value.as_object_mut().unwrap();
+        object.remove("$schema");
+        if format == Format::Protobuf {
+            retain_proto_fields(&mut value, &schema::BOM);
+        }
+        if format != Format::Json {
+            remove_empty_arrays(&mut value);
+        }
+        value
+    }
+
+    #[test]
+    fn test_detect() {
+        let json = b"\xEF\xBB\xBF\n  {\"bomFormat\": \"CycloneDX\"}";
+        assert_eq!(Some(Format::Json), Format::detect(json));
+        assert_eq!(Some(Format::Xml), Format::detect(b"<?xml version=\"1.0\"?><bom/>"));
+        assert_eq!(Some(Format::Protobuf), Format::detect(b"\n\x031.5\x10\x01"));
+        for data in [&b""[..], b"SPDXVersion: SPDX-2.3\n", b"\n\x031.5\x10", b"\x10\x01"] {
+            assert_eq!(None, Format::detect(data));
+        }
+        let err = CycloneDXBom::parse(b"name: app\n").unwrap_err();
+        assert!(err.to_string().contains("Unknown format"), "{err}");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for fixture in FIXTURES {
+            let sbom = CycloneDXBom::parse(fixture).unwrap();
+            for format in [Format::Json, Format::Xml, Format::Protobuf] {
+                let data = sbom.write(format).unwrap();
+                assert_eq!(Some(format), Format::detect(&data));
+                let parsed = CycloneDXBom::parse(&data).unwrap();
+                assert_same(
+                    &format!("{format:?}"),
+                    &encodable(&sbom, format),
+                    &encodable(&parsed, format),
+                );
+            }
+        }
+    }
+
+    /// The XML is laid out as cyclonedx-cli writes it. The protobuf is encoded
+    /// apart from [`protobuf::write`] with the field numbers of
+    /// `bom-1.5.proto`, in number order as protoc does.
+    #[test]
+    fn test_external_encodings() {
+        let json = include_bytes!("../../tests/fixtures/bom-1.5.cdx.json");
+        let xml = include_bytes!("../../tests/fixtures/bom-1.5.cdx.xml");
+        let protobuf = include_bytes!("../../tests/fixtures/bom-1.5.cdx.bin");
+        let expected = CycloneDXBom::parse(json).unwrap();
+        for (format, data) in [(Format::Xml, &xml[..]), (Format::Protobuf, &protobuf[..])] {
+            assert_eq!(Some(format), Format::detect(data));
+            let sbom = CycloneDXBom::parse(data).unwrap();
+            assert_same(
+                &format!("{format:?}"),
+                &encodable(&expected, format),
+                &encodable(&sbom, format),
+            );
+        }
+    }
+
+    #[test]
+    fn test_xml_1_4() {
+        let xml = br#"<?xml version="1.0" encoding="UTF-8"?>
+            <bom xmlns="http://cyclonedx.org/schema/bom/1.4" serialNumber="urn:uuid:3e671687-395b-41f5-a30f-a58921a69b79" version="1">
+              <metadata>
+                <timestamp>2023-08-01T10:00:00+02:00</timestamp>
+                <tools><tool><vendor>CycloneDX</vendor><name>cargo-cyclonedx</name></tool></tools>
+              </metadata>
+              <components>
+                <component type="library" bom-ref="pkg:cargo/serde@1.0.0">
+                  <name>serde</name>
+                  <version>1.0.0</version>
+                  <hashes><hash alg="SHA-256">abc</hash></hashes>
+                  <licenses><expression>MIT OR Apache-2.0</expression></licenses>
+                  <purl>pkg:cargo/serde@1.0.0</purl>
+                  <externalReferences>
+                    <reference type="vcs"><url>https://github.com/serde-rs/serde</url></reference>
+                  </externalReferences>
+                </component>
+              </components>
+              <dependencies>
+                <dependency ref="app"><dependency ref="pkg:cargo/serde@1.0.0"/></dependency>
+              </dependencies>
+            </bom>"#;
+        let sbom = CycloneDXBom::parse(xml).unwrap();
+        assert_eq!("1.4", sbom.spec_version);
+        let Some(crate::Tools::Legacy(tools)) = sbom.metadata.unwrap().tools else {
+            panic!("Expected the legacy tools")
+        };
+        assert_eq!(Some("cargo-cyclonedx"), tools[0].name.as_deref());
+        let component = &sbom.components[0];
+        assert_eq!("abc", component.hashes.as_ref().unwrap()[0].content);
+        assert_eq!(
+            Some("MIT OR Apache-2.0"),
+            component.licenses.as_ref().unwrap()[0].expression.as_deref()
+        );
+        assert_eq!("vcs", component.external_references.as_ref().unwrap()[0].ref_type);
+        let dependencies = sbom.dependencies.unwrap();
+        assert_eq!(Some(vec!["pkg:cargo/serde@1.0.0".to_string()]), dependencies[0].depends_on);
+    }
+}
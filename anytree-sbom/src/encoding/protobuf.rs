@@ -0,0 +1,345 @@
+//! CycloneDX protobuf, see `bom-1.5.proto` of the specification. The wire
+//! format is simple enough to be read and written field by field with the
+//! numbers in [`super::schema`].
+
+use anyhow::bail;
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use serde_json::{Map, Number, Value};
+
+use super::schema::{Kind, Type, BOM, COMPONENT_DATA, DEPENDENCY, PROPERTY, TOOL};
+use super::{array, enum_name, enum_number, object, string};
+
+const VARINT: u64 = 0;
+const I64: u64 = 1;
+const LEN: u64 = 2;
+const I32: u64 = 5;
+
+pub(super) fn read(data: &[u8]) -> anyhow::Result<Value> {
+    let mut bom = read_message(&BOM, data)?;
+    bom.insert("bomFormat".to_string(), "CycloneDX".into());
+    Ok(Value::Object(bom))
+}
+
+/// `data` is a protobuf message with the spec version, the first field of
+/// `Bom`
+pub(super) fn is_bom(data: &[u8]) -> bool {
+    fields(data).map_or(false, |fields| {
+        fields.iter().any(|(tag, payload)| {
+            matches!(payload, Payload::Len(version) if *tag == 1 && std::str::from_utf8(version).is_ok())
+        })
+    })
+}
+
+pub(super) fn write(bom: &Value) -> anyhow::Result<Vec<u8>> {
+    message(&BOM, object(bom)?)
+}
+
+fn wire_type(kind: Kind) -> u64 {
+    match kind {
+        Kind::Int | Kind::Bool | Kind::Enum(_) => VARINT,
+        Kind::Float => I32,
+        Kind::Double => I64,
+        _ => LEN,
+    }
+}
+
+fn write_varint(mut value: u64, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn write_bytes(tag: u32, bytes: &[u8], out: &mut Vec<u8>) {
+    write_varint(u64::from(tag) << 3 | LEN, out);
+    write_varint(bytes.len() as u64, out);
+    out.extend_from_slice(bytes);
+}
+
+fn message(ty: &Type, object: &Map<String, Value>) -> anyhow::Result<Vec<u8>> {
+    let mut out = vec![];
+    for field in ty.fields.iter().filter(|field| field.tag != 0) {
+        let value = match object.get(field.json) {
+            None | Some(Value::Null) => continue,
+            Some(value) => value,
+        };
+        let items = match (field.kind, value) {
+            (Kind::EnvironmentVars | Kind::Datasets | Kind::Tools, Value::Array(items)) => items,
+            _ if field.repeated => array(value)?,
+            _ => std::slice::from_ref(value),
+        };
+        if field.repeated && wire_type(field.kind) != LEN {
+            let mut packed = vec![];
+            for item in items {
+                write_payload(field.kind, item, &mut packed)?;
+            }
+            write_bytes(field.tag, &packed, &mut out);
+            continue;
+        }
+        for item in items {
+            write_varint(u64::from(field.tag) << 3 | wire_type(field.kind), &mut out);
+            write_payload(field.kind, item, &mut out)?;
+        }
+    }
+    Ok(out)
+}
+
+/// Value of the field without the key, with the length for the `LEN` fields
+fn write_payload(kind: Kind, value: &Value, out: &mut Vec<u8>) -> anyhow::Result<()> {
+    let number = |value: &Value| {
+        value.as_f64().ok_or_else(|| anyhow::format_err!("Expected number, got {value}"))
+    };
+    let bytes = match kind {
+        Kind::Int => {
+            let int = value
+                .as_i64()
+                .ok_or_else(|| anyhow::format_err!("Expected integer, got {value}"))?;
+            write_varint(int as u64, out);
+            return Ok(());
+        }
+        Kind::Bool => {
+            let bool =
+                value.as_bool().ok_or_else(|| anyhow::format_err!("Expected bool, got {value}"))?;
+            write_varint(u64::from(bool), out);
+            return Ok(());
+        }
+        Kind::Enum(values) => {
+            // Negative numbers are sign extended like int64
+            write_varint(i64::from(enum_number(values, string(value)?)?) as u64, out);
+            return Ok(());
+        }
+        Kind::Float => {
+            out.extend_from_slice(&(number(value)? as f32).to_le_bytes());
+            return Ok(());
+        }
+        Kind::Double => {
+            out.extend_from_slice(&number(value)?.to_le_bytes());
+            return Ok(());
+        }
+        Kind::Str => string(value)?.as_bytes().to_vec(),
+        Kind::Time => {
+            let time = DateTime::parse_from_rfc3339(string(value)?)?;
+            let mut timestamp = vec![];
+            write_varint(1 << 3 | VARINT, &mut timestamp);
+            write_varint(time.timestamp() as u64, &mut timestamp);
+            write_varint(2 << 3 | VARINT, &mut timestamp);
+            write_varint(u64::from(time.timestamp_subsec_nanos()), &mut timestamp);
+            timestamp
+        }
+        Kind::Msg(ty) => message(ty, object(value)?)?,
+        Kind::DependsOn => {
+            let mut dependency = vec![];
+            write_bytes(1, string(value)?.as_bytes(), &mut dependency);
+            dependency
+        }
+        Kind::Tools => message(&TOOL, object(value)?)?,
+        Kind::EnvironmentVars => {
+            let mut choice = vec![];
+            match value {
+                Value::String(var) => write_bytes(2, var.as_bytes(), &mut choice),
+                _ => write_bytes(1, &message(&PROPERTY, object(value)?)?, &mut choice),
+            }
+            choice
+        }
+        Kind::Datasets => {
+            let dataset = object(value)?;
+            let mut choice = vec![];
+            match dataset.get("ref") {
+                Some(reference) if !dataset.contains_key("type") => {
+                    write_bytes(2, string(reference)?.as_bytes(), &mut choice)
+                }
+                _ => write_bytes(1, &message(&COMPONENT_DATA, dataset)?, &mut choice),
+            }
+            choice
+        }
+    };
+    write_varint(bytes.len() as u64, out);
+    out.extend_from_slice(&bytes);
+    Ok(())
+}
+
+enum Payload<'a> {
+    Varint(u64),
+    I64([u8; 8]),
+    Len(&'a [u8]),
+    I32([u8; 4]),
+}
+
+fn read_varint(data: &mut &[u8]) -> anyhow::Result<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let Some((&byte, rest)) = data.split_first() else { bail!("Truncated protobuf") };
+        *data = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte < 0x80 {
+            return Ok(value);
+        }
+    }
+    bail!("Too long varint")
+}
+
+fn take<'a>(data: &mut &'a [u8], len: usize) -> anyhow::Result<&'a [u8]> {
+    if data.len() < len {
+        bail!("Truncated protobuf");
+    }
+    let (head, rest) = data.split_at(len);
+    *data = rest;
+    Ok(head)
+}
+
+fn read_payload<'a>(wire_type: u64, data: &mut &'a [u8]) -> anyhow::Result<Payload<'a>> {
+    Ok(match wire_type {
+        VARINT => Payload::Varint(read_varint(data)?),
+        I64 => Payload::I64(take(data, 8)?.try_into()?),
+        LEN => {
+            let len = read_varint(data)?;
+            Payload::Len(take(data, usize::try_from(len)?)?)
+        }
+        I32 => Payload::I32(take(data, 4)?.try_into()?),
+        _ => bail!("Unsupported protobuf wire type {wire_type}"),
+    })
+}
+
+fn fields(mut data: &[u8]) -> anyhow::Result<Vec<(u64, Payload<'_>)>> {
+    let mut fields = vec![];
+    while !data.is_empty() {
+        let key = read_varint(&mut data)?;
+        fields.push((key >> 3, read_payload(key & 7, &mut data)?));
+    }
+    Ok(fields)
+}
+
+fn read_message(ty: &Type, data: &[u8]) -> anyhow::Result<Map<String, Value>> {
+    let mut object = Map::new();
+    for (tag, payload) in fields(data)? {
+        let Some(field) = ty.fields.iter().find(|field| u64::from(field.tag) == tag) else {
+            continue;
+        };
+        let values = match payload {
+            Payload::Len(mut packed) if wire_type(field.kind) != LEN => {
+                let mut values = vec![];
+                while !packed.is_empty() {
+                    values.push(read_value(
+                        field.kind,
+                        read_payload(wire_type(field.kind), &mut packed)?,
+                    )?);
+                }
+                values
+            }
+            payload => vec![read_value(field.kind, payload)?],
+        };
+        let list = field.repeated
+            || matches!(field.kind, Kind::EnvironmentVars | Kind::Datasets | Kind::Tools);
+        if list {
+            let Value::Array(items) =
+                object.entry(field.json).or_insert_with(|| Value::Array(vec![]))
+            else {
+                unreachable!("Only arrays are inserted for lists")
+            };
+            items.extend(values);
+        } else if let Some(value) = values.into_iter().last() {
+            object.insert(field.json.to_string(), value);
+        }
+    }
+
+    for field in ty.fields.iter().filter(|field| field.tag != 0 && field.required) {
+        // Default values are not written to proto3
+        let default = match field.kind {
+            _ if field.repeated => Value::Array(vec![]),
+            Kind::Str => "".into(),
+            Kind::Int => 0.into(),
+            Kind::Bool => false.into(),
+            Kind::Float | Kind::Double => 0.0.into(),
+            Kind::Enum(values) => match enum_name(values, 0) {
+                Ok(name) => name.into(),
+                Err(_) => continue,
+            },
+            _ => continue,
+        };
+        object.entry(field.json).or_insert(default);
+    }
+    if let Some(Value::Array(tools)) = object.get("tools") {
+        if let Some(tools) = tools_object(tools) {
+            object.insert("tools".to_string(), tools);
+        }
+    }
+    Ok(object)
+}
+
+/// Tools of 1.5 are in one tool with only the components and services
+fn tools_object(tools: &[Value]) -> Option<Value> {
+    let [Value::Object(tool)] = tools else { return None };
+    if tool.is_empty() || !tool.keys().all(|key| key == "components" || key == "services") {
+        return None;
+    }
+    Some(Value::Object(tool.clone()))
+}
+
+/// Field 1 or 2 of the message with a `oneof` of two fields
+fn read_choice(
+    data: &[u8],
+    first: impl Fn(&[u8]) -> anyhow::Result<Value>,
+    second: impl Fn(&str) -> Value,
+) -> anyhow::Result<Value> {
+    let mut value = None;
+    for (tag, payload) in fields(data)? {
+        match (tag, payload) {
+            (1, Payload::Len(bytes)) => value = Some(first(bytes)?),
+            (2, Payload::Len(bytes)) => value = Some(second(std::str::from_utf8(bytes)?)),
+            _ => {}
+        }
+    }
+    value.ok_or_else(|| anyhow::format_err!("Empty protobuf oneof"))
+}
+
+fn read_value(kind: Kind, payload: Payload) -> anyhow::Result<Value> {
+    let number = |number: f64| {
+        Number::from_f64(number)
+            .map(Value::Number)
+            .ok_or_else(|| anyhow::format_err!("{number} is not a JSON number"))
+    };
+    Ok(match (kind, payload) {
+        (Kind::Int, Payload::Varint(int)) => (int as i64).into(),
+        (Kind::Bool, Payload::Varint(bool)) => (bool != 0).into(),
+        (Kind::Enum(values), Payload::Varint(value)) => enum_name(values, value as i32)?.into(),
+        // Through the shortest decimal of the f32, so 0.1 doesn't become
+        // 0.10000000149011612
+        (Kind::Float, Payload::I32(bytes)) => {
+            number(f32::from_le_bytes(bytes).to_string().parse()?)?
+        }
+        (Kind::Double, Payload::I64(bytes)) => number(f64::from_le_bytes(bytes))?,
+        (Kind::Str, Payload::Len(bytes)) => std::str::from_utf8(bytes)?.into(),
+        (Kind::Time, Payload::Len(bytes)) => {
+            let (mut seconds, mut nanos) = (0, 0);
+            for (tag, payload) in fields(bytes)? {
+                match (tag, payload) {
+                    (1, Payload::Varint(value)) => seconds = value as i64,
+                    (2, Payload::Varint(value)) => nanos = value as u32,
+                    _ => {}
+                }
+            }
+            let Some(time) = Utc.timestamp_opt(seconds, nanos).single() else {
+                bail!("Invalid timestamp {seconds}.{nanos}")
+            };
+            time.to_rfc3339_opts(SecondsFormat::AutoSi, true).into()
+        }
+        (Kind::Msg(ty), Payload::Len(bytes)) => Value::Object(read_message(ty, bytes)?),
+        (Kind::DependsOn, Payload::Len(bytes)) => {
+            let mut dependency = read_message(&DEPENDENCY, bytes)?;
+            dependency.remove("ref").unwrap_or_default()
+        }
+        (Kind::Tools, Payload::Len(bytes)) => Value::Object(read_message(&TOOL, bytes)?),
+        (Kind::EnvironmentVars, Payload::Len(bytes)) => read_choice(
+            bytes,
+            |property| Ok(Value::Object(read_message(&PROPERTY, property)?)),
+            |var: &str| Value::from(var),
+        )?,
+        (Kind::Datasets, Payload::Len(bytes)) => read_choice(
+            bytes,
+            |dataset| Ok(Value::Object(read_message(&COMPONENT_DATA, dataset)?)),
+            |reference| serde_json::json!({ "ref": reference }),
+        )?,
+        _ => bail!("Unexpected protobuf wire type"),
+    })
+}
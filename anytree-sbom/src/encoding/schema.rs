@@ -0,0 +1,1107 @@
+//! Where the fields of the JSON BOM go in the XML and protobuf encodings:
+//! element names and protobuf field numbers from `bom-1.5.xsd` and
+//! `bom-1.5.proto`
+
+use Kind::{Bool, Double, Float, Int, Msg, Str, Time};
+
+pub(super) struct Type {
+    pub fields: &'static [Field],
+}
+
+impl Type {
+    pub fn field(&self, json: &str) -> Option<&'static Field> {
+        self.fields.iter().find(|field| field.json == json)
+    }
+}
+
+pub(super) struct Field {
+    /// Key in the JSON object
+    pub json: &'static str,
+    /// Protobuf field number, 0 when the field is not in the proto
+    pub tag: u32,
+    pub xml: Xml,
+    pub kind: Kind,
+    /// JSON array, repeated field in the proto
+    pub repeated: bool,
+    /// Required in JSON, so absent in the proto means the default value
+    pub required: bool,
+}
+
+#[derive(Clone, Copy)]
+pub(super) enum Xml {
+    Attr(&'static str),
+    /// Child element, repeated for arrays
+    Elem(&'static str),
+    /// Text of the element itself
+    Text,
+    /// Array items in elements named by the second string inside the wrapper
+    /// element. With no item name the fields of the items are put right into
+    /// the wrapper.
+    List(&'static str, &'static str),
+    /// Array of strings in the `ref` attributes of items in the wrapper,
+    /// with no wrapper name the items are right in the element
+    Refs(&'static str, &'static str),
+    /// Attribute of the child element
+    AttrOf(&'static str, &'static str),
+    None,
+}
+
+#[derive(Clone, Copy)]
+pub(super) enum Kind {
+    Str,
+    Int,
+    Bool,
+    Float,
+    Double,
+    /// RFC 3339 string, `google.protobuf.Timestamp` in the proto
+    Time,
+    /// JSON strings and their protobuf numbers
+    Enum(&'static [(&'static str, i32)]),
+    Msg(&'static Type),
+    /// `dependsOn` refs, `Dependency` messages in the proto
+    DependsOn,
+    /// Legacy array of tools or the object with components and services
+    Tools,
+    /// Array of properties and `NAME=value` strings
+    EnvironmentVars,
+    /// Array of datasets and `{"ref": ...}` objects
+    Datasets,
+}
+
+const fn field(
+    json: &'static str,
+    tag: u32,
+    xml: Xml,
+    kind: Kind,
+    repeated: bool,
+    required: bool,
+) -> Field {
+    Field { json, tag, xml, kind, repeated, required }
+}
+
+const fn elem(json: &'static str, tag: u32, kind: Kind) -> Field {
+    field(json, tag, Xml::Elem(json), kind, false, false)
+}
+
+const fn req_elem(json: &'static str, tag: u32, kind: Kind) -> Field {
+    field(json, tag, Xml::Elem(json), kind, false, true)
+}
+
+const fn attr(json: &'static str, tag: u32, kind: Kind) -> Field {
+    field(json, tag, Xml::Attr(json), kind, false, false)
+}
+
+const fn req_attr(json: &'static str, tag: u32, kind: Kind) -> Field {
+    field(json, tag, Xml::Attr(json), kind, false, true)
+}
+
+const fn list(json: &'static str, tag: u32, item: &'static str, kind: Kind) -> Field {
+    field(json, tag, Xml::List(json, item), kind, true, false)
+}
+
+/// Repeated elements without a wrapper
+const fn rep(json: &'static str, tag: u32, kind: Kind) -> Field {
+    field(json, tag, Xml::Elem(json), kind, true, false)
+}
+
+const fn refs(json: &'static str, tag: u32, item: &'static str) -> Field {
+    field(json, tag, Xml::Refs(json, item), Str, true, false)
+}
+
+const fn bom_ref(tag: u32) -> Field {
+    attr("bom-ref", tag, Str)
+}
+
+const fn properties(tag: u32) -> Field {
+    list("properties", tag, "property", Msg(&PROPERTY))
+}
+
+const fn licenses(tag: u32) -> Field {
+    list("licenses", tag, "", Msg(&LICENSE_CHOICE))
+}
+
+const fn external_references(tag: u32) -> Field {
+    list("externalReferences", tag, "reference", Msg(&EXTERNAL_REFERENCE))
+}
+
+const fn hashes(tag: u32) -> Field {
+    list("hashes", tag, "hash", Msg(&HASH))
+}
+
+const fn resource_references(tag: u32) -> Field {
+    list("resourceReferences", tag, "resourceReference", Msg(&RESOURCE_REFERENCE))
+}
+
+const fn task_types(tag: u32) -> Field {
+    field("taskTypes", tag, Xml::List("taskTypes", "taskType"), Kind::Enum(TASK_TYPES), true, true)
+}
+
+const fn signature() -> Field {
+    field("signature", 0, Xml::None, Str, false, false)
+}
+
+pub(super) static BOM: Type = Type {
+    fields: &[
+        field("bomFormat", 0, Xml::None, Str, false, false),
+        field("specVersion", 1, Xml::None, Str, false, true),
+        attr("serialNumber", 3, Str),
+        attr("version", 2, Int),
+        elem("metadata", 4, Msg(&METADATA)),
+        list("components", 5, "component", Msg(&COMPONENT)),
+        list("services", 6, "service", Msg(&SERVICE)),
+        external_references(7),
+        list("dependencies", 8, "dependency", Msg(&DEPENDENCY)),
+        list("compositions", 9, "composition", Msg(&COMPOSITION)),
+        properties(12),
+        list("vulnerabilities", 10, "vulnerability", Msg(&VULNERABILITY)),
+        list("annotations", 11, "annotation", Msg(&ANNOTATION)),
+        list("formulation", 13, "formula", Msg(&FORMULA)),
+        signature(),
+        field("$schema", 0, Xml::None, Str, false, false),
+    ],
+};
+
+static METADATA: Type = Type {
+    fields: &[
+        elem("timestamp", 1, Time),
+        list("lifecycles", 9, "lifecycle", Msg(&LIFECYCLE)),
+        field("tools", 2, Xml::Elem("tools"), Kind::Tools, false, false),
+        list("authors", 3, "author", Msg(&ORGANIZATIONAL_CONTACT)),
+        elem("component", 4, Msg(&COMPONENT)),
+        elem("manufacture", 5, Msg(&ORGANIZATIONAL_ENTITY)),
+        elem("supplier", 6, Msg(&ORGANIZATIONAL_ENTITY)),
+        licenses(7),
+        properties(8),
+    ],
+};
+
+static LIFECYCLE: Type = Type {
+    fields: &[
+        elem("phase", 1, Kind::Enum(LIFECYCLE_PHASES)),
+        elem("name", 2, Str),
+        elem("description", 3, Str),
+    ],
+};
+
+/// Legacy tool, or in 1.5 the only tool with the components and services
+pub(super) static TOOL: Type = Type {
+    fields: &[
+        elem("vendor", 1, Str),
+        elem("name", 2, Str),
+        elem("version", 3, Str),
+        hashes(4),
+        external_references(5),
+        list("components", 6, "component", Msg(&COMPONENT)),
+        list("services", 7, "service", Msg(&SERVICE)),
+    ],
+};
+
+static ATTACHMENT: Type = Type {
+    fields: &[
+        field("contentType", 1, Xml::Attr("content-type"), Str, false, false),
+        attr("encoding", 2, Str),
+        field("content", 3, Xml::Text, Str, false, true),
+    ],
+};
+
+static HASH: Type = Type {
+    fields: &[
+        req_attr("alg", 1, Kind::Enum(HASH_ALGS)),
+        field("content", 2, Xml::Text, Str, false, true),
+    ],
+};
+
+pub(super) static PROPERTY: Type =
+    Type { fields: &[req_attr("name", 1, Str), field("value", 2, Xml::Text, Str, false, true)] };
+
+static EXTERNAL_REFERENCE: Type = Type {
+    fields: &[
+        req_attr("type", 1, Kind::Enum(EXTERNAL_REFERENCE_TYPES)),
+        req_elem("url", 2, Str),
+        elem("comment", 3, Str),
+        hashes(4),
+    ],
+};
+
+static ORGANIZATIONAL_CONTACT: Type = Type {
+    fields: &[bom_ref(1), elem("name", 2, Str), elem("email", 3, Str), elem("phone", 4, Str)],
+};
+
+static ORGANIZATIONAL_ENTITY: Type = Type {
+    fields: &[
+        bom_ref(1),
+        elem("name", 2, Str),
+        rep("url", 3, Str),
+        rep("contact", 4, Msg(&ORGANIZATIONAL_CONTACT)),
+    ],
+};
+
+static ORGANIZATION_OR_INDIVIDUAL: Type = Type {
+    fields: &[
+        elem("organization", 1, Msg(&ORGANIZATIONAL_ENTITY)),
+        elem("individual", 2, Msg(&ORGANIZATIONAL_CONTACT)),
+    ],
+};
+
+static IDENTIFIABLE_ACTION: Type =
+    Type { fields: &[elem("timestamp", 1, Time), elem("name", 2, Str), elem("email", 3, Str)] };
+
+static SOURCE: Type = Type { fields: &[elem("name", 1, Str), elem("url", 2, Str)] };
+
+static ISSUE: Type = Type {
+    fields: &[
+        req_attr("type", 1, Kind::Enum(ISSUE_TYPES)),
+        elem("id", 2, Str),
+        elem("name", 3, Str),
+        elem("description", 4, Str),
+        elem("source", 5, Msg(&SOURCE)),
+        list("references", 6, "url", Str),
+    ],
+};
+
+static RELEASE_NOTES: Type = Type {
+    fields: &[
+        req_elem("type", 1, Str),
+        elem("title", 2, Str),
+        elem("featuredImage", 3, Str),
+        elem("socialImage", 4, Str),
+        elem("description", 5, Str),
+        elem("timestamp", 6, Time),
+        list("aliases", 7, "alias", Str),
+        list("tags", 8, "tag", Str),
+        list("resolves", 9, "issue", Msg(&ISSUE)),
+        list("notes", 10, "note", Msg(&NOTE)),
+        properties(11),
+    ],
+};
+
+static NOTE: Type = Type { fields: &[elem("locale", 1, Str), elem("text", 2, Msg(&ATTACHMENT))] };
+
+static LICENSE_CHOICE: Type = Type {
+    fields: &[
+        elem("license", 1, Msg(&LICENSE)),
+        elem("expression", 2, Str),
+        field("bom-ref", 0, Xml::AttrOf("expression", "bom-ref"), Str, false, false),
+    ],
+};
+
+static LICENSE: Type = Type {
+    fields: &[
+        bom_ref(5),
+        elem("id", 1, Str),
+        elem("name", 2, Str),
+        elem("text", 3, Msg(&ATTACHMENT)),
+        elem("url", 4, Str),
+        elem("licensing", 6, Msg(&LICENSING)),
+        properties(7),
+    ],
+};
+
+static LICENSING: Type = Type {
+    fields: &[
+        list("altIds", 1, "altId", Str),
+        elem("licensor", 2, Msg(&ORGANIZATION_OR_INDIVIDUAL)),
+        elem("licensee", 3, Msg(&ORGANIZATION_OR_INDIVIDUAL)),
+        elem("purchaser", 4, Msg(&ORGANIZATION_OR_INDIVIDUAL)),
+        elem("purchaseOrder", 5, Str),
+        list("licenseTypes", 6, "licenseType", Kind::Enum(LICENSE_TYPES)),
+        elem("lastRenewal", 7, Time),
+        elem("expiration", 8, Time),
+    ],
+};
+
+static COMPONENT: Type = Type {
+    fields: &[
+        req_attr("type", 1, Kind::Enum(COMPONENT_TYPES)),
+        attr("mime-type", 2, Str),
+        bom_ref(3),
+        elem("supplier", 4, Msg(&ORGANIZATIONAL_ENTITY)),
+        elem("author", 5, Str),
+        elem("publisher", 6, Str),
+        elem("group", 7, Str),
+        req_elem("name", 8, Str),
+        elem("version", 9, Str),
+        elem("description", 10, Str),
+        elem("scope", 11, Kind::Enum(SCOPES)),
+        hashes(12),
+        licenses(13),
+        elem("copyright", 14, Str),
+        elem("cpe", 15, Str),
+        elem("purl", 16, Str),
+        elem("swid", 17, Msg(&SWID)),
+        elem("modified", 18, Bool),
+        elem("pedigree", 19, Msg(&PEDIGREE)),
+        external_references(20),
+        properties(21),
+        list("components", 22, "component", Msg(&COMPONENT)),
+        elem("evidence", 23, Msg(&EVIDENCE)),
+        elem("releaseNotes", 24, Msg(&RELEASE_NOTES)),
+        elem("modelCard", 25, Msg(&MODEL_CARD)),
+        rep("data", 26, Msg(&COMPONENT_DATA)),
+        signature(),
+    ],
+};
+
+static SWID: Type = Type {
+    fields: &[
+        req_attr("tagId", 1, Str),
+        req_attr("name", 2, Str),
+        attr("version", 3, Str),
+        attr("tagVersion", 4, Int),
+        attr("patch", 5, Bool),
+        elem("text", 6, Msg(&ATTACHMENT)),
+        elem("url", 7, Str),
+    ],
+};
+
+static PEDIGREE: Type = Type {
+    fields: &[
+        list("ancestors", 1, "component", Msg(&COMPONENT)),
+        list("descendants", 2, "component", Msg(&COMPONENT)),
+        list("variants", 3, "component", Msg(&COMPONENT)),
+        list("commits", 4, "commit", Msg(&COMMIT)),
+        list("patches", 5, "patch", Msg(&PATCH)),
+        elem("notes", 6, Str),
+    ],
+};
+
+static COMMIT: Type = Type {
+    fields: &[
+        elem("uid", 1, Str),
+        elem("url", 2, Str),
+        elem("author", 3, Msg(&IDENTIFIABLE_ACTION)),
+        elem("committer", 4, Msg(&IDENTIFIABLE_ACTION)),
+        elem("message", 5, Str),
+    ],
+};
+
+static PATCH: Type = Type {
+    fields: &[
+        req_attr("type", 1, Kind::Enum(PATCH_TYPES)),
+        elem("diff", 2, Msg(&DIFF)),
+        list("resolves", 3, "issue", Msg(&ISSUE)),
+    ],
+};
+
+static DIFF: Type = Type { fields: &[elem("text", 1, Msg(&ATTACHMENT)), elem("url", 2, Str)] };
+
+static EVIDENCE: Type = Type {
+    fields: &[
+        elem("identity", 3, Msg(&IDENTITY)),
+        list("occurrences", 4, "occurrence", Msg(&OCCURRENCE)),
+        elem("callstack", 5, Msg(&CALLSTACK)),
+        licenses(1),
+        list("copyright", 2, "text", Msg(&COPYRIGHT)),
+    ],
+};
+
+static IDENTITY: Type = Type {
+    fields: &[
+        req_elem("field", 1, Kind::Enum(IDENTITY_FIELDS)),
+        elem("confidence", 2, Float),
+        list("methods", 3, "method", Msg(&IDENTITY_METHOD)),
+        refs("tools", 4, "tool"),
+    ],
+};
+
+static IDENTITY_METHOD: Type = Type {
+    fields: &[
+        req_elem("technique", 1, Kind::Enum(TECHNIQUES)),
+        req_elem("confidence", 2, Float),
+        elem("value", 3, Str),
+    ],
+};
+
+static OCCURRENCE: Type = Type { fields: &[bom_ref(1), req_elem("location", 2, Str)] };
+
+static CALLSTACK: Type = Type { fields: &[list("frames", 1, "frame", Msg(&FRAME))] };
+
+static FRAME: Type = Type {
+    fields: &[
+        elem("package", 1, Str),
+        req_elem("module", 2, Str),
+        elem("function", 3, Str),
+        list("parameters", 4, "parameter", Str),
+        elem("line", 5, Int),
+        elem("column", 6, Int),
+        elem("fullFilename", 7, Str),
+    ],
+};
+
+static COPYRIGHT: Type = Type { fields: &[field("text", 1, Xml::Text, Str, false, true)] };
+
+pub(super) static COMPONENT_DATA: Type = Type {
+    fields: &[
+        bom_ref(1),
+        req_elem("type", 2, Kind::Enum(DATA_TYPES)),
+        elem("name", 3, Str),
+        elem("contents", 4, Msg(&DATA_CONTENTS)),
+        elem("classification", 5, Str),
+        rep("sensitiveData", 6, Str),
+        elem("graphics", 7, Msg(&GRAPHICS)),
+        elem("description", 8, Str),
+        elem("governance", 9, Msg(&DATA_GOVERNANCE)),
+    ],
+};
+
+static DATA_CONTENTS: Type =
+    Type { fields: &[elem("attachment", 1, Msg(&ATTACHMENT)), elem("url", 2, Str), properties(3)] };
+
+static GRAPHICS: Type = Type {
+    fields: &[elem("description", 1, Str), list("collection", 2, "graphic", Msg(&GRAPHIC))],
+};
+
+static GRAPHIC: Type = Type { fields: &[elem("name", 1, Str), elem("image", 2, Msg(&ATTACHMENT))] };
+
+static DATA_GOVERNANCE: Type = Type {
+    fields: &[
+        list("custodians", 1, "custodian", Msg(&DATA_GOVERNANCE_PARTY)),
+        list("stewards", 2, "steward", Msg(&DATA_GOVERNANCE_PARTY)),
+        list("owners", 3, "owner", Msg(&DATA_GOVERNANCE_PARTY)),
+    ],
+};
+
+static DATA_GOVERNANCE_PARTY: Type = Type {
+    fields: &[
+        elem("organization", 1, Msg(&ORGANIZATIONAL_ENTITY)),
+        elem("contact", 2, Msg(&ORGANIZATIONAL_CONTACT)),
+    ],
+};
+
+static MODEL_CARD: Type = Type {
+    fields: &[
+        bom_ref(1),
+        elem("modelParameters", 2, Msg(&MODEL_PARAMETERS)),
+        elem("quantitativeAnalysis", 3, Msg(&QUANTITATIVE_ANALYSIS)),
+        elem("considerations", 4, Msg(&CONSIDERATIONS)),
+        properties(0),
+    ],
+};
+
+static MODEL_PARAMETERS: Type = Type {
+    fields: &[
+        elem("approach", 1, Msg(&APPROACH)),
+        elem("task", 2, Str),
+        elem("architectureFamily", 3, Str),
+        elem("modelArchitecture", 4, Str),
+        field("datasets", 5, Xml::Elem("datasets"), Kind::Datasets, false, false),
+        list("inputs", 6, "input", Msg(&INPUT_OUTPUT_FORMAT)),
+        list("outputs", 7, "output", Msg(&INPUT_OUTPUT_FORMAT)),
+    ],
+};
+
+static APPROACH: Type = Type { fields: &[elem("type", 1, Kind::Enum(APPROACH_TYPES))] };
+
+static INPUT_OUTPUT_FORMAT: Type = Type { fields: &[elem("format", 1, Str)] };
+
+static QUANTITATIVE_ANALYSIS: Type = Type {
+    fields: &[
+        list("performanceMetrics", 1, "performanceMetric", Msg(&PERFORMANCE_METRIC)),
+        elem("graphics", 2, Msg(&GRAPHICS)),
+    ],
+};
+
+static PERFORMANCE_METRIC: Type = Type {
+    fields: &[
+        elem("type", 1, Str),
+        elem("value", 2, Str),
+        elem("slice", 3, Str),
+        elem("confidenceInterval", 4, Msg(&CONFIDENCE_INTERVAL)),
+    ],
+};
+
+static CONFIDENCE_INTERVAL: Type =
+    Type { fields: &[elem("lowerBound", 1, Str), elem("upperBound", 2, Str)] };
+
+static CONSIDERATIONS: Type = Type {
+    fields: &[
+        list("users", 1, "user", Str),
+        list("useCases", 2, "useCase", Str),
+        list("technicalLimitations", 3, "technicalLimitation", Str),
+        list("performanceTradeoffs", 4, "performanceTradeoff", Str),
+        list("ethicalConsiderations", 5, "ethicalConsideration", Msg(&ETHICAL_CONSIDERATION)),
+        list("fairnessAssessments", 6, "fairnessAssessment", Msg(&FAIRNESS_ASSESSMENT)),
+    ],
+};
+
+static ETHICAL_CONSIDERATION: Type =
+    Type { fields: &[elem("name", 1, Str), elem("mitigationStrategy", 2, Str)] };
+
+static FAIRNESS_ASSESSMENT: Type = Type {
+    fields: &[
+        elem("groupAtRisk", 1, Str),
+        elem("benefits", 2, Str),
+        elem("harms", 3, Str),
+        elem("mitigationStrategy", 4, Str),
+    ],
+};
+
+static SERVICE: Type = Type {
+    fields: &[
+        bom_ref(1),
+        elem("provider", 2, Msg(&ORGANIZATIONAL_ENTITY)),
+        elem("group", 3, Str),
+        req_elem("name", 4, Str),
+        elem("version", 5, Str),
+        elem("description", 6, Str),
+        list("endpoints", 7, "endpoint", Str),
+        elem("authenticated", 8, Bool),
+        elem("x-trust-boundary", 9, Bool),
+        elem("trustZone", 16, Str),
+        list("data", 10, "dataflow", Msg(&SERVICE_DATA)),
+        licenses(11),
+        external_references(12),
+        properties(14),
+        list("services", 13, "service", Msg(&SERVICE)),
+        elem("releaseNotes", 15, Msg(&RELEASE_NOTES)),
+        signature(),
+    ],
+};
+
+static SERVICE_DATA: Type = Type {
+    fields: &[
+        attr("name", 3, Str),
+        attr("description", 4, Str),
+        field("flow", 1, Xml::AttrOf("classification", "flow"), Kind::Enum(FLOWS), false, true),
+        req_elem("classification", 2, Str),
+        elem("governance", 5, Msg(&DATA_GOVERNANCE)),
+        list("source", 6, "url", Str),
+        list("destination", 7, "url", Str),
+    ],
+};
+
+pub(super) static DEPENDENCY: Type = Type {
+    fields: &[
+        req_attr("ref", 1, Str),
+        field("dependsOn", 2, Xml::Refs("", "dependency"), Kind::DependsOn, true, false),
+    ],
+};
+
+static COMPOSITION: Type = Type {
+    fields: &[
+        bom_ref(5),
+        req_elem("aggregate", 1, Kind::Enum(AGGREGATES)),
+        refs("assemblies", 2, "assembly"),
+        refs("dependencies", 3, "dependency"),
+        refs("vulnerabilities", 4, "vulnerability"),
+        signature(),
+    ],
+};
+
+static ANNOTATION: Type = Type {
+    fields: &[
+        bom_ref(1),
+        refs("subjects", 2, "subject"),
+        elem("annotator", 3, Msg(&ANNOTATOR)),
+        elem("timestamp", 4, Time),
+        req_elem("text", 5, Str),
+        signature(),
+    ],
+};
+
+static ANNOTATOR: Type = Type {
+    fields: &[
+        elem("organization", 1, Msg(&ORGANIZATIONAL_ENTITY)),
+        elem("individual", 2, Msg(&ORGANIZATIONAL_CONTACT)),
+        elem("component", 3, Msg(&COMPONENT)),
+        elem("service", 4, Msg(&SERVICE)),
+    ],
+};
+
+static VULNERABILITY: Type = Type {
+    fields: &[
+        bom_ref(1),
+        elem("id", 2, Str),
+        elem("source", 3, Msg(&SOURCE)),
+        list("references", 4, "reference", Msg(&VULNERABILITY_REFERENCE)),
+        list("ratings", 5, "rating", Msg(&RATING)),
+        list("cwes", 6, "cwe", Int),
+        elem("description", 7, Str),
+        elem("detail", 8, Str),
+        elem("recommendation", 9, Str),
+        elem("workaround", 21, Str),
+        elem("proofOfConcept", 20, Msg(&PROOF_OF_CONCEPT)),
+        list("advisories", 10, "advisory", Msg(&ADVISORY)),
+        elem("created", 11, Time),
+        elem("published", 12, Time),
+        elem("updated", 13, Time),
+        elem("rejected", 19, Time),
+        elem("credits", 14, Msg(&CREDITS)),
+        field("tools", 15, Xml::Elem("tools"), Kind::Tools, false, false),
+        elem("analysis", 16, Msg(&ANALYSIS)),
+        list("affects", 17, "target", Msg(&AFFECT)),
+        properties(18),
+    ],
+};
+
+static VULNERABILITY_REFERENCE: Type =
+    Type { fields: &[req_elem("id", 1, Str), elem("source", 2, Msg(&SOURCE))] };
+
+static RATING: Type = Type {
+    fields: &[
+        elem("source", 1, Msg(&SOURCE)),
+        elem("score", 2, Double),
+        elem("severity", 3, Kind::Enum(SEVERITIES)),
+        elem("method", 4, Kind::Enum(SCORE_METHODS)),
+        elem("vector", 5, Str),
+        elem("justification", 6, Str),
+    ],
+};
+
+static PROOF_OF_CONCEPT: Type = Type {
+    fields: &[
+        elem("reproductionSteps", 1, Str),
+        elem("environment", 2, Str),
+        list("supportingMaterial", 3, "attachment", Msg(&ATTACHMENT)),
+    ],
+};
+
+static ADVISORY: Type = Type { fields: &[elem("title", 1, Str), req_elem("url", 2, Str)] };
+
+static CREDITS: Type = Type {
+    fields: &[
+        list("organizations", 1, "organization", Msg(&ORGANIZATIONAL_ENTITY)),
+        list("individuals", 2, "individual", Msg(&ORGANIZATIONAL_CONTACT)),
+    ],
+};
+
+static ANALYSIS: Type = Type {
+    fields: &[
+        elem("state", 1, Kind::Enum(ANALYSIS_STATES)),
+        elem("justification", 2, Kind::Enum(JUSTIFICATIONS)),
+        field(
+            "response",
+            3,
+            Xml::List("responses", "response"),
+            Kind::Enum(RESPONSES),
+            true,
+            false,
+        ),
+        elem("detail", 4, Str),
+        elem("firstIssued", 5, Time),
+        elem("lastUpdated", 6, Time),
+    ],
+};
+
+static AFFECT: Type = Type {
+    fields: &[req_elem("ref", 1, Str), list("versions", 2, "version", Msg(&AFFECTED_VERSION))],
+};
+
+static AFFECTED_VERSION: Type = Type {
+    fields: &[
+        elem("version", 1, Str),
+        elem("range", 2, Str),
+        elem("status", 3, Kind::Enum(AFFECTED_STATUSES)),
+    ],
+};
+
+static FORMULA: Type = Type {
+    fields: &[
+        bom_ref(1),
+        list("components", 2, "component", Msg(&COMPONENT)),
+        list("services", 3, "service", Msg(&SERVICE)),
+        list("workflows", 4, "workflow", Msg(&WORKFLOW)),
+        properties(5),
+    ],
+};
+
+static WORKFLOW: Type = Type {
+    fields: &[
+        req_attr("bom-ref", 1, Str),
+        req_elem("uid", 2, Str),
+        elem("name", 3, Str),
+        elem("description", 4, Str),
+        resource_references(5),
+        list("tasks", 6, "task", Msg(&TASK)),
+        list("taskDependencies", 7, "dependency", Msg(&DEPENDENCY)),
+        task_types(8),
+        elem("trigger", 9, Msg(&TRIGGER)),
+        list("steps", 10, "step", Msg(&STEP)),
+        list("inputs", 11, "input", Msg(&INPUT)),
+        list("outputs", 12, "output", Msg(&OUTPUT)),
+        elem("timeStart", 13, Time),
+        elem("timeEnd", 14, Time),
+        list("workspaces", 15, "workspace", Msg(&WORKSPACE)),
+        list("runtimeTopology", 16, "dependency", Msg(&DEPENDENCY)),
+        properties(17),
+    ],
+};
+
+static TASK: Type = Type {
+    fields: &[
+        req_attr("bom-ref", 1, Str),
+        req_elem("uid", 2, Str),
+        elem("name", 3, Str),
+        elem("description", 4, Str),
+        resource_references(5),
+        task_types(6),
+        elem("trigger", 7, Msg(&TRIGGER)),
+        list("steps", 8, "step", Msg(&STEP)),
+        list("inputs", 9, "input", Msg(&INPUT)),
+        list("outputs", 10, "output", Msg(&OUTPUT)),
+        elem("timeStart", 11, Time),
+        elem("timeEnd", 12, Time),
+        list("workspaces", 13, "workspace", Msg(&WORKSPACE)),
+        list("runtimeTopology", 14, "dependency", Msg(&DEPENDENCY)),
+        properties(15),
+    ],
+};
+
+static STEP: Type = Type {
+    fields: &[
+        elem("name", 1, Str),
+        elem("description", 2, Str),
+        list("commands", 3, "command", Msg(&COMMAND)),
+        properties(4),
+    ],
+};
+
+static COMMAND: Type = Type { fields: &[elem("executed", 1, Str), properties(2)] };
+
+static WORKSPACE: Type = Type {
+    fields: &[
+        req_attr("bom-ref", 1, Str),
+        req_elem("uid", 2, Str),
+        elem("name", 3, Str),
+        list("aliases", 4, "alias", Str),
+        elem("description", 5, Str),
+        resource_references(6),
+        elem("accessMode", 7, Kind::Enum(ACCESS_MODES)),
+        elem("mountPath", 8, Str),
+        elem("managedDataType", 9, Str),
+        elem("volumeRequest", 10, Str),
+        elem("volume", 11, Msg(&VOLUME)),
+        properties(12),
+    ],
+};
+
+static VOLUME: Type = Type {
+    fields: &[
+        elem("uid", 1, Str),
+        elem("name", 2, Str),
+        elem("mode", 3, Kind::Enum(VOLUME_MODES)),
+        elem("path", 4, Str),
+        elem("sizeAllocated", 5, Str),
+        elem("persistent", 6, Bool),
+        elem("remote", 7, Bool),
+        properties(8),
+    ],
+};
+
+static TRIGGER: Type = Type {
+    fields: &[
+        req_attr("bom-ref", 1, Str),
+        req_elem("uid", 2, Str),
+        elem("name", 3, Str),
+        elem("description", 4, Str),
+        resource_references(5),
+        req_elem("type", 6, Kind::Enum(TRIGGER_TYPES)),
+        elem("event", 7, Msg(&EVENT)),
+        list("conditions", 8, "condition", Msg(&CONDITION)),
+        elem("timeActivated", 9, Time),
+        list("inputs", 10, "input", Msg(&INPUT)),
+        list("outputs", 11, "output", Msg(&OUTPUT)),
+        properties(12),
+    ],
+};
+
+static EVENT: Type = Type {
+    fields: &[
+        elem("uid", 1, Str),
+        elem("description", 2, Str),
+        elem("timeReceived", 3, Time),
+        elem("data", 4, Msg(&ATTACHMENT)),
+        elem("source", 5, Msg(&RESOURCE_REFERENCE)),
+        elem("target", 6, Msg(&RESOURCE_REFERENCE)),
+        properties(7),
+    ],
+};
+
+static CONDITION: Type =
+    Type { fields: &[elem("description", 1, Str), elem("expression", 2, Str), properties(3)] };
+
+static INPUT: Type = Type {
+    fields: &[
+        elem("source", 1, Msg(&RESOURCE_REFERENCE)),
+        elem("target", 2, Msg(&RESOURCE_REFERENCE)),
+        elem("resource", 3, Msg(&RESOURCE_REFERENCE)),
+        list("parameters", 4, "parameter", Msg(&PARAMETER)),
+        field(
+            "environmentVars",
+            5,
+            Xml::Elem("environmentVars"),
+            Kind::EnvironmentVars,
+            false,
+            false,
+        ),
+        elem("data", 6, Msg(&ATTACHMENT)),
+        properties(7),
+    ],
+};
+
+static OUTPUT: Type = Type {
+    fields: &[
+        elem("type", 1, Kind::Enum(OUTPUT_TYPES)),
+        elem("source", 2, Msg(&RESOURCE_REFERENCE)),
+        elem("target", 3, Msg(&RESOURCE_REFERENCE)),
+        elem("resource", 4, Msg(&RESOURCE_REFERENCE)),
+        elem("data", 5, Msg(&ATTACHMENT)),
+        field(
+            "environmentVars",
+            6,
+            Xml::Elem("environmentVars"),
+            Kind::EnvironmentVars,
+            false,
+            false,
+        ),
+        properties(7),
+    ],
+};
+
+static PARAMETER: Type =
+    Type { fields: &[elem("name", 1, Str), elem("value", 2, Str), elem("dataType", 3, Str)] };
+
+static RESOURCE_REFERENCE: Type =
+    Type { fields: &[elem("ref", 1, Str), elem("externalReference", 2, Msg(&EXTERNAL_REFERENCE))] };
+
+static COMPONENT_TYPES: &[(&str, i32)] = &[
+    ("application", 1),
+    ("framework", 2),
+    ("library", 3),
+    ("operating-system", 4),
+    ("device", 5),
+    ("file", 6),
+    ("container", 7),
+    ("firmware", 8),
+    ("platform", 9),
+    ("device-driver", 10),
+    ("machine-learning-model", 11),
+    ("data", 12),
+];
+
+static SCOPES: &[(&str, i32)] = &[("required", 1), ("optional", 2), ("excluded", 3)];
+
+static HASH_ALGS: &[(&str, i32)] = &[
+    ("MD5", 1),
+    ("SHA-1", 2),
+    ("SHA-256", 3),
+    ("SHA-384", 4),
+    ("SHA-512", 5),
+    ("SHA3-256", 6),
+    ("SHA3-384", 7),
+    ("SHA3-512", 8),
+    ("BLAKE2b-256", 9),
+    ("BLAKE2b-384", 10),
+    ("BLAKE2b-512", 11),
+    ("BLAKE3", 12),
+];
+
+static EXTERNAL_REFERENCE_TYPES: &[(&str, i32)] = &[
+    ("other", 0),
+    ("vcs", 1),
+    ("issue-tracker", 2),
+    ("website", 3),
+    ("advisories", 4),
+    ("bom", 5),
+    ("mailing-list", 6),
+    ("social", 7),
+    ("chat", 8),
+    ("documentation", 9),
+    ("support", 10),
+    ("distribution", 11),
+    ("license", 12),
+    ("build-meta", 13),
+    ("build-system", 14),
+    ("security-contact", 15),
+    ("attestation", 16),
+    ("threat-model", 17),
+    ("adversary-model", 18),
+    ("risk-assessment", 19),
+    ("distribution-intake", 20),
+    ("vulnerability-assertion", 21),
+    ("exploitability-statement", 22),
+    ("pentest-report", 23),
+    ("static-analysis-report", 24),
+    ("dynamic-analysis-report", 25),
+    ("runtime-analysis-report", 26),
+    ("component-analysis-report", 27),
+    ("maturity-report", 28),
+    ("certification-report", 29),
+    ("quality-metrics", 30),
+    ("codified-infrastructure", 31),
+    ("model-card", 32),
+    ("poam", 33),
+    ("log", 34),
+    ("configuration", 35),
+    ("evidence", 36),
+    ("formulation", 37),
+    ("release-notes", 38),
+    ("electronic-signature", 39),
+    ("digital-signature", 40),
+    ("rfc-9116", 41),
+];
+
+static LIFECYCLE_PHASES: &[(&str, i32)] = &[
+    ("design", 0),
+    ("pre-build", 1),
+    ("build", 2),
+    ("post-build", 3),
+    ("operations", 4),
+    ("discovery", 5),
+    ("decommission", 6),
+];
+
+static ISSUE_TYPES: &[(&str, i32)] = &[("defect", 1), ("enhancement", 2), ("security", 3)];
+
+static PATCH_TYPES: &[(&str, i32)] =
+    &[("unofficial", 1), ("monkey", 2), ("backport", 3), ("cherry-pick", 4)];
+
+static LICENSE_TYPES: &[(&str, i32)] = &[
+    ("academic", 1),
+    ("appliance", 2),
+    ("client-access", 3),
+    ("concurrent-user", 4),
+    ("core-points", 5),
+    ("custom-metric", 6),
+    ("device", 7),
+    ("evaluation", 8),
+    ("named-user", 9),
+    ("node-locked", 10),
+    ("oem", 11),
+    ("perpetual", 12),
+    ("processor-points", 13),
+    ("subscription", 14),
+    ("user", 15),
+    ("other", 16),
+];
+
+static IDENTITY_FIELDS: &[(&str, i32)] =
+    &[("group", 1), ("name", 2), ("version", 3), ("purl", 4), ("cpe", 5), ("swid", 6), ("hash", 7)];
+
+static TECHNIQUES: &[(&str, i32)] = &[
+    ("source-code-analysis", 0),
+    ("binary-analysis", 1),
+    ("manifest-analysis", 2),
+    ("ast-fingerprint", 3),
+    ("hash-comparison", 4),
+    ("instrumentation", 5),
+    ("dynamic-analysis", 6),
+    ("filename", 7),
+    ("attestation", 8),
+    ("other", 9),
+];
+
+static DATA_TYPES: &[(&str, i32)] =
+    &[("source-code", 1), ("configuration", 2), ("dataset", 3), ("definition", 4), ("other", 5)];
+
+static APPROACH_TYPES: &[(&str, i32)] = &[
+    ("supervised", 0),
+    ("unsupervised", 1),
+    ("reinforcement-learning", 2),
+    ("semi-supervised", 3),
+    ("self-supervised", 4),
+];
+
+static FLOWS: &[(&str, i32)] =
+    &[("inbound", 1), ("outbound", 2), ("bi-directional", 3), ("unknown", 4)];
+
+static AGGREGATES: &[(&str, i32)] = &[
+    ("not_specified", 0),
+    ("complete", 1),
+    ("incomplete", 2),
+    ("incomplete_first_party_only", 3),
+    ("incomplete_third_party_only", 4),
+    ("unknown", 5),
+    ("incomplete_first_party_proprietary_only", 6),
+    ("incomplete_first_party_opensource_only", 7),
+    ("incomplete_third_party_proprietary_only", 8),
+    ("incomplete_third_party_opensource_only", 9),
+];
+
+static SEVERITIES: &[(&str, i32)] = &[
+    ("unknown", 0),
+    ("critical", 1),
+    ("high", 2),
+    ("medium", 3),
+    ("low", 4),
+    ("info", 5),
+    ("none", 6),
+];
+
+static SCORE_METHODS: &[(&str, i32)] = &[
+    ("CVSSv2", 1),
+    ("CVSSv3", 2),
+    ("CVSSv31", 3),
+    ("OWASP", 4),
+    ("other", 5),
+    ("SSVC", 6),
+    ("CVSSv4", 7),
+];
+
+static ANALYSIS_STATES: &[(&str, i32)] = &[
+    ("resolved", 1),
+    ("resolved_with_pedigree", 2),
+    ("exploitable", 3),
+    ("in_triage", 4),
+    ("false_positive", 5),
+    ("not_affected", 6),
+];
+
+static JUSTIFICATIONS: &[(&str, i32)] = &[
+    ("code_not_present", 1),
+    ("code_not_reachable", 2),
+    ("requires_configuration", 3),
+    ("requires_dependency", 4),
+    ("requires_environment", 5),
+    ("protected_by_compiler", 6),
+    ("protected_at_runtime", 7),
+    ("protected_at_perimeter", 8),
+    ("protected_by_mitigating_control", 9),
+];
+
+static RESPONSES: &[(&str, i32)] = &[
+    ("can_not_fix", 1),
+    ("will_not_fix", 2),
+    ("update", 3),
+    ("rollback", 4),
+    ("workaround_available", 5),
+];
+
+static AFFECTED_STATUSES: &[(&str, i32)] = &[("unknown", 0), ("affected", 1), ("unaffected", 2)];
+
+static ACCESS_MODES: &[(&str, i32)] = &[
+    ("read-only", 0),
+    ("read-write", 1),
+    ("read-write-once", 2),
+    ("write-once", 3),
+    ("write-only", 4),
+];
+
+static VOLUME_MODES: &[(&str, i32)] = &[("filesystem", 0), ("block", 1)];
+
+static TRIGGER_TYPES: &[(&str, i32)] =
+    &[("manual", 0), ("api", 1), ("webhook", 2), ("scheduled", 3)];
+
+static OUTPUT_TYPES: &[(&str, i32)] = &[
+    ("artifact", 0),
+    ("attestation", 1),
+    ("log", 2),
+    ("evidence", 3),
+    ("metrics", 4),
+    ("other", 5),
+];
+
+static TASK_TYPES: &[(&str, i32)] = &[
+    ("copy", 0),
+    ("clone", 1),
+    ("lint", 2),
+    ("scan", 3),
+    ("merge", 4),
+    ("build", 5),
+    ("test", 6),
+    ("deliver", 7),
+    ("deploy", 8),
+    ("release", 9),
+    ("clean", 10),
+    ("other", 11),
+];
@@ -0,0 +1,526 @@
+//! CycloneDX XML, see https://cyclonedx.org/docs/1.5/xml
+
+use anyhow::bail;
+use quick_xml::events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event};
+use quick_xml::{Reader, Writer};
+use serde_json::{Map, Number, Value};
+
+use super::schema::{Kind, Type, Xml, BOM, COMPONENT_DATA, PROPERTY, TOOL};
+use super::{array, object, string};
+
+/// Namespace of the spec version, e.g. `http://cyclonedx.org/schema/bom/1.5`
+const NAMESPACE: &str = "http://cyclonedx.org/schema/bom/";
+
+#[derive(Debug, Clone, Default)]
+struct Element {
+    name: String,
+    /// Attributes without a namespace prefix
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+    text: String,
+}
+
+impl Element {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+}
+
+/// Root element and the spec version of its namespace
+fn parse(data: &[u8]) -> anyhow::Result<(Element, Option<String>)> {
+    let mut reader = Reader::from_str(std::str::from_utf8(data)?);
+    reader.config_mut().trim_text(true);
+    let mut stack: Vec<Element> = vec![];
+    let mut spec_version = None;
+    loop {
+        let (start, empty) = match reader.read_event()? {
+            Event::Start(start) => (start, false),
+            Event::Empty(start) => (start, true),
+            Event::End(_) => {
+                let Some(element) = stack.pop() else { bail!("Unexpected end tag") };
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok((element, spec_version)),
+                }
+                continue;
+            }
+            Event::Text(text) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&text.unescape()?);
+                }
+                continue;
+            }
+            Event::CData(text) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&text.decode()?);
+                }
+                continue;
+            }
+            Event::Eof => bail!("Unexpected end of XML"),
+            _ => continue,
+        };
+        let mut element = Element {
+            name: std::str::from_utf8(start.local_name().as_ref())?.to_string(),
+            ..Default::default()
+        };
+        for attribute in start.attributes() {
+            let attribute = attribute?;
+            let key = std::str::from_utf8(attribute.key.as_ref())?;
+            let value = attribute.unescape_value()?.into_owned();
+            if key == "xmlns" || key.starts_with("xmlns:") {
+                if let (true, Some(version)) = (stack.is_empty(), value.strip_prefix(NAMESPACE)) {
+                    spec_version = Some(version.to_string());
+                }
+            } else if !key.contains(':') {
+                element.attributes.push((key.to_string(), value));
+            }
+        }
+        if !empty {
+            stack.push(element);
+        } else if let Some(parent) = stack.last_mut() {
+            parent.children.push(element);
+        } else {
+            return Ok((element, spec_version));
+        }
+    }
+}
+
+pub(super) fn read(data: &[u8]) -> anyhow::Result<Value> {
+    let (root, spec_version) = parse(data)?;
+    if root.name != "bom" {
+        bail!("Expected a CycloneDX bom, got <{}>", root.name);
+    }
+    let Some(spec_version) = spec_version else {
+        bail!("The bom is not in a {NAMESPACE} namespace");
+    };
+    let mut bom = read_object(&BOM, &root)?;
+    bom.insert("bomFormat".to_string(), "CycloneDX".into());
+    bom.insert("specVersion".to_string(), spec_version.into());
+    Ok(Value::Object(bom))
+}
+
+fn read_object(ty: &Type, element: &Element) -> anyhow::Result<Map<String, Value>> {
+    let mut object = Map::new();
+    // Child elements of the fields, the rest are unknown
+    let mut known = vec![];
+    for field in ty.fields {
+        let value = match field.xml {
+            Xml::Attr(name) => {
+                element.attribute(name).map(|text| scalar(field.kind, text)).transpose()?
+            }
+            Xml::Text => (field.required || !element.text.is_empty())
+                .then(|| scalar(field.kind, &element.text))
+                .transpose()?,
+            Xml::Elem(name) => {
+                known.push(name);
+                if field.repeated {
+                    let items = element
+                        .children(name)
+                        .map(|child| read_value(field.kind, child))
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+                    (!items.is_empty()).then_some(Value::Array(items))
+                } else {
+                    element.child(name).map(|child| read_value(field.kind, child)).transpose()?
+                }
+            }
+            Xml::List(wrapper, item) => {
+                known.push(wrapper);
+                match element.child(wrapper) {
+                    Some(list) => Some(Value::Array(
+                        list.children
+                            .iter()
+                            .filter(|child| item.is_empty() || child.name == item)
+                            .map(|child| read_item(field.kind, child, item.is_empty()))
+                            .collect::<anyhow::Result<_>>()?,
+                    )),
+                    None => None,
+                }
+            }
+            Xml::Refs(wrapper, item) => {
+                let list = if wrapper.is_empty() {
+                    known.push(item);
+                    element.children(item).next().map(|_| element)
+                } else {
+                    known.push(wrapper);
+                    element.child(wrapper)
+                };
+                match list {
+                    Some(list) => Some(Value::Array(
+                        list.children(item)
+                            .map(|child| match child.attribute("ref") {
+                                Some(reference) => Ok(Value::from(reference)),
+                                None => Err(anyhow::format_err!("<{item}> without a ref")),
+                            })
+                            .collect::<anyhow::Result<_>>()?,
+                    )),
+                    None => None,
+                }
+            }
+            Xml::AttrOf(child, attribute) => element
+                .child(child)
+                .and_then(|child| child.attribute(attribute))
+                .map(|text| scalar(field.kind, text))
+                .transpose()?,
+            Xml::None => None,
+        };
+        match value {
+            Some(value) => {
+                object.insert(field.json.to_string(), value);
+            }
+            None if field.required && field.repeated => {
+                object.insert(field.json.to_string(), Value::Array(vec![]));
+            }
+            None => {}
+        }
+    }
+
+    for (name, value) in &element.attributes {
+        if !ty.fields.iter().any(|field| matches!(field.xml, Xml::Attr(attr) if attr == name)) {
+            object.insert(name.clone(), value.as_str().into());
+        }
+    }
+    for child in element.children.iter().filter(|child| !known.contains(&child.name.as_str())) {
+        insert_generic(&mut object, child);
+    }
+    Ok(object)
+}
+
+/// Item of a list, the items without an element of their own are read from
+/// the child element itself, e.g. `<expression>` of `<licenses>`
+fn read_item(kind: Kind, element: &Element, inline: bool) -> anyhow::Result<Value> {
+    match kind {
+        Kind::Msg(ty) if inline => {
+            let wrapper = Element { children: vec![element.clone()], ..Default::default() };
+            Ok(Value::Object(read_object(ty, &wrapper)?))
+        }
+        _ => read_value(kind, element),
+    }
+}
+
+fn read_value(kind: Kind, element: &Element) -> anyhow::Result<Value> {
+    Ok(match kind {
+        Kind::Msg(ty) => Value::Object(read_object(ty, element)?),
+        Kind::Tools if element.child("tool").is_some() => Value::Array(
+            element
+                .children("tool")
+                .map(|tool| Ok(Value::Object(read_object(&TOOL, tool)?)))
+                .collect::<anyhow::Result<_>>()?,
+        ),
+        Kind::Tools => Value::Object(read_object(&TOOL, element)?),
+        Kind::EnvironmentVars => Value::Array(
+            element
+                .children
+                .iter()
+                .map(|var| match var.name.as_str() {
+                    "environmentVar" => Ok(Value::Object(read_object(&PROPERTY, var)?)),
+                    "value" => Ok(Value::from(var.text.as_str())),
+                    name => bail!("Unexpected <{name}> in <environmentVars>"),
+                })
+                .collect::<anyhow::Result<_>>()?,
+        ),
+        Kind::Datasets => Value::Array(
+            element
+                .children
+                .iter()
+                .map(|dataset| match dataset.name.as_str() {
+                    "dataset" => Ok(Value::Object(read_object(&COMPONENT_DATA, dataset)?)),
+                    "ref" => Ok(serde_json::json!({ "ref": dataset.text })),
+                    name => bail!("Unexpected <{name}> in <datasets>"),
+                })
+                .collect::<anyhow::Result<_>>()?,
+        ),
+        _ => scalar(kind, &element.text)?,
+    })
+}
+
+fn scalar(kind: Kind, text: &str) -> anyhow::Result<Value> {
+    let invalid = |e: &dyn std::fmt::Display| anyhow::format_err!("Invalid value {text:?}: {e}");
+    Ok(match kind {
+        Kind::Int => text.trim().parse::<i64>().map_err(|e| invalid(&e))?.into(),
+        Kind::Bool => text.trim().parse::<bool>().map_err(|e| invalid(&e))?.into(),
+        Kind::Float | Kind::Double => {
+            let number = text.trim().parse::<f64>().map_err(|e| invalid(&e))?;
+            Value::Number(Number::from_f64(number).ok_or_else(|| invalid(&"not finite"))?)
+        }
+        _ => text.into(),
+    })
+}
+
+/// Element unknown to the schema: the text of a plain element, otherwise an
+/// object of the attributes and children, repeated children in arrays
+fn insert_generic(object: &mut Map<String, Value>, element: &Element) {
+    let value = if element.attributes.is_empty() && element.children.is_empty() {
+        Value::from(element.text.as_str())
+    } else {
+        let mut fields = Map::new();
+        for (name, value) in &element.attributes {
+            fields.insert(name.clone(), value.as_str().into());
+        }
+        for child in &element.children {
+            insert_generic(&mut fields, child);
+        }
+        if !element.text.is_empty() {
+            fields.insert("value".to_string(), element.text.as_str().into());
+        }
+        Value::Object(fields)
+    };
+    match object.get_mut(&element.name) {
+        Some(Value::Array(values)) => values.push(value),
+        Some(first) => *first = Value::Array(vec![first.take(), value]),
+        None => {
+            object.insert(element.name.clone(), value);
+        }
+    }
+}
+
+type XmlWriter = Writer<Vec<u8>>;
+
+type Attributes = Vec<(&'static str, String)>;
+
+pub(super) fn write(bom: &Value) -> anyhow::Result<Vec<u8>> {
+    let bom = object(bom)?;
+    let spec_version = bom.get("specVersion").map(string).transpose()?.unwrap_or("1.5");
+    let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    write_object(
+        &mut writer,
+        "bom",
+        &BOM,
+        bom,
+        vec![("xmlns", format!("{NAMESPACE}{spec_version}"))],
+    )?;
+    let mut data = writer.into_inner();
+    data.push(b'\n');
+    Ok(data)
+}
+
+fn start(
+    writer: &mut XmlWriter,
+    name: &str,
+    attributes: Attributes,
+    empty: bool,
+) -> anyhow::Result<()> {
+    let mut start = BytesStart::new(name);
+    for (key, value) in &attributes {
+        start.push_attribute((*key, value.as_str()));
+    }
+    writer.write_event(if empty { Event::Empty(start) } else { Event::Start(start) })?;
+    Ok(())
+}
+
+fn end(writer: &mut XmlWriter, name: &str) -> anyhow::Result<()> {
+    writer.write_event(Event::End(BytesEnd::new(name)))?;
+    Ok(())
+}
+
+fn write_text(
+    writer: &mut XmlWriter,
+    name: &str,
+    text: &str,
+    attributes: Attributes,
+) -> anyhow::Result<()> {
+    start(writer, name, attributes, text.is_empty())?;
+    if !text.is_empty() {
+        writer.write_event(Event::Text(BytesText::new(text)))?;
+        end(writer, name)?;
+    }
+    Ok(())
+}
+
+fn text(value: &Value) -> anyhow::Result<String> {
+    Ok(match value {
+        Value::String(text) => text.clone(),
+        Value::Number(number) => number.to_string(),
+        Value::Bool(bool) => bool.to_string(),
+        _ => bail!("Expected a scalar, got {value}"),
+    })
+}
+
+/// Whether the JSON key is written as an element: a child of a field or an
+/// unknown field
+fn is_element(ty: &Type, key: &str) -> bool {
+    match ty.field(key) {
+        Some(field) => matches!(field.xml, Xml::Elem(_) | Xml::List(..) | Xml::Refs(..)),
+        None => !key.starts_with('$'),
+    }
+}
+
+fn write_object(
+    writer: &mut XmlWriter,
+    name: &str,
+    ty: &Type,
+    object: &Map<String, Value>,
+    mut attributes: Attributes,
+) -> anyhow::Result<()> {
+    for field in ty.fields {
+        if let (Xml::Attr(attribute), Some(value)) = (field.xml, object.get(field.json)) {
+            attributes.push((attribute, text(value)?));
+        }
+    }
+    let text_field = ty.fields.iter().find(|field| matches!(field.xml, Xml::Text));
+    let text_value = text_field.and_then(|field| object.get(field.json)).map(text).transpose()?;
+    let has_children = object.keys().any(|key| is_element(ty, key));
+    if !has_children {
+        return write_text(writer, name, text_value.as_deref().unwrap_or_default(), attributes);
+    }
+
+    start(writer, name, attributes, false)?;
+    if let Some(text_value) = text_value {
+        writer.write_event(Event::Text(BytesText::new(&text_value)))?;
+    }
+    write_children(writer, ty, object)?;
+    end(writer, name)
+}
+
+fn write_children(
+    writer: &mut XmlWriter,
+    ty: &Type,
+    object: &Map<String, Value>,
+) -> anyhow::Result<()> {
+    for field in ty.fields {
+        let Some(value) = object.get(field.json) else { continue };
+        match field.xml {
+            Xml::Elem(name) if field.repeated => {
+                for item in array(value)? {
+                    write_value(writer, name, field.kind, item, attributes_of(ty, object, name)?)?;
+                }
+            }
+            Xml::Elem(name) => {
+                write_value(writer, name, field.kind, value, attributes_of(ty, object, name)?)?
+            }
+            Xml::List(wrapper, item) => {
+                let items = array(value)?;
+                start(writer, wrapper, vec![], items.is_empty())?;
+                if items.is_empty() {
+                    continue;
+                }
+                for value in items {
+                    match field.kind {
+                        Kind::Msg(ty) if item.is_empty() => {
+                            write_children(writer, ty, super::object(value)?)?
+                        }
+                        kind => write_value(writer, item, kind, value, vec![])?,
+                    }
+                }
+                end(writer, wrapper)?;
+            }
+            Xml::Refs(wrapper, item) => {
+                if !wrapper.is_empty() {
+                    start(writer, wrapper, vec![], false)?;
+                }
+                for reference in array(value)? {
+                    start(writer, item, vec![("ref", string(reference)?.to_string())], true)?;
+                }
+                if !wrapper.is_empty() {
+                    end(writer, wrapper)?;
+                }
+            }
+            Xml::Attr(_) | Xml::Text | Xml::AttrOf(..) | Xml::None => {}
+        }
+    }
+    for (key, value) in
+        object.iter().filter(|(key, _)| ty.field(key).is_none() && !key.starts_with('$'))
+    {
+        write_generic(writer, key, value)?;
+    }
+    Ok(())
+}
+
+/// Attributes the fields put on the child element, e.g. the flow of the data
+/// on its `<classification>`
+fn attributes_of(
+    ty: &Type,
+    object: &Map<String, Value>,
+    child: &str,
+) -> anyhow::Result<Attributes> {
+    let mut attributes = vec![];
+    for field in ty.fields {
+        if let (Xml::AttrOf(element, attribute), Some(value)) = (&field.xml, object.get(field.json))
+        {
+            if *element == child {
+                attributes.push((*attribute, text(value)?));
+            }
+        }
+    }
+    Ok(attributes)
+}
+
+fn write_value(
+    writer: &mut XmlWriter,
+    name: &str,
+    kind: Kind,
+    value: &Value,
+    attributes: Attributes,
+) -> anyhow::Result<()> {
+    match kind {
+        Kind::Msg(ty) => write_object(writer, name, ty, object(value)?, attributes),
+        Kind::Tools => match value {
+            Value::Array(tools) => {
+                start(writer, name, attributes, tools.is_empty())?;
+                if tools.is_empty() {
+                    return Ok(());
+                }
+                for tool in tools {
+                    write_object(writer, "tool", &TOOL, object(tool)?, vec![])?;
+                }
+                end(writer, name)
+            }
+            _ => write_object(writer, name, &TOOL, object(value)?, attributes),
+        },
+        Kind::EnvironmentVars => {
+            start(writer, name, attributes, false)?;
+            for var in array(value)? {
+                match var {
+                    Value::String(var) => write_text(writer, "value", var, vec![])?,
+                    _ => write_object(writer, "environmentVar", &PROPERTY, object(var)?, vec![])?,
+                }
+            }
+            end(writer, name)
+        }
+        Kind::Datasets => {
+            start(writer, name, attributes, false)?;
+            for dataset in array(value)? {
+                let dataset = object(dataset)?;
+                match dataset.get("ref") {
+                    Some(reference) if !dataset.contains_key("type") => {
+                        write_text(writer, "ref", string(reference)?, vec![])?
+                    }
+                    _ => write_object(writer, "dataset", &COMPONENT_DATA, dataset, vec![])?,
+                }
+            }
+            end(writer, name)
+        }
+        _ => write_text(writer, name, &text(value)?, attributes),
+    }
+}
+
+fn write_generic(writer: &mut XmlWriter, name: &str, value: &Value) -> anyhow::Result<()> {
+    match value {
+        Value::Array(values) => {
+            for value in values {
+                write_generic(writer, name, value)?;
+            }
+            Ok(())
+        }
+        Value::Object(fields) => {
+            start(writer, name, vec![], fields.is_empty())?;
+            if fields.is_empty() {
+                return Ok(());
+            }
+            for (key, value) in fields {
+                write_generic(writer, key, value)?;
+            }
+            end(writer, name)
+        }
+        Value::Null => start(writer, name, vec![], true),
+        _ => write_text(writer, name, &text(value)?, vec![]),
+    }
+}
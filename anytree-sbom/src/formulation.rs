@@ -0,0 +1,247 @@
+//! How the components were built: workflows, tasks and their environment
+
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use crate::{
+    Attachment, Component, Dependency, ExternalReference, Extra, Property, Service, Timestamp,
+};
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Formula {
+    #[serde(rename = "bom-ref")]
+    pub bom_ref: Option<String>,
+    pub components: Option<Vec<Component>>,
+    pub services: Option<Vec<Service>>,
+    pub workflows: Option<Vec<Workflow>>,
+    pub properties: Option<Vec<Property>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Workflow {
+    #[serde(rename = "bom-ref")]
+    pub bom_ref: String,
+    pub uid: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub resource_references: Option<Vec<ResourceReference>>,
+    pub tasks: Option<Vec<Task>>,
+    pub task_dependencies: Option<Vec<Dependency>>,
+    /// copy, clone, lint, scan, merge, build, test, deliver, deploy, release,
+    /// clean or other
+    pub task_types: Vec<String>,
+    pub trigger: Option<Trigger>,
+    pub steps: Option<Vec<Step>>,
+    pub inputs: Option<Vec<Input>>,
+    pub outputs: Option<Vec<Output>>,
+    pub time_start: Option<Timestamp>,
+    pub time_end: Option<Timestamp>,
+    pub workspaces: Option<Vec<Workspace>>,
+    pub runtime_topology: Option<Vec<Dependency>>,
+    pub properties: Option<Vec<Property>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Task {
+    #[serde(rename = "bom-ref")]
+    pub bom_ref: String,
+    pub uid: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub resource_references: Option<Vec<ResourceReference>>,
+    pub task_types: Vec<String>,
+    pub trigger: Option<Trigger>,
+    pub steps: Option<Vec<Step>>,
+    pub inputs: Option<Vec<Input>>,
+    pub outputs: Option<Vec<Output>>,
+    pub time_start: Option<Timestamp>,
+    pub time_end: Option<Timestamp>,
+    pub workspaces: Option<Vec<Workspace>>,
+    pub runtime_topology: Option<Vec<Dependency>>,
+    pub properties: Option<Vec<Property>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Step {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub commands: Option<Vec<Command>>,
+    pub properties: Option<Vec<Property>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Command {
+    pub executed: Option<String>,
+    pub properties: Option<Vec<Property>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Workspace {
+    #[serde(rename = "bom-ref")]
+    pub bom_ref: String,
+    pub uid: String,
+    pub name: Option<String>,
+    pub aliases: Option<Vec<String>>,
+    pub description: Option<String>,
+    pub resource_references: Option<Vec<ResourceReference>>,
+    /// read-only, read-write, read-write-once, write-once or write-only
+    pub access_mode: Option<String>,
+    pub mount_path: Option<String>,
+    pub managed_data_type: Option<String>,
+    pub volume_request: Option<String>,
+    pub volume: Option<Volume>,
+    pub properties: Option<Vec<Property>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Volume {
+    pub uid: Option<String>,
+    pub name: Option<String>,
+    /// filesystem or block
+    pub mode: Option<String>,
+    pub path: Option<String>,
+    pub size_allocated: Option<String>,
+    pub persistent: Option<bool>,
+    pub remote: Option<bool>,
+    pub properties: Option<Vec<Property>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Trigger {
+    #[serde(rename = "bom-ref")]
+    pub bom_ref: String,
+    pub uid: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub resource_references: Option<Vec<ResourceReference>>,
+    /// manual, api, webhook or scheduled
+    #[serde(rename = "type")]
+    pub trigger_type: String,
+    pub event: Option<Event>,
+    pub conditions: Option<Vec<Condition>>,
+    pub time_activated: Option<Timestamp>,
+    pub inputs: Option<Vec<Input>>,
+    pub outputs: Option<Vec<Output>>,
+    pub properties: Option<Vec<Property>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Event {
+    pub uid: Option<String>,
+    pub description: Option<String>,
+    pub time_received: Option<Timestamp>,
+    pub data: Option<Attachment>,
+    pub source: Option<ResourceReference>,
+    pub target: Option<ResourceReference>,
+    pub properties: Option<Vec<Property>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Condition {
+    pub description: Option<String>,
+    pub expression: Option<String>,
+    pub properties: Option<Vec<Property>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// Input of a task, one of `resource`, `parameters`, `environmentVars` or
+/// `data` is set
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Input {
+    pub source: Option<ResourceReference>,
+    pub target: Option<ResourceReference>,
+    pub resource: Option<ResourceReference>,
+    pub parameters: Option<Vec<Parameter>>,
+    pub environment_vars: Option<Vec<EnvironmentVar>>,
+    pub data: Option<Attachment>,
+    pub properties: Option<Vec<Property>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// Output of a task, one of `resource`, `environmentVars` or `data` is set
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Output {
+    /// artifact, attestation, log, evidence, metrics or other
+    #[serde(rename = "type")]
+    pub output_type: Option<String>,
+    pub source: Option<ResourceReference>,
+    pub target: Option<ResourceReference>,
+    pub resource: Option<ResourceReference>,
+    pub data: Option<Attachment>,
+    pub environment_vars: Option<Vec<EnvironmentVar>>,
+    pub properties: Option<Vec<Property>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameter {
+    pub name: Option<String>,
+    pub value: Option<String>,
+    pub data_type: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// `NAME=value` string or a property with the name and the value
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum EnvironmentVar {
+    Property(Property),
+    Value(String),
+}
+
+/// `bom-ref` of an object in the BOM or an external reference, only one of
+/// them is set
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceReference {
+    #[serde(rename = "ref")]
+    pub reference: Option<String>,
+    pub external_reference: Option<ExternalReference>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
@@ -0,0 +1,79 @@
+//! Order of the object keys of the JSON the SBOM was read from. The model
+//! writes the known fields in the schema order and the unknown ones after
+//! them, so the keys are put back in the order they were read in.
+
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+
+/// Keys of every object of the JSON, keyed by the JSON pointer of the object
+#[derive(Debug, Clone, Default)]
+pub struct KeyOrder(HashMap<String, Vec<String>>);
+
+/// Key order is only a matter of formatting, SBOMs read from differently
+/// ordered JSON are equal
+impl PartialEq for KeyOrder {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl KeyOrder {
+    pub fn record(value: &Value) -> Self {
+        let mut order = Self::default();
+        order.record_at(String::new(), value);
+        order
+    }
+
+    fn record_at(&mut self, pointer: String, value: &Value) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map {
+                    self.record_at(child(&pointer, key), value);
+                }
+                self.0.insert(pointer, map.keys().cloned().collect());
+            }
+            Value::Array(values) => {
+                for (i, value) in values.iter().enumerate() {
+                    self.record_at(child(&pointer, &i.to_string()), value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Reorders the keys of the objects of `value` as they were recorded. Keys
+    /// added since then follow the recorded ones.
+    pub fn apply(&self, value: &mut Value) {
+        if !self.0.is_empty() {
+            self.apply_at(String::new(), value);
+        }
+    }
+
+    fn apply_at(&self, pointer: String, value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                if let Some(keys) = self.0.get(&pointer) {
+                    let mut entries = std::mem::take(map).into_iter().collect::<Vec<_>>();
+                    entries.sort_by_key(|(key, _)| {
+                        keys.iter().position(|recorded| recorded == key).unwrap_or(keys.len())
+                    });
+                    *map = entries.into_iter().collect::<Map<_, _>>();
+                }
+                for (key, value) in map.iter_mut() {
+                    self.apply_at(child(&pointer, key), value);
+                }
+            }
+            Value::Array(values) => {
+                for (i, value) in values.iter_mut().enumerate() {
+                    self.apply_at(child(&pointer, &i.to_string()), value);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn child(pointer: &str, key: &str) -> String {
+    format!("{pointer}/{}", key.replace('~', "~0").replace('/', "~1"))
+}
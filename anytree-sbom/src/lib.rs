@@ -1,92 +1,128 @@
-//! See https://github.com/CycloneDX
+//! CycloneDX 1.5 JSON model, see https://cyclonedx.org/docs/1.5/json
+//!
+//! Enumerations are kept as strings, except the component type, so BOMs using
+//! the values of newer specs still parse. Unknown fields are kept in `extra`
+//! of each struct, so an SBOM is written back with everything it was read
+//! with, in the same order.
 
-use chrono::offset::Utc;
-use chrono::DateTime;
+mod annotation;
+mod common;
+mod component;
+mod dependency;
+mod encoding;
+mod formulation;
+mod key_order;
+mod license;
+mod model_card;
+mod service;
+pub mod spdx;
+mod vulnerability;
+
+pub use annotation::*;
+pub use common::*;
+pub use component::*;
+pub use dependency::*;
+pub use encoding::Format;
+pub use formulation::*;
+pub use key_order::KeyOrder;
+pub use license::*;
+pub use model_card::*;
 use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+pub use service::*;
+pub use vulnerability::*;
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct CycloneDXBom {
-    #[serde(rename = "bomFormat")]
+    #[serde(rename = "$schema")]
+    pub schema: Option<String>,
     pub bom_format: String,
-    #[serde(rename = "specVersion")]
     pub spec_version: String,
-    #[serde(rename = "serialNumber")]
     pub serial_number: Option<String>,
-    // add default
+    #[serde(default = "default_version")]
     pub version: i32,
     pub metadata: Option<Metadata>,
+    #[serde(default)]
     pub components: Vec<Component>,
+    pub services: Option<Vec<Service>>,
+    pub external_references: Option<Vec<ExternalReference>>,
+    pub dependencies: Option<Vec<Dependency>>,
+    pub compositions: Option<Vec<Composition>>,
+    pub properties: Option<Vec<Property>>,
+    pub vulnerabilities: Option<Vec<Vulnerability>>,
+    pub annotations: Option<Vec<Annotation>>,
+    pub formulation: Option<Vec<Formula>>,
+    pub signature: Option<Signature>,
+    #[serde(flatten)]
+    pub extra: Extra,
+    /// Order of the keys of the JSON the SBOM was parsed from
+    #[serde(skip)]
+    pub key_order: KeyOrder,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+fn default_version() -> i32 {
+    1
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
 pub struct Metadata {
-    pub timestamp: Option<DateTime<Utc>>,
-    pub tools: Option<Vec<Tool>>, // vec of tool is deprecated in 1.5
+    pub timestamp: Option<Timestamp>,
+    pub lifecycles: Option<Vec<Lifecycle>>,
+    pub tools: Option<Tools>,
+    pub authors: Option<Vec<OrganizationalContact>>,
     pub component: Option<Component>,
+    pub manufacture: Option<OrganizationalEntity>,
+    pub supplier: Option<OrganizationalEntity>,
+    pub licenses: Option<Vec<LicenseChoice>>,
+    pub properties: Option<Vec<Property>>,
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct Tool {
-    pub vendor: Option<String>,
+/// Phase of the product lifecycle the BOM was made in, a standard `phase` or a
+/// custom `name`
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Lifecycle {
+    /// design, pre-build, build, post-build, operations, discovery or
+    /// decommission
+    pub phase: Option<String>,
     pub name: Option<String>,
-    pub version: Option<String>,
-    pub hashes: Option<Vec<Hash>>,
-}
-
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct Hash {
-    pub alg: String,
-    pub content: String,
-}
-
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct Property {
-    pub name: String,
-    pub value: String,
+    pub description: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub enum ComponentType {
-    #[serde(rename = "application")]
-    Application,
-    #[serde(rename = "framework")]
-    Framework,
-    #[serde(rename = "library")]
-    Library,
-    #[serde(rename = "container")]
-    Container,
-    #[serde(rename = "operating-system")]
-    OperatingSystem,
-    #[serde(rename = "device")]
-    Device,
-    #[serde(rename = "firmware")]
-    Firmware,
-    #[serde(rename = "file")]
-    File,
+/// Tools that made the BOM. The list of [`Tool`]s is deprecated in 1.5 in
+/// favour of components and services.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Tools {
+    Legacy(Vec<Tool>),
+    Objects {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        components: Option<Vec<Component>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        services: Option<Vec<Service>>,
+        #[serde(flatten)]
+        extra: Extra,
+    },
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct Component {
-    pub bom_ref: Option<String>,
-    #[serde(rename = "type")]
-    pub component_type: ComponentType,
-    pub name: String,
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tool {
+    pub vendor: Option<String>,
+    pub name: Option<String>,
     pub version: Option<String>,
-    pub purl: Option<String>,
-    #[serde(rename = "externalReferences")]
-    pub external_references: Option<Vec<ExternalReference>>,
-    pub properties: Option<Vec<Property>>,
-    #[serde(rename = "mime-type")]
-    pub mime_type: Option<String>,
     pub hashes: Option<Vec<Hash>>,
-    pub description: Option<String>,
-}
-
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct ExternalReference {
-    pub url: String,
-    #[serde(rename = "type")]
-    pub ref_type: String,
+    pub external_references: Option<Vec<ExternalReference>>,
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
 #[cfg(test)]
@@ -102,6 +138,56 @@ mod tests {
         assert_eq!(sbom.components.first().unwrap().external_references.as_ref().unwrap().len(), 1);
     }
 
+    #[test]
+    fn test_bom_ref_dependencies_and_compositions() {
+        let json = include_bytes!("../tests/fixtures/proton-bridge-v1.6.3.cdx.json");
+        let sbom = serde_json::from_slice::<CycloneDXBom>(json).unwrap();
+        let component = sbom.metadata.unwrap().component.unwrap();
+        let dependencies = sbom.dependencies.unwrap();
+        let dependency =
+            dependencies.iter().find(|d| Some(&d.reference) == component.bom_ref.as_ref()).unwrap();
+        assert!(!dependency.depends_on.as_ref().unwrap().is_empty());
+
+        let json = include_bytes!("../../hack/bash_sbom.cdx.json");
+        let sbom = serde_json::from_slice::<CycloneDXBom>(json).unwrap();
+        let compositions = sbom.compositions.unwrap();
+        assert_eq!(2, compositions.len());
+        // misspelled and single value fields of older SBOMs are kept as they are
+        assert_eq!(None, compositions[0].bom_ref);
+        assert_eq!(Some("12312"), compositions[0].extra["bon-ref"].as_str());
+        assert_eq!("not_specified", compositions[1].aggregate);
+        let assemblies = compositions[1].assemblies.as_ref().unwrap();
+        assert_eq!(&OneOrMany::One("1".to_string()), assemblies);
+        assert_eq!(["1".to_string()], assemblies.as_slice());
+        let value = serde_json::to_value(&compositions[1]).unwrap();
+        assert_eq!(serde_json::json!("1"), value["assemblies"]);
+    }
+
+    #[test]
+    fn test_tools_and_skipped_fields() {
+        let json = br#"{
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.5",
+            "metadata": {"tools": {"components": [{"type": "application", "name": "anytree"}]}},
+            "components": [{"type": "machine-learning-model", "name": "model", "scope": "excluded"}]
+        }"#;
+        let sbom = serde_json::from_slice::<CycloneDXBom>(json).unwrap();
+        assert_eq!(1, sbom.version);
+        let Some(Tools::Objects { components: Some(tools), .. }) =
+            &sbom.metadata.as_ref().unwrap().tools
+        else {
+            panic!("Expected tools as components")
+        };
+        assert_eq!("anytree", tools[0].name);
+        assert_eq!(ComponentType::MachineLearningModel, sbom.components[0].component_type);
+
+        let value = serde_json::to_value(&sbom).unwrap();
+        assert_eq!(
+            serde_json::json!({"type": "machine-learning-model", "name": "model", "scope": "excluded"}),
+            value["components"][0]
+        );
+    }
+
     #[test]
     fn test_wrong_type_should_fail() {
         let json = include_bytes!("../tests/fixtures/wrong_component_type.cdx.json");
@@ -112,4 +198,130 @@ mod tests {
 
         assert!(err.is_data());
     }
+
+    /// SBOMs that parse, `wrong_component_type` is rejected on purpose
+    pub(crate) const FIXTURES: &[&[u8]] = &[
+        include_bytes!("../tests/fixtures/proton-bridge-v1.6.3.cdx.json"),
+        include_bytes!("../../hack/bash_sbom.cdx.json"),
+        include_bytes!("../../hack/bom.cdx.json"),
+        include_bytes!("../../hack/cargo_sbom.cdx.json"),
+        include_bytes!("../../hack/cargo_sbom_mac.cdx.json"),
+    ];
+
+    fn round_trip(value: &serde_json::Value) -> serde_json::Value {
+        let sbom = CycloneDXBom::parse(&serde_json::to_vec(value).unwrap()).unwrap();
+        serde_json::from_slice(&sbom.write(Format::Json).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_fixtures_are_written_back_as_read() {
+        for fixture in FIXTURES {
+            let value = serde_json::from_slice::<serde_json::Value>(fixture).unwrap();
+            let mut expected = serde_json::to_vec_pretty(&value).unwrap();
+            expected.push(b'\n');
+            let sbom = CycloneDXBom::parse(fixture).unwrap();
+            let actual = sbom.write(Format::Json).unwrap();
+            assert!(expected == actual, "{}", String::from_utf8_lossy(&actual));
+        }
+    }
+
+    /// Same JSON with the same order of object keys, up to the notation of
+    /// numbers and the offset notation of timestamps
+    pub(crate) fn assert_same(
+        path: &str,
+        expected: &serde_json::Value,
+        actual: &serde_json::Value,
+    ) {
+        use serde_json::Value;
+        match (expected, actual) {
+            (Value::Object(expected), Value::Object(actual)) => {
+                let keys = |map: &serde_json::Map<_, _>| map.keys().cloned().collect::<Vec<_>>();
+                assert_eq!(keys(expected), keys(actual), "keys of {path}");
+                for (key, value) in expected {
+                    assert_same(&format!("{path}/{key}"), value, &actual[key]);
+                }
+            }
+            (Value::Array(expected), Value::Array(actual)) => {
+                assert_eq!(expected.len(), actual.len(), "length of {path}");
+                for (i, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+                    assert_same(&format!("{path}/{i}"), expected, actual);
+                }
+            }
+            (Value::Number(expected), Value::Number(actual)) if expected != actual => {
+                assert_eq!(expected.as_f64(), actual.as_f64(), "{path}");
+            }
+            (Value::String(expected), Value::String(actual)) if expected != actual => {
+                let time = |s: &str| chrono::DateTime::parse_from_rfc3339(s).ok();
+                assert!(time(expected).is_some(), "{path}: {expected:?} != {actual:?}");
+                assert_eq!(time(expected), time(actual), "{path}");
+            }
+            _ => assert_eq!(expected, actual, "{path}"),
+        }
+    }
+
+    fn json_value() -> impl proptest::strategy::Strategy<Value = serde_json::Value> {
+        use proptest::prelude::*;
+        use serde_json::Value;
+        let leaf = prop_oneof![
+            Just(Value::Null),
+            any::<bool>().prop_map(Value::from),
+            any::<i64>().prop_map(Value::from),
+            "[a-z0-9 :-]{0,12}".prop_map(Value::from),
+        ];
+        leaf.prop_recursive(3, 16, 4, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..4).prop_map(Value::from),
+                prop::collection::btree_map("[a-z]{1,6}", inner, 0..4)
+                    .prop_map(|map| Value::Object(map.into_iter().collect())),
+            ]
+        })
+    }
+
+    /// JSON pointers of all objects in `value`
+    fn objects(value: &serde_json::Value, path: String, pointers: &mut Vec<String>) {
+        match value {
+            serde_json::Value::Object(map) => {
+                for (key, value) in map {
+                    objects(
+                        value,
+                        format!("{path}/{}", key.replace('~', "~0").replace('/', "~1")),
+                        pointers,
+                    );
+                }
+                pointers.push(path);
+            }
+            serde_json::Value::Array(values) => {
+                for (i, value) in values.iter().enumerate() {
+                    objects(value, format!("{path}/{i}"), pointers);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    proptest::proptest! {
+        #![proptest_config(proptest::prelude::ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn test_round_trip_keeps_unknown_fields(
+            fixture in proptest::sample::select(FIXTURES),
+            object in proptest::prelude::any::<proptest::sample::Index>(),
+            position in proptest::prelude::any::<proptest::sample::Index>(),
+            key in "x-[a-z]{1,8}",
+            value in json_value(),
+        ) {
+            let mut json = serde_json::from_slice::<serde_json::Value>(fixture).unwrap();
+            assert_same("", &json, &round_trip(&json));
+
+            let mut pointers = vec![];
+            objects(&json, String::new(), &mut pointers);
+            let pointer = object.get(&pointers);
+            // unknown field goes anywhere among the known ones
+            let map = json.pointer_mut(pointer).unwrap().as_object_mut().unwrap();
+            let mut entries = std::mem::take(map).into_iter().collect::<Vec<_>>();
+            entries.insert(position.index(entries.len() + 1), (key, value));
+            *map = entries.into_iter().collect();
+            assert_same("", &json, &round_trip(&json));
+        }
+    }
 }
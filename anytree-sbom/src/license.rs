@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use crate::{Attachment, Extra, OrganizationOrIndividual, Property, Timestamp};
+
+/// Item of `licenses`, either a license or an SPDX license expression. The
+/// expression is the only item when it is used.
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct LicenseChoice {
+    pub license: Option<License>,
+    pub expression: Option<String>,
+    #[serde(rename = "bom-ref")]
+    pub bom_ref: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// License with an SPDX `id` or, for the others, a `name`
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct License {
+    #[serde(rename = "bom-ref")]
+    pub bom_ref: Option<String>,
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub text: Option<Attachment>,
+    pub url: Option<String>,
+    pub licensing: Option<Licensing>,
+    pub properties: Option<Vec<Property>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// Terms of a commercial license
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Licensing {
+    pub alt_ids: Option<Vec<String>>,
+    pub licensor: Option<OrganizationOrIndividual>,
+    pub licensee: Option<OrganizationOrIndividual>,
+    pub purchaser: Option<OrganizationOrIndividual>,
+    pub purchase_order: Option<String>,
+    /// academic, appliance, client-access, concurrent-user, core-points,
+    /// custom-metric, device, evaluation, named-user, node-locked, oem,
+    /// perpetual, processor-points, subscription, user or other
+    pub license_types: Option<Vec<String>>,
+    pub last_renewal: Option<Timestamp>,
+    pub expiration: Option<Timestamp>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
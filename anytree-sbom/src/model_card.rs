@@ -0,0 +1,136 @@
+//! Model cards of `machine-learning-model` components
+
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use crate::{ComponentData, Extra, GraphicsCollection, Property};
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelCard {
+    #[serde(rename = "bom-ref")]
+    pub bom_ref: Option<String>,
+    pub model_parameters: Option<ModelParameters>,
+    pub quantitative_analysis: Option<QuantitativeAnalysis>,
+    pub considerations: Option<Considerations>,
+    pub properties: Option<Vec<Property>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelParameters {
+    pub approach: Option<Approach>,
+    pub task: Option<String>,
+    pub architecture_family: Option<String>,
+    pub model_architecture: Option<String>,
+    pub datasets: Option<Vec<Dataset>>,
+    pub inputs: Option<Vec<InputOutputFormat>>,
+    pub outputs: Option<Vec<InputOutputFormat>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Approach {
+    /// supervised, unsupervised, reinforcement-learning, semi-supervised or
+    /// self-supervised
+    #[serde(rename = "type")]
+    pub approach_type: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// Dataset described inline or a reference to a `data` component
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Dataset {
+    Ref {
+        #[serde(rename = "ref")]
+        reference: String,
+        #[serde(flatten)]
+        extra: Extra,
+    },
+    Data(Box<ComponentData>),
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct InputOutputFormat {
+    pub format: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuantitativeAnalysis {
+    pub performance_metrics: Option<Vec<PerformanceMetric>>,
+    pub graphics: Option<GraphicsCollection>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PerformanceMetric {
+    #[serde(rename = "type")]
+    pub metric_type: Option<String>,
+    pub value: Option<String>,
+    pub slice: Option<String>,
+    pub confidence_interval: Option<ConfidenceInterval>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfidenceInterval {
+    pub lower_bound: Option<String>,
+    pub upper_bound: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Considerations {
+    pub users: Option<Vec<String>>,
+    pub use_cases: Option<Vec<String>>,
+    pub technical_limitations: Option<Vec<String>>,
+    pub performance_tradeoffs: Option<Vec<String>>,
+    pub ethical_considerations: Option<Vec<EthicalConsideration>>,
+    pub fairness_assessments: Option<Vec<FairnessAssessment>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EthicalConsideration {
+    pub name: Option<String>,
+    pub mitigation_strategy: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FairnessAssessment {
+    pub group_at_risk: Option<String>,
+    pub benefits: Option<String>,
+    pub harms: Option<String>,
+    pub mitigation_strategy: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use crate::{
+    DataGovernance, ExternalReference, Extra, LicenseChoice, OrganizationalEntity, Property,
+    ReleaseNotes, Signature,
+};
+
+/// External API the software calls or the software itself when it is a
+/// service
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Service {
+    #[serde(rename = "bom-ref")]
+    pub bom_ref: Option<String>,
+    pub provider: Option<OrganizationalEntity>,
+    pub group: Option<String>,
+    pub name: String,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    pub endpoints: Option<Vec<String>>,
+    pub authenticated: Option<bool>,
+    #[serde(rename = "x-trust-boundary")]
+    pub x_trust_boundary: Option<bool>,
+    pub trust_zone: Option<String>,
+    pub data: Option<Vec<ServiceData>>,
+    pub licenses: Option<Vec<LicenseChoice>>,
+    pub external_references: Option<Vec<ExternalReference>>,
+    pub properties: Option<Vec<Property>>,
+    pub services: Option<Vec<Service>>,
+    pub release_notes: Option<ReleaseNotes>,
+    pub signature: Option<Signature>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// Data the service sends or receives
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct ServiceData {
+    /// inbound, outbound, bi-directional or unknown
+    pub flow: String,
+    pub classification: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub governance: Option<DataGovernance>,
+    /// URIs or `bom-ref`s of the services the data comes from
+    pub source: Option<Vec<String>>,
+    /// URIs or `bom-ref`s of the services the data goes to
+    pub destination: Option<Vec<String>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
@@ -0,0 +1,693 @@
+//! SPDX packages to CycloneDX components and back. Everything that has no
+//! counterpart on the other side is listed in [`Conversion::unmapped`].
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use super::{
+    Checksum, CreationInfo, ExternalRef, Package, Relationship, SpdxDocument, DOCUMENT_ID,
+    NOASSERTION,
+};
+use crate::{
+    Component, ComponentType, CycloneDXBom, Dependency, ExternalReference, Hash, License,
+    LicenseChoice, Metadata, OrganizationalContact, OrganizationalEntity, Property, Tools,
+};
+
+/// Converted document and what could not be taken over, e.g.
+/// `package SPDXRef-foo: packageFileName`
+#[derive(Debug, Clone)]
+pub struct Conversion<T> {
+    pub output: T,
+    pub unmapped: Vec<String>,
+}
+
+/// Properties of the BOM metadata that keep the SPDX document identity
+const DOCUMENT_NAME: &str = "spdx:documentName";
+const DOCUMENT_NAMESPACE: &str = "spdx:documentNamespace";
+
+/// Type of the `OTHER` external refs that keep the component properties, the
+/// locator is the name and the comment the value
+const PROPERTY_REF_TYPE: &str = "cdx-property";
+
+/// SPDX checksum algorithms and the CycloneDX hash algorithms
+const ALGORITHMS: &[(&str, &str)] = &[
+    ("MD5", "MD5"),
+    ("SHA1", "SHA-1"),
+    ("SHA256", "SHA-256"),
+    ("SHA384", "SHA-384"),
+    ("SHA512", "SHA-512"),
+    ("SHA3-256", "SHA3-256"),
+    ("SHA3-384", "SHA3-384"),
+    ("SHA3-512", "SHA3-512"),
+    ("BLAKE2b-256", "BLAKE2b-256"),
+    ("BLAKE2b-384", "BLAKE2b-384"),
+    ("BLAKE2b-512", "BLAKE2b-512"),
+    ("BLAKE3", "BLAKE3"),
+];
+
+/// SPDX primary package purposes and the CycloneDX component types
+const PURPOSES: &[(&str, ComponentType)] = &[
+    ("APPLICATION", ComponentType::Application),
+    ("FRAMEWORK", ComponentType::Framework),
+    ("LIBRARY", ComponentType::Library),
+    ("CONTAINER", ComponentType::Container),
+    ("OPERATING-SYSTEM", ComponentType::OperatingSystem),
+    ("DEVICE", ComponentType::Device),
+    ("FIRMWARE", ComponentType::Firmware),
+    ("FILE", ComponentType::File),
+];
+
+/// Component fields that have a place in an SPDX package
+const COMPONENT_FIELDS: &[&str] = &[
+    "type",
+    "bom-ref",
+    "name",
+    "version",
+    "supplier",
+    "author",
+    "description",
+    "hashes",
+    "licenses",
+    "copyright",
+    "cpe",
+    "purl",
+    "externalReferences",
+    "properties",
+    "components",
+];
+
+/// Whether the SPDX value tells nothing
+fn is_empty(value: &str) -> bool {
+    value.is_empty() || value == NOASSERTION || value == "NONE"
+}
+
+/// Kind, name and email of `Person: name (email)`
+fn split_actor(actor: &str) -> Option<(&str, &str, Option<&str>)> {
+    let (kind, rest) = actor.split_once(':')?;
+    let rest = rest.trim();
+    match rest.strip_suffix(')').and_then(|rest| rest.rsplit_once('(')) {
+        Some((name, email)) => {
+            Some((kind.trim(), name.trim(), Some(email.trim()).filter(|email| !email.is_empty())))
+        }
+        None => Some((kind.trim(), rest, None)),
+    }
+}
+
+/// UUID at the end of the document namespace, most tools end it with one
+fn namespace_uuid(namespace: &str) -> Option<&str> {
+    let uuid = namespace.get(namespace.len().checked_sub(36)?..)?;
+    let groups = uuid.split('-').map(str::len).collect::<Vec<_>>();
+    (groups == [8, 4, 4, 4, 12] && uuid.chars().all(|c| c == '-' || c.is_ascii_hexdigit()))
+        .then_some(uuid)
+}
+
+/// License of the package, an SPDX license ID when it is a single one
+fn license_choice(expression: &str) -> LicenseChoice {
+    let simple = expression.chars().all(|c| c.is_ascii_alphanumeric() || "-.+".contains(c));
+    let license = |license| LicenseChoice { license: Some(license), ..Default::default() };
+    match expression.strip_prefix("LicenseRef-") {
+        _ if !simple => {
+            LicenseChoice { expression: Some(expression.to_string()), ..Default::default() }
+        }
+        Some(_) => license(License { name: Some(expression.to_string()), ..Default::default() }),
+        None => license(License { id: Some(expression.to_string()), ..Default::default() }),
+    }
+}
+
+impl SpdxDocument {
+    pub fn to_cyclonedx(&self) -> Conversion<CycloneDXBom> {
+        let mut unmapped = vec![];
+        unmapped.extend(self.extra.keys().map(|key| format!("document: {key}")));
+        unmapped.extend(self.creation_info.extra.keys().map(|key| format!("creationInfo: {key}")));
+
+        let mut described = self.document_describes.clone().unwrap_or_default();
+        for relationship in &self.relationships {
+            let id = match relationship.relationship_type.as_str() {
+                "DESCRIBES" if relationship.spdx_element_id == self.spdx_id => {
+                    &relationship.related_spdx_element
+                }
+                "DESCRIBED_BY" if relationship.related_spdx_element == self.spdx_id => {
+                    &relationship.spdx_element_id
+                }
+                _ => continue,
+            };
+            if !described.contains(id) {
+                described.push(id.clone());
+            }
+        }
+        let mut root = None;
+        let mut components = vec![];
+        for package in &self.packages {
+            let component = package_to_component(package, &mut unmapped);
+            if described.len() == 1 && described[0] == package.spdx_id {
+                root = Some(component);
+            } else {
+                components.push(component);
+            }
+        }
+
+        let mut tools = vec![];
+        let mut authors = vec![];
+        for creator in &self.creation_info.creators {
+            match split_actor(creator) {
+                Some(("Tool", name, _)) => tools.push(Component {
+                    component_type: ComponentType::Application,
+                    name: name.to_string(),
+                    ..Default::default()
+                }),
+                Some(("Person" | "Organization", name, email)) => {
+                    authors.push(OrganizationalContact {
+                        name: Some(name.to_string()),
+                        email: email.map(str::to_string),
+                        ..Default::default()
+                    })
+                }
+                _ => unmapped.push(format!("creator {creator:?}")),
+            }
+        }
+
+        let packages = self.packages.iter().map(|p| p.spdx_id.as_str()).collect::<HashSet<_>>();
+        let mut dependencies: Vec<Dependency> = vec![];
+        for relationship in &self.relationships {
+            let Relationship { spdx_element_id: element, related_spdx_element: related, .. } =
+                relationship;
+            let kind = relationship.relationship_type.as_str();
+            let name = format!("relationship {element} {kind} {related}");
+            let (from, to) = match kind {
+                "DESCRIBES" if *element == self.spdx_id => continue,
+                "DESCRIBED_BY" if *related == self.spdx_id => continue,
+                "DEPENDS_ON" => (element, related),
+                "DEPENDENCY_OF" => (related, element),
+                _ if kind.ends_with("_DEPENDENCY_OF") => {
+                    unmapped.push(format!("{name}: kept as a plain dependency"));
+                    (related, element)
+                }
+                _ => {
+                    unmapped.push(name);
+                    continue;
+                }
+            };
+            if let Some(id) = [from, to].into_iter().find(|id| !packages.contains(id.as_str())) {
+                unmapped.push(format!("{name}: {id} is not a package"));
+                continue;
+            }
+            if relationship.comment.is_some() {
+                unmapped.push(format!("{name}: comment"));
+            }
+            match dependencies.iter_mut().find(|dependency| dependency.reference == *from) {
+                Some(dependency) => {
+                    dependency.depends_on.get_or_insert_with(Vec::new).push(to.clone())
+                }
+                None => dependencies.push(Dependency {
+                    reference: from.clone(),
+                    depends_on: Some(vec![to.clone()]),
+                    ..Default::default()
+                }),
+            }
+        }
+
+        let timestamp = match DateTime::parse_from_rfc3339(&self.creation_info.created) {
+            Ok(timestamp) => Some(timestamp),
+            Err(_) => {
+                unmapped.push(format!("creationInfo: created {:?}", self.creation_info.created));
+                None
+            }
+        };
+        let properties =
+            [(DOCUMENT_NAME, &self.name), (DOCUMENT_NAMESPACE, &self.document_namespace)]
+                .into_iter()
+                .filter(|(_, value)| !value.is_empty())
+                .map(|(name, value)| Property {
+                    name: name.to_string(),
+                    value: value.clone(),
+                    ..Default::default()
+                })
+                .collect::<Vec<_>>();
+        let metadata = Metadata {
+            timestamp,
+            tools: (!tools.is_empty()).then(|| Tools::Objects {
+                components: Some(tools),
+                services: None,
+                extra: Default::default(),
+            }),
+            authors: (!authors.is_empty()).then_some(authors),
+            component: root,
+            properties: (!properties.is_empty()).then_some(properties),
+            ..Default::default()
+        };
+        let bom = CycloneDXBom {
+            bom_format: "CycloneDX".to_string(),
+            spec_version: "1.5".to_string(),
+            serial_number: namespace_uuid(&self.document_namespace)
+                .map(|uuid| format!("urn:uuid:{uuid}")),
+            version: 1,
+            metadata: Some(metadata),
+            components,
+            dependencies: (!dependencies.is_empty()).then_some(dependencies),
+            ..Default::default()
+        };
+        Conversion { output: bom, unmapped }
+    }
+
+    pub fn from_cyclonedx(bom: &CycloneDXBom) -> Conversion<SpdxDocument> {
+        let mut unmapped = vec![];
+        let bom_fields = [
+            "$schema",
+            "bomFormat",
+            "specVersion",
+            "serialNumber",
+            "version",
+            "metadata",
+            "components",
+            "dependencies",
+        ];
+        unmapped.extend(unmapped_fields(bom, &bom_fields).map(|key| format!("bom: {key}")));
+
+        let metadata = bom.metadata.clone().unwrap_or_default();
+        let metadata_fields = ["timestamp", "tools", "authors", "component", "properties"];
+        unmapped.extend(
+            unmapped_fields(&metadata, &metadata_fields).map(|key| format!("metadata: {key}")),
+        );
+        let property = |name: &str| {
+            metadata.properties.iter().flatten().find(|p| p.name == name).map(|p| p.value.clone())
+        };
+        for other in metadata.properties.iter().flatten() {
+            if other.name != DOCUMENT_NAME && other.name != DOCUMENT_NAMESPACE {
+                unmapped.push(format!("metadata: property {}", other.name));
+            }
+        }
+
+        let mut packages = Packages::default();
+        let root = metadata.component.as_ref().map(|c| packages.add(c, None, &mut unmapped));
+        let top = bom.components.iter().map(|c| packages.add(c, None, &mut unmapped)).collect();
+        for described in root.map_or(top, |root| vec![root]) {
+            packages.relate(DOCUMENT_ID, "DESCRIBES", described);
+        }
+        for dependency in bom.dependencies.iter().flatten() {
+            let Some(from) = packages.ids.get(&dependency.reference).cloned() else {
+                unmapped.push(format!("dependency {}: not a component", dependency.reference));
+                continue;
+            };
+            for to in dependency.depends_on.iter().flatten() {
+                match packages.ids.get(to).cloned() {
+                    Some(to) => packages.relate_ids(from.clone(), "DEPENDS_ON", to),
+                    None => unmapped.push(format!(
+                        "dependency {}: {to} is not a component",
+                        dependency.reference
+                    )),
+                }
+            }
+        }
+
+        let mut creators = vec![];
+        let tool = |tool: &Component| match &tool.version {
+            Some(version) => format!("Tool: {}-{version}", tool.name),
+            None => format!("Tool: {}", tool.name),
+        };
+        match &metadata.tools {
+            Some(Tools::Legacy(tools)) => creators.extend(tools.iter().map(|t| {
+                let name = t.name.clone().unwrap_or_default();
+                tool(&Component { name, version: t.version.clone(), ..Default::default() })
+            })),
+            Some(Tools::Objects { components, services, .. }) => {
+                creators.extend(components.iter().flatten().map(tool));
+                creators.extend(services.iter().flatten().map(|service| {
+                    let name = service.name.clone();
+                    tool(&Component {
+                        name,
+                        version: service.version.clone(),
+                        ..Default::default()
+                    })
+                }));
+            }
+            None => {}
+        }
+        for author in metadata.authors.iter().flatten() {
+            let name = author.name.as_deref().unwrap_or_default();
+            creators.push(match &author.email {
+                Some(email) => format!("Person: {name} ({email})"),
+                None => format!("Person: {name}"),
+            });
+        }
+        if creators.is_empty() {
+            creators.push("Tool: anytree".to_string());
+        }
+
+        let name = property(DOCUMENT_NAME)
+            .or_else(|| metadata.component.as_ref().map(|c| c.name.clone()))
+            .unwrap_or_else(|| "sbom".to_string());
+        let namespace = property(DOCUMENT_NAMESPACE).unwrap_or_else(|| {
+            let uuid = bom.serial_number.as_deref().map(|s| s.trim_start_matches("urn:uuid:"));
+            match uuid {
+                Some(uuid) => format!("https://spdx.org/spdxdocs/{name}-{uuid}"),
+                None => format!("https://spdx.org/spdxdocs/{name}"),
+            }
+        });
+        let created = metadata.timestamp.map_or_else(Utc::now, |t| t.with_timezone(&Utc));
+        let document = SpdxDocument {
+            spdx_version: "SPDX-2.3".to_string(),
+            data_license: "CC0-1.0".to_string(),
+            spdx_id: DOCUMENT_ID.to_string(),
+            name,
+            document_namespace: namespace,
+            creation_info: CreationInfo {
+                created: created.to_rfc3339_opts(SecondsFormat::Secs, true),
+                creators,
+                ..Default::default()
+            },
+            packages: packages.packages,
+            relationships: packages.relationships,
+            ..Default::default()
+        };
+        Conversion { output: document, unmapped }
+    }
+}
+
+/// Non-empty fields of the serialized value that are not in `mapped`
+fn unmapped_fields<'a>(
+    value: &impl serde::Serialize,
+    mapped: &'a [&str],
+) -> impl Iterator<Item = String> + 'a {
+    let value = serde_json::to_value(value).unwrap_or_default();
+    let keys = match value {
+        serde_json::Value::Object(object) => object
+            .into_iter()
+            .filter(|(_, value)| !matches!(value, serde_json::Value::Array(a) if a.is_empty()))
+            .map(|(key, _)| key)
+            .collect(),
+        _ => vec![],
+    };
+    keys.into_iter().filter(move |key| !mapped.contains(&key.as_str()))
+}
+
+fn package_to_component(package: &Package, unmapped: &mut Vec<String>) -> Component {
+    let mut note = |what: String| unmapped.push(format!("package {}: {what}", package.spdx_id));
+    for key in package.extra.keys() {
+        note(key.clone());
+    }
+
+    let purpose = package.primary_package_purpose.as_deref();
+    let component_type = match PURPOSES.iter().find(|(name, _)| Some(*name) == purpose) {
+        Some((_, component_type)) => component_type.clone(),
+        None => {
+            if let Some(purpose) = purpose {
+                note(format!("primaryPackagePurpose {purpose}"));
+            }
+            ComponentType::Library
+        }
+    };
+    fn actor(actor: &Option<String>) -> Option<(&str, &str, Option<&str>)> {
+        actor.as_deref().filter(|actor| !is_empty(actor)).and_then(split_actor)
+    }
+    let supplier = actor(&package.supplier).map(|(_, name, email)| OrganizationalEntity {
+        name: Some(name.to_string()),
+        contact: email.map(|email| {
+            vec![OrganizationalContact { email: Some(email.to_string()), ..Default::default() }]
+        }),
+        ..Default::default()
+    });
+    let author = actor(&package.originator).map(|(_, name, email)| match email {
+        Some(email) => format!("{name} ({email})"),
+        None => name.to_string(),
+    });
+
+    let mut external_references = vec![];
+    let mut reference = |url: &str, ref_type: &str| {
+        external_references.push(ExternalReference {
+            url: url.to_string(),
+            ref_type: ref_type.to_string(),
+            ..Default::default()
+        })
+    };
+    if !is_empty(&package.download_location) {
+        match package.download_location.strip_prefix("git+") {
+            Some(url) => reference(url, "vcs"),
+            None => reference(&package.download_location, "distribution"),
+        }
+    }
+    if let Some(homepage) = package.homepage.as_deref().filter(|homepage| !is_empty(homepage)) {
+        reference(homepage, "website");
+    }
+    let (mut purl, mut cpe) = (None, None);
+    let mut properties = vec![];
+    for external_ref in package.external_refs.iter().flatten() {
+        let ExternalRef { reference_category: category, reference_type, reference_locator, .. } =
+            external_ref;
+        match (category.replace('_', "-").as_str(), reference_type.as_str()) {
+            ("OTHER", PROPERTY_REF_TYPE) => {
+                properties.push(Property {
+                    name: reference_locator.clone(),
+                    value: external_ref.comment.clone().unwrap_or_default(),
+                    ..Default::default()
+                });
+                continue;
+            }
+            ("PACKAGE-MANAGER", "purl") if purl.is_none() => purl = Some(reference_locator.clone()),
+            ("SECURITY", "cpe22Type" | "cpe23Type") if cpe.is_none() => {
+                cpe = Some(reference_locator.clone())
+            }
+            ("SECURITY", "advisory") => reference(reference_locator, "advisories"),
+            _ => {
+                note(format!("externalRef {category} {reference_type} {reference_locator}"));
+                continue;
+            }
+        }
+        if external_ref.comment.is_some() {
+            note(format!("externalRef {reference_locator}: comment"));
+        }
+    }
+
+    let mut hashes = vec![];
+    for checksum in package.checksums.iter().flatten() {
+        match ALGORITHMS.iter().find(|(spdx, _)| *spdx == checksum.algorithm) {
+            Some((_, alg)) => hashes.push(Hash {
+                alg: alg.to_string(),
+                content: checksum.checksum_value.clone(),
+                ..Default::default()
+            }),
+            None => note(format!("checksum {}", checksum.algorithm)),
+        }
+    }
+
+    let license = |license: &Option<String>| {
+        license.as_deref().filter(|license| !is_empty(license)).map(str::to_string)
+    };
+    let (declared, concluded) =
+        (license(&package.license_declared), license(&package.license_concluded));
+    if let (Some(declared), Some(concluded)) = (&declared, &concluded) {
+        if declared != concluded {
+            note(format!("licenseConcluded {concluded}"));
+        }
+    }
+    let description = match (&package.description, &package.summary) {
+        (Some(description), Some(_)) => {
+            note("summary".to_string());
+            Some(description.clone())
+        }
+        (description, summary) => description.clone().or_else(|| summary.clone()),
+    };
+
+    Component {
+        component_type,
+        bom_ref: Some(package.spdx_id.clone()),
+        supplier,
+        author,
+        name: package.name.clone(),
+        version: package.version_info.clone(),
+        description,
+        hashes: (!hashes.is_empty()).then_some(hashes),
+        licenses: declared.or(concluded).map(|license| vec![license_choice(&license)]),
+        copyright: package.copyright_text.clone().filter(|copyright| !is_empty(copyright)),
+        cpe,
+        purl,
+        external_references: (!external_references.is_empty()).then_some(external_references),
+        properties: (!properties.is_empty()).then_some(properties),
+        ..Default::default()
+    }
+}
+
+/// Packages made of the components, with the SPDX IDs of their `bom-ref`s
+#[derive(Default)]
+struct Packages {
+    packages: Vec<Package>,
+    relationships: Vec<Relationship>,
+    ids: HashMap<String, String>,
+    used: HashSet<String>,
+}
+
+impl Packages {
+    fn relate(&mut self, from: &str, kind: &str, to: String) {
+        self.relate_ids(from.to_string(), kind, to)
+    }
+
+    fn relate_ids(&mut self, from: String, kind: &str, to: String) {
+        self.relationships.push(Relationship {
+            spdx_element_id: from,
+            relationship_type: kind.to_string(),
+            related_spdx_element: to,
+            ..Default::default()
+        });
+    }
+
+    /// SPDX ID made of the `bom-ref` or the name, unique in the document
+    fn new_id(&mut self, component: &Component) -> String {
+        let base = component.bom_ref.as_deref().unwrap_or(&component.name);
+        let base = base.strip_prefix("SPDXRef-").unwrap_or(base);
+        let base = base
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '-' })
+            .collect::<String>();
+        let mut id = format!("SPDXRef-{base}");
+        for n in 2.. {
+            if self.used.insert(id.clone()) {
+                break;
+            }
+            id = format!("SPDXRef-{base}-{n}");
+        }
+        if let Some(bom_ref) = &component.bom_ref {
+            self.ids.insert(bom_ref.clone(), id.clone());
+        }
+        id
+    }
+
+    /// Adds the package of the component and of its subcomponents, contained
+    /// by it
+    fn add(
+        &mut self,
+        component: &Component,
+        parent: Option<&str>,
+        unmapped: &mut Vec<String>,
+    ) -> String {
+        let id = self.new_id(component);
+        let mut note =
+            |what: String| unmapped.push(format!("component {}: {what}", component.name));
+        for key in unmapped_fields(component, COMPONENT_FIELDS) {
+            note(key);
+        }
+
+        let primary_package_purpose =
+            match PURPOSES.iter().find(|(_, t)| *t == component.component_type) {
+                Some((purpose, _)) => purpose.to_string(),
+                None => {
+                    let value = serde_json::to_value(&component.component_type).unwrap_or_default();
+                    note(format!("type {}", value.as_str().unwrap_or_default()));
+                    "OTHER".to_string()
+                }
+            };
+        let supplier = component.supplier.as_ref().and_then(|supplier| {
+            let name = supplier.name.as_deref()?;
+            let email = supplier.contact.iter().flatten().find_map(|c| c.email.as_deref());
+            Some(match email {
+                Some(email) => format!("Organization: {name} ({email})"),
+                None => format!("Organization: {name}"),
+            })
+        });
+
+        let (mut download_location, mut homepage) = (None, None);
+        let mut external_refs = vec![];
+        let mut external_ref = |category: &str, reference_type: &str, locator: &str| {
+            external_refs.push(ExternalRef {
+                reference_category: category.to_string(),
+                reference_type: reference_type.to_string(),
+                reference_locator: locator.to_string(),
+                ..Default::default()
+            })
+        };
+        for reference in component.external_references.iter().flatten() {
+            match reference.ref_type.as_str() {
+                "vcs" if download_location.is_none() => {
+                    let scheme = reference.url.split_once("://").map(|(scheme, _)| scheme);
+                    download_location = Some(match scheme {
+                        Some(scheme) if scheme.contains('+') => reference.url.clone(),
+                        _ => format!("git+{}", reference.url),
+                    });
+                }
+                "distribution" if download_location.is_none() => {
+                    download_location = Some(reference.url.clone())
+                }
+                "website" if homepage.is_none() => homepage = Some(reference.url.clone()),
+                "advisories" => external_ref("SECURITY", "advisory", &reference.url),
+                other => note(format!("externalReference {other} {}", reference.url)),
+            }
+        }
+        if let Some(purl) = &component.purl {
+            external_ref("PACKAGE-MANAGER", "purl", purl);
+        }
+        if let Some(cpe) = &component.cpe {
+            let cpe_type = if cpe.starts_with("cpe:2.3:") { "cpe23Type" } else { "cpe22Type" };
+            external_ref("SECURITY", cpe_type, cpe);
+        }
+        for property in component.properties.iter().flatten() {
+            if property.name.is_empty() || property.name.contains(char::is_whitespace) {
+                note(format!("property {:?}", property.name));
+                continue;
+            }
+            external_refs.push(ExternalRef {
+                reference_category: "OTHER".to_string(),
+                reference_type: PROPERTY_REF_TYPE.to_string(),
+                reference_locator: property.name.clone(),
+                comment: Some(property.value.clone()),
+                ..Default::default()
+            });
+        }
+
+        let mut checksums = vec![];
+        for hash in component.hashes.iter().flatten() {
+            match ALGORITHMS.iter().find(|(_, alg)| *alg == hash.alg) {
+                Some((algorithm, _)) => checksums.push(Checksum {
+                    algorithm: algorithm.to_string(),
+                    checksum_value: hash.content.clone(),
+                    ..Default::default()
+                }),
+                None => note(format!("hash {}", hash.alg)),
+            }
+        }
+
+        let mut licenses = vec![];
+        for choice in component.licenses.iter().flatten() {
+            let license = choice.license.as_ref();
+            match (&choice.expression, license.and_then(|l| l.id.as_ref())) {
+                (Some(expression), _) if expression.contains(' ') => {
+                    licenses.push(format!("({expression})"))
+                }
+                (Some(id), _) | (None, Some(id)) => licenses.push(id.clone()),
+                (None, None) => {
+                    let name = license.and_then(|l| l.name.as_deref()).unwrap_or_default();
+                    note(format!("license {name:?} without an SPDX ID"));
+                }
+            }
+        }
+        let license_declared = match licenses.as_slice() {
+            [] => NOASSERTION.to_string(),
+            [license] => license.trim_start_matches('(').trim_end_matches(')').to_string(),
+            _ => licenses.join(" AND "),
+        };
+
+        self.packages.push(Package {
+            spdx_id: id.clone(),
+            name: component.name.clone(),
+            version_info: component.version.clone(),
+            supplier,
+            originator: component.author.as_ref().map(|author| format!("Person: {author}")),
+            download_location: download_location.unwrap_or_else(|| NOASSERTION.to_string()),
+            files_analyzed: Some(false),
+            checksums: (!checksums.is_empty()).then_some(checksums),
+            homepage,
+            license_concluded: Some(NOASSERTION.to_string()),
+            license_declared: Some(license_declared),
+            copyright_text: component.copyright.clone(),
+            description: component.description.clone(),
+            external_refs: (!external_refs.is_empty()).then_some(external_refs),
+            primary_package_purpose: Some(primary_package_purpose),
+            ..Default::default()
+        });
+        if let Some(parent) = parent {
+            self.relate(parent, "CONTAINS", id.clone());
+        }
+        for child in component.components.iter().flatten() {
+            self.add(child, Some(&id), unmapped);
+        }
+        id
+    }
+}
@@ -0,0 +1,367 @@
+//! SPDX 2.3 documents, see https://spdx.github.io/spdx-spec/v2.3, and their
+//! conversion to and from [`CycloneDXBom`](crate::CycloneDXBom)
+//!
+//! Only the package information that has a place in CycloneDX is modelled,
+//! files, snippets and the rest of the fields are kept in `extra`.
+
+mod convert;
+mod tag_value;
+
+pub use convert::Conversion;
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use crate::Extra;
+
+/// SPDX ID of the document itself
+pub const DOCUMENT_ID: &str = "SPDXRef-DOCUMENT";
+
+/// Value of the fields with no information
+pub const NOASSERTION: &str = "NOASSERTION";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    TagValue,
+}
+
+impl Format {
+    /// Encoding of the SPDX document, `None` when it is not SPDX, e.g. a
+    /// CycloneDX SBOM
+    pub fn detect(data: &[u8]) -> Option<Format> {
+        #[derive(Deserialize)]
+        struct Version {
+            #[serde(rename = "spdxVersion")]
+            spdx_version: Option<String>,
+        }
+
+        let text = data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(data);
+        match text.iter().find(|b| !b.is_ascii_whitespace()) {
+            Some(b'{') => serde_json::from_slice::<Version>(text)
+                .map_or(false, |version| version.spdx_version.is_some())
+                .then_some(Format::Json),
+            Some(_) => String::from_utf8_lossy(text)
+                .lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty() && !line.starts_with('#'))
+                .map_or(false, |line| line.starts_with("SPDXVersion:"))
+                .then_some(Format::TagValue),
+            None => None,
+        }
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpdxDocument {
+    /// `SPDX-2.3`
+    pub spdx_version: String,
+    /// `CC0-1.0`
+    pub data_license: String,
+    #[serde(rename = "SPDXID")]
+    pub spdx_id: String,
+    pub name: String,
+    pub document_namespace: String,
+    pub creation_info: CreationInfo,
+    /// Deprecated in favour of `DESCRIBES` relationships
+    pub document_describes: Option<Vec<String>>,
+    #[serde(default)]
+    pub packages: Vec<Package>,
+    #[serde(default)]
+    pub relationships: Vec<Relationship>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreationInfo {
+    /// UTC time, e.g. `2023-08-01T10:00:00Z`
+    pub created: String,
+    /// `Tool: name-version`, `Organization: name (email)` or
+    /// `Person: name (email)`
+    pub creators: Vec<String>,
+    pub license_list_version: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Package {
+    #[serde(rename = "SPDXID")]
+    pub spdx_id: String,
+    pub name: String,
+    pub version_info: Option<String>,
+    /// `Organization: name (email)`, `Person: name (email)` or `NOASSERTION`
+    pub supplier: Option<String>,
+    /// Same as `supplier`
+    pub originator: Option<String>,
+    /// URL, VCS location, `NONE` or `NOASSERTION`
+    pub download_location: String,
+    pub files_analyzed: Option<bool>,
+    pub checksums: Option<Vec<Checksum>>,
+    pub homepage: Option<String>,
+    /// License expression, `NONE` or `NOASSERTION`
+    pub license_concluded: Option<String>,
+    pub license_declared: Option<String>,
+    pub copyright_text: Option<String>,
+    pub summary: Option<String>,
+    pub description: Option<String>,
+    pub external_refs: Option<Vec<ExternalRef>>,
+    /// APPLICATION, FRAMEWORK, LIBRARY, CONTAINER, OPERATING-SYSTEM, DEVICE,
+    /// FIRMWARE, SOURCE, ARCHIVE, FILE, INSTALL or OTHER
+    pub primary_package_purpose: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Checksum {
+    /// SHA1, SHA224, SHA256, SHA384, SHA512, SHA3-256, SHA3-384, SHA3-512,
+    /// BLAKE2b-256, BLAKE2b-384, BLAKE2b-512, BLAKE3, MD2, MD4, MD5, MD6 or
+    /// ADLER32
+    pub algorithm: String,
+    pub checksum_value: String,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalRef {
+    /// SECURITY, PACKAGE-MANAGER, PERSISTENT-ID or OTHER
+    pub reference_category: String,
+    /// e.g. purl, cpe23Type or advisory
+    pub reference_type: String,
+    pub reference_locator: String,
+    pub comment: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Relationship {
+    pub spdx_element_id: String,
+    /// e.g. DESCRIBES, DEPENDS_ON or DEPENDENCY_OF
+    pub relationship_type: String,
+    /// SPDX ID, `NONE` or `NOASSERTION`
+    pub related_spdx_element: String,
+    pub comment: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+impl SpdxDocument {
+    /// Parses the document in any of the encodings, see [`Format::detect`]
+    pub fn parse(data: &[u8]) -> anyhow::Result<Self> {
+        let format =
+            Format::detect(data).ok_or_else(|| anyhow::format_err!("Not an SPDX document"))?;
+        Self::parse_as(data, format)
+    }
+
+    pub fn parse_as(data: &[u8], format: Format) -> anyhow::Result<Self> {
+        match format {
+            Format::Json => Ok(serde_json::from_slice(data)?),
+            Format::TagValue => tag_value::read(std::str::from_utf8(data)?),
+        }
+    }
+
+    /// Encodes the document. Tag-value has no place for the fields in
+    /// `extra`.
+    pub fn write(&self, format: Format) -> anyhow::Result<Vec<u8>> {
+        match format {
+            Format::Json => {
+                let mut data = serde_json::to_vec_pretty(self)?;
+                data.push(b'\n');
+                Ok(data)
+            }
+            Format::TagValue => Ok(tag_value::write(self).into_bytes()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use serde_json::Value;
+
+    use super::*;
+    use crate::CycloneDXBom;
+
+    /// Name and version of the components, and names of the dependencies
+    fn summary(sbom: &CycloneDXBom) -> (BTreeSet<String>, BTreeSet<(String, String)>) {
+        let components = sbom.components.iter().map(|c| (c.bom_ref.clone(), c));
+        let names = components.collect::<std::collections::HashMap<_, _>>();
+        let versions = names
+            .values()
+            .map(|c| {
+                format!("{}@{} {:?}", c.name, c.version.as_deref().unwrap_or_default(), c.hashes)
+            })
+            .collect();
+        let name = |reference: &String| names[&Some(reference.clone())].name.clone();
+        let dependencies = sbom
+            .dependencies
+            .iter()
+            .flatten()
+            .flat_map(|d| d.depends_on.iter().flatten().map(|to| (name(&d.reference), name(to))))
+            .collect();
+        (versions, dependencies)
+    }
+
+    /// Properties of the components by name and version
+    fn properties(sbom: &CycloneDXBom) -> BTreeSet<(String, String, String)> {
+        let root = sbom.metadata.as_ref().and_then(|m| m.component.as_ref());
+        let components = root.into_iter().chain(&sbom.components);
+        components
+            .flat_map(|c| {
+                let version = c.version.clone().unwrap_or_default();
+                c.properties.iter().flatten().map(move |p| {
+                    (format!("{}@{version}", c.name), p.name.clone(), p.value.clone())
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_cyclonedx_round_trip() {
+        let sbom =
+            CycloneDXBom::parse(include_bytes!("../../../hack/cargo_sbom.cdx.json")).unwrap();
+        let spdx = SpdxDocument::from_cyclonedx(&sbom);
+        assert_eq!(
+            Some(&"Tool: anytree-0.0.0".to_string()),
+            spdx.output.creation_info.creators.first()
+        );
+        assert!(!spdx.unmapped.iter().any(|item| item.ends_with(": properties")));
+
+        for format in [Format::Json, Format::TagValue] {
+            let data = spdx.output.write(format).unwrap();
+            assert_eq!(Some(format), Format::detect(&data));
+            let document = SpdxDocument::parse(&data).unwrap();
+            assert_eq!(spdx.output, document);
+            let converted = document.to_cyclonedx();
+            assert_eq!(Vec::<String>::new(), converted.unmapped);
+            let root = converted.output.metadata.as_ref().unwrap().component.as_ref().unwrap();
+            assert_eq!("anytree-test-project", root.name);
+            assert_eq!(summary(&sbom), summary(&converted.output));
+            assert_eq!(properties(&sbom), properties(&converted.output));
+        }
+    }
+
+    #[test]
+    fn test_unmapped() {
+        let json = br#"{
+            "spdxVersion": "SPDX-2.3",
+            "dataLicense": "CC0-1.0",
+            "SPDXID": "SPDXRef-DOCUMENT",
+            "name": "app",
+            "documentNamespace": "https://example.com/app-3e671687-395b-41f5-a30f-a58921a69b79",
+            "creationInfo": {"created": "2023-08-01T10:00:00Z", "creators": ["Tool: syft-0.85.0"]},
+            "packages": [
+                {
+                    "SPDXID": "SPDXRef-app",
+                    "name": "app",
+                    "downloadLocation": "git+https://github.com/example/app.git",
+                    "primaryPackagePurpose": "APPLICATION"
+                },
+                {
+                    "SPDXID": "SPDXRef-serde",
+                    "name": "serde",
+                    "versionInfo": "1.0.0",
+                    "downloadLocation": "NOASSERTION",
+                    "checksums": [
+                        {"algorithm": "SHA256", "checksumValue": "abc"},
+                        {"algorithm": "MD2", "checksumValue": "def"}
+                    ],
+                    "licenseDeclared": "MIT OR Apache-2.0",
+                    "externalRefs": [{
+                        "referenceCategory": "PACKAGE_MANAGER",
+                        "referenceType": "purl",
+                        "referenceLocator": "pkg:cargo/serde@1.0.0"
+                    }],
+                    "packageFileName": "serde-1.0.0.crate"
+                }
+            ],
+            "files": [{"SPDXID": "SPDXRef-main", "fileName": "./src/main.rs"}],
+            "relationships": [
+                {"spdxElementId": "SPDXRef-DOCUMENT", "relationshipType": "DESCRIBES", "relatedSpdxElement": "SPDXRef-app"},
+                {"spdxElementId": "SPDXRef-serde", "relationshipType": "DEV_DEPENDENCY_OF", "relatedSpdxElement": "SPDXRef-app"},
+                {"spdxElementId": "SPDXRef-app", "relationshipType": "CONTAINS", "relatedSpdxElement": "SPDXRef-main"}
+            ]
+        }"#;
+        let converted = SpdxDocument::parse(json).unwrap().to_cyclonedx();
+        assert_eq!(
+            vec![
+                "document: files",
+                "package SPDXRef-serde: packageFileName",
+                "package SPDXRef-serde: checksum MD2",
+                "relationship SPDXRef-serde DEV_DEPENDENCY_OF SPDXRef-app: kept as a plain dependency",
+                "relationship SPDXRef-app CONTAINS SPDXRef-main",
+            ],
+            converted.unmapped
+        );
+        let sbom = converted.output;
+        assert_eq!(
+            Some("urn:uuid:3e671687-395b-41f5-a30f-a58921a69b79"),
+            sbom.serial_number.as_deref()
+        );
+        let root = sbom.metadata.as_ref().unwrap().component.as_ref().unwrap();
+        assert_eq!("vcs", root.external_references.as_ref().unwrap()[0].ref_type);
+        let serde = &sbom.components[0];
+        assert_eq!(Some("pkg:cargo/serde@1.0.0"), serde.purl.as_deref());
+        assert_eq!("SHA-256", serde.hashes.as_ref().unwrap()[0].alg);
+        let license = &serde.licenses.as_ref().unwrap()[0];
+        assert_eq!(Some("MIT OR Apache-2.0"), license.expression.as_deref());
+        let dependencies = sbom.dependencies.unwrap();
+        assert_eq!("SPDXRef-app", dependencies[0].reference);
+        assert_eq!(Some(vec!["SPDXRef-serde".to_string()]), dependencies[0].depends_on);
+    }
+
+    #[test]
+    fn test_tag_value() {
+        let text = b"# made by hand
+SPDXVersion: SPDX-2.3
+DataLicense: CC0-1.0
+SPDXID: SPDXRef-DOCUMENT
+DocumentName: app
+DocumentNamespace: https://example.com/app
+Creator: Person: Jane Doe (jane@example.com)
+Created: 2023-08-01T10:00:00Z
+
+PackageName: app
+SPDXID: SPDXRef-app
+PackageDownloadLocation: NOASSERTION
+PackageChecksum: SHA1: 85ed0817af83a24ad8da68c2b5094de69833983c
+PackageDescription: <text>First line
+second line</text>
+PackageFileName: app.tar.gz
+
+FileName: ./app
+SPDXID: SPDXRef-file
+
+Relationship: SPDXRef-DOCUMENT DESCRIBES SPDXRef-app
+";
+        assert_eq!(Some(Format::TagValue), Format::detect(text));
+        let document = SpdxDocument::parse(text).unwrap();
+        let package = &document.packages[0];
+        assert_eq!(Some("First line\nsecond line"), package.description.as_deref());
+        assert_eq!("SHA1", package.checksums.as_ref().unwrap()[0].algorithm);
+        assert_eq!(Some(&Value::from("app.tar.gz")), package.extra.get("PackageFileName"));
+        assert_eq!(
+            serde_json::json!([{"FileName": "./app", "SPDXID": "SPDXRef-file"}]),
+            document.extra["files"]
+        );
+        let sbom = document.to_cyclonedx().output;
+        let metadata = sbom.metadata.unwrap();
+        assert_eq!(Some("jane@example.com"), metadata.authors.unwrap()[0].email.as_deref());
+        assert_eq!("SHA-1", metadata.component.unwrap().hashes.unwrap()[0].alg);
+    }
+}
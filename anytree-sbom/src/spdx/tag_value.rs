@@ -0,0 +1,277 @@
+//! Tag-value encoding, `Tag: value` lines with multi-line values in
+//! `<text>...</text>`
+
+use std::fmt::Write;
+
+use anyhow::bail;
+use serde_json::Value;
+
+use super::{Checksum, ExternalRef, Package, Relationship, SpdxDocument};
+use crate::Extra;
+
+/// Tags that start the sections of the other elements, kept in `extra` of the
+/// document under the JSON name of the list
+const SECTIONS: &[(&str, &str)] = &[
+    ("FileName", "files"),
+    ("SnippetSPDXID", "snippets"),
+    ("LicenseID", "hasExtractedLicensingInfos"),
+    ("Annotator", "annotations"),
+];
+
+/// `Tag: value` pairs of the text
+fn pairs(text: &str) -> anyhow::Result<Vec<(&str, String)>> {
+    let mut pairs = vec![];
+    let mut lines = text.lines().enumerate();
+    while let Some((number, line)) = lines.next() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((tag, value)) = line.split_once(':') else {
+            bail!("Line {}: expected `Tag: value`, got {line:?}", number + 1);
+        };
+        let value = value.trim();
+        let Some(value) = value.strip_prefix("<text>") else {
+            pairs.push((tag.trim(), value.to_string()));
+            continue;
+        };
+        let mut text = value.to_string();
+        while !text.contains("</text>") {
+            let Some((_, line)) = lines.next() else {
+                bail!("Line {}: <text> of {tag} is not closed", number + 1);
+            };
+            text.push('\n');
+            text.push_str(line);
+        }
+        let end = text.find("</text>").unwrap_or(text.len());
+        text.truncate(end);
+        pairs.push((tag.trim(), text));
+    }
+    Ok(pairs)
+}
+
+/// Adds the value of the unknown tag, the values of a repeated tag go into an
+/// array
+fn insert(extra: &mut Extra, tag: &str, value: String) {
+    match extra.get_mut(tag) {
+        Some(Value::Array(values)) => values.push(value.into()),
+        Some(first) => *first = Value::Array(vec![first.take(), value.into()]),
+        None => {
+            extra.insert(tag.to_string(), value.into());
+        }
+    }
+}
+
+enum Section {
+    Document,
+    Package,
+    /// Item of the list in `extra` of the document
+    Other(&'static str),
+}
+
+pub(super) fn read(text: &str) -> anyhow::Result<SpdxDocument> {
+    let mut document = SpdxDocument::default();
+    let mut section = Section::Document;
+    for (tag, value) in pairs(text)? {
+        if let Some((_, list)) = SECTIONS.iter().find(|(start, _)| *start == tag) {
+            section = Section::Other(list);
+            let items = document.extra.entry(*list).or_insert_with(|| Value::Array(vec![]));
+            if let Value::Array(items) = items {
+                items.push(Value::Object(Extra::new()));
+            }
+        }
+        match tag {
+            "PackageName" => {
+                section = Section::Package;
+                document.packages.push(Package { name: value, ..Default::default() });
+                continue;
+            }
+            "Relationship" => {
+                let mut parts = value.split_whitespace();
+                let (Some(element), Some(relationship_type), Some(related), None) =
+                    (parts.next(), parts.next(), parts.next(), parts.next())
+                else {
+                    bail!("Expected `Relationship: <id> <type> <id>`, got {value:?}");
+                };
+                document.relationships.push(Relationship {
+                    spdx_element_id: element.to_string(),
+                    relationship_type: relationship_type.to_string(),
+                    related_spdx_element: related.to_string(),
+                    ..Default::default()
+                });
+                continue;
+            }
+            "RelationshipComment" => {
+                if let Some(relationship) = document.relationships.last_mut() {
+                    relationship.comment = Some(value);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        match (&section, document.packages.last_mut()) {
+            (Section::Package, Some(package)) => read_package_tag(package, tag, value)?,
+            (Section::Other(list), _) => {
+                if let Some(Value::Object(item)) =
+                    document.extra.get_mut(*list).and_then(|items| items.as_array_mut()?.last_mut())
+                {
+                    insert(item, tag, value);
+                }
+            }
+            _ => read_document_tag(&mut document, tag, value),
+        }
+    }
+    Ok(document)
+}
+
+fn read_document_tag(document: &mut SpdxDocument, tag: &str, value: String) {
+    match tag {
+        "SPDXVersion" => document.spdx_version = value,
+        "DataLicense" => document.data_license = value,
+        "SPDXID" => document.spdx_id = value,
+        "DocumentName" => document.name = value,
+        "DocumentNamespace" => document.document_namespace = value,
+        "Creator" => document.creation_info.creators.push(value),
+        "Created" => document.creation_info.created = value,
+        "LicenseListVersion" => document.creation_info.license_list_version = Some(value),
+        _ => insert(&mut document.extra, tag, value),
+    }
+}
+
+fn read_package_tag(package: &mut Package, tag: &str, value: String) -> anyhow::Result<()> {
+    match tag {
+        "SPDXID" => package.spdx_id = value,
+        "PackageVersion" => package.version_info = Some(value),
+        "PackageSupplier" => package.supplier = Some(value),
+        "PackageOriginator" => package.originator = Some(value),
+        "PackageDownloadLocation" => package.download_location = value,
+        "FilesAnalyzed" => package.files_analyzed = Some(value.parse()?),
+        "PackageChecksum" => {
+            let Some((algorithm, checksum)) = value.split_once(':') else {
+                bail!("Expected `PackageChecksum: <algorithm>: <value>`, got {value:?}");
+            };
+            package.checksums.get_or_insert_with(Vec::new).push(Checksum {
+                algorithm: algorithm.trim().to_string(),
+                checksum_value: checksum.trim().to_string(),
+                ..Default::default()
+            });
+        }
+        "PackageHomePage" => package.homepage = Some(value),
+        "PackageLicenseConcluded" => package.license_concluded = Some(value),
+        "PackageLicenseDeclared" => package.license_declared = Some(value),
+        "PackageCopyrightText" => package.copyright_text = Some(value),
+        "PackageSummary" => package.summary = Some(value),
+        "PackageDescription" => package.description = Some(value),
+        "ExternalRef" => {
+            let mut parts = value.split_whitespace();
+            let (Some(category), Some(reference_type), Some(locator), None) =
+                (parts.next(), parts.next(), parts.next(), parts.next())
+            else {
+                bail!("Expected `ExternalRef: <category> <type> <locator>`, got {value:?}");
+            };
+            package.external_refs.get_or_insert_with(Vec::new).push(ExternalRef {
+                reference_category: category.to_string(),
+                reference_type: reference_type.to_string(),
+                reference_locator: locator.to_string(),
+                ..Default::default()
+            });
+        }
+        "ExternalRefComment" => {
+            if let Some(external_ref) = package.external_refs.as_mut().and_then(|r| r.last_mut()) {
+                external_ref.comment = Some(value);
+            }
+        }
+        "PrimaryPackagePurpose" => package.primary_package_purpose = Some(value),
+        _ => insert(&mut package.extra, tag, value),
+    }
+    Ok(())
+}
+
+fn line(out: &mut String, tag: &str, value: &str) {
+    if value.contains('\n') {
+        let _ = writeln!(out, "{tag}: <text>{value}</text>");
+    } else {
+        let _ = writeln!(out, "{tag}: {value}");
+    }
+}
+
+fn optional(out: &mut String, tag: &str, value: &Option<String>) {
+    if let Some(value) = value {
+        line(out, tag, value);
+    }
+}
+
+pub(super) fn write(document: &SpdxDocument) -> String {
+    let mut out = String::new();
+    line(&mut out, "SPDXVersion", &document.spdx_version);
+    line(&mut out, "DataLicense", &document.data_license);
+    line(&mut out, "SPDXID", &document.spdx_id);
+    line(&mut out, "DocumentName", &document.name);
+    line(&mut out, "DocumentNamespace", &document.document_namespace);
+    for creator in &document.creation_info.creators {
+        line(&mut out, "Creator", creator);
+    }
+    line(&mut out, "Created", &document.creation_info.created);
+    optional(&mut out, "LicenseListVersion", &document.creation_info.license_list_version);
+
+    for package in &document.packages {
+        out.push('\n');
+        line(&mut out, "PackageName", &package.name);
+        line(&mut out, "SPDXID", &package.spdx_id);
+        optional(&mut out, "PackageVersion", &package.version_info);
+        optional(&mut out, "PackageSupplier", &package.supplier);
+        optional(&mut out, "PackageOriginator", &package.originator);
+        line(&mut out, "PackageDownloadLocation", &package.download_location);
+        optional(&mut out, "FilesAnalyzed", &package.files_analyzed.map(|b| b.to_string()));
+        for checksum in package.checksums.iter().flatten() {
+            let checksum = format!("{}: {}", checksum.algorithm, checksum.checksum_value);
+            line(&mut out, "PackageChecksum", &checksum);
+        }
+        optional(&mut out, "PackageHomePage", &package.homepage);
+        optional(&mut out, "PackageLicenseConcluded", &package.license_concluded);
+        optional(&mut out, "PackageLicenseDeclared", &package.license_declared);
+        optional(&mut out, "PackageCopyrightText", &package.copyright_text);
+        optional(&mut out, "PackageSummary", &package.summary);
+        optional(&mut out, "PackageDescription", &package.description);
+        for external_ref in package.external_refs.iter().flatten() {
+            let value = format!(
+                "{} {} {}",
+                external_ref.reference_category,
+                external_ref.reference_type,
+                external_ref.reference_locator
+            );
+            line(&mut out, "ExternalRef", &value);
+            optional(&mut out, "ExternalRefComment", &external_ref.comment);
+        }
+        optional(&mut out, "PrimaryPackagePurpose", &package.primary_package_purpose);
+    }
+
+    let described = document.document_describes.iter().flatten().filter(|id| {
+        !document.relationships.iter().any(|relationship| {
+            relationship.spdx_element_id == document.spdx_id
+                && relationship.relationship_type == "DESCRIBES"
+                && &relationship.related_spdx_element == *id
+        })
+    });
+    let describes = described.map(|id| Relationship {
+        spdx_element_id: document.spdx_id.clone(),
+        relationship_type: "DESCRIBES".to_string(),
+        related_spdx_element: id.clone(),
+        ..Default::default()
+    });
+    let relationships = describes.chain(document.relationships.iter().cloned()).collect::<Vec<_>>();
+    if !relationships.is_empty() {
+        out.push('\n');
+    }
+    for relationship in &relationships {
+        let value = format!(
+            "{} {} {}",
+            relationship.spdx_element_id,
+            relationship.relationship_type,
+            relationship.related_spdx_element
+        );
+        line(&mut out, "Relationship", &value);
+        optional(&mut out, "RelationshipComment", &relationship.comment);
+    }
+    out
+}
@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use crate::{
+    Attachment, Extra, OrganizationalContact, OrganizationalEntity, Property, Timestamp, Tools,
+};
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Vulnerability {
+    #[serde(rename = "bom-ref")]
+    pub bom_ref: Option<String>,
+    /// E.g. a CVE or GHSA id
+    pub id: Option<String>,
+    pub source: Option<VulnerabilitySource>,
+    /// Same vulnerability in other sources
+    pub references: Option<Vec<VulnerabilityReference>>,
+    pub ratings: Option<Vec<Rating>>,
+    pub cwes: Option<Vec<i32>>,
+    pub description: Option<String>,
+    pub detail: Option<String>,
+    pub recommendation: Option<String>,
+    pub workaround: Option<String>,
+    pub proof_of_concept: Option<ProofOfConcept>,
+    pub advisories: Option<Vec<Advisory>>,
+    pub created: Option<Timestamp>,
+    pub published: Option<Timestamp>,
+    pub updated: Option<Timestamp>,
+    pub rejected: Option<Timestamp>,
+    pub credits: Option<Credits>,
+    pub tools: Option<Tools>,
+    pub analysis: Option<Analysis>,
+    pub affects: Option<Vec<Affect>>,
+    pub properties: Option<Vec<Property>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct VulnerabilitySource {
+    pub url: Option<String>,
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct VulnerabilityReference {
+    pub id: String,
+    pub source: VulnerabilitySource,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Rating {
+    pub source: Option<VulnerabilitySource>,
+    pub score: Option<f64>,
+    /// critical, high, medium, low, info, none or unknown
+    pub severity: Option<String>,
+    /// CVSSv2, CVSSv3, CVSSv31, CVSSv4, OWASP, SSVC or other
+    pub method: Option<String>,
+    pub vector: Option<String>,
+    pub justification: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProofOfConcept {
+    pub reproduction_steps: Option<String>,
+    pub environment: Option<String>,
+    pub supporting_material: Option<Vec<Attachment>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Advisory {
+    pub title: Option<String>,
+    pub url: String,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Credits {
+    pub organizations: Option<Vec<OrganizationalEntity>>,
+    pub individuals: Option<Vec<OrganizationalContact>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// Whether and how the vulnerability affects the components
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Analysis {
+    /// resolved, resolved_with_pedigree, exploitable, in_triage,
+    /// false_positive or not_affected
+    pub state: Option<String>,
+    /// code_not_present, code_not_reachable, requires_configuration,
+    /// requires_dependency, requires_environment, protected_by_compiler,
+    /// protected_at_runtime, protected_at_perimeter or
+    /// protected_by_mitigating_control
+    pub justification: Option<String>,
+    /// can_not_fix, will_not_fix, update, rollback or workaround_available
+    pub response: Option<Vec<String>>,
+    pub detail: Option<String>,
+    pub first_issued: Option<Timestamp>,
+    pub last_updated: Option<Timestamp>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Affect {
+    /// `bom-ref` of the affected component or service
+    #[serde(rename = "ref")]
+    pub reference: String,
+    pub versions: Option<Vec<AffectedVersion>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// Single `version` or a vers `range`, only one of them is set
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct AffectedVersion {
+    pub version: Option<String>,
+    pub range: Option<String>,
+    /// affected, unaffected or unknown
+    pub status: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
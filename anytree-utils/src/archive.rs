@@ -0,0 +1,231 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+use flate2::read::GzDecoder;
+use tar::EntryType;
+
+/// Layout the archive entries must follow
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExtractOptions<'a> {
+    /// Top level dir every entry must be in, e.g. `serde-1.0.0` for crates
+    pub prefix: Option<&'a str>,
+    /// Number of leading path components dropped from every entry
+    pub strip_components: usize,
+}
+
+/// Extracts a `.tar.gz` archive into `dest`.
+///
+/// Unlike `tar -xzf` it never writes outside of `dest`: entries with absolute
+/// paths or `..`, symlinks pointing outside of `dest`, device files and
+/// entries outside of the `prefix` dir make the whole extraction fail.
+/// Nothing is written through symlinks created by the archive itself.
+pub fn extract_tar_gz(
+    archive: impl Read,
+    dest: &Path,
+    options: ExtractOptions,
+) -> anyhow::Result<()> {
+    std::fs::create_dir_all(dest)?;
+    let mut archive = tar::Archive::new(GzDecoder::new(archive));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_path = entry.path()?.into_owned();
+        let entry_type = entry.header().entry_type();
+        if matches!(entry_type, EntryType::XGlobalHeader | EntryType::XHeader) {
+            continue;
+        }
+        let Some(relative) = entry_dest(&entry_path, options)? else {
+            continue;
+        };
+        let path = dest.join(&relative);
+        check_parents(dest, &relative)?;
+        // an entry replaces a symlink of the same name instead of writing
+        // through it
+        if std::fs::symlink_metadata(&path).map_or(false, |m| m.file_type().is_symlink()) {
+            std::fs::remove_file(&path)?;
+        }
+
+        match entry_type {
+            EntryType::Directory => std::fs::create_dir_all(&path)?,
+            EntryType::Regular | EntryType::Continuous => {
+                let mut file = File::create(&path)?;
+                std::io::copy(&mut entry, &mut file)?;
+                set_mode(&file, entry.header().mode()?)?;
+            }
+            EntryType::Symlink => {
+                let target = entry
+                    .link_name()?
+                    .ok_or(anyhow::format_err!("Symlink {:?} has no target", entry_path))?;
+                check_symlink(&relative, &target)
+                    .map_err(|e| anyhow::format_err!("Symlink {:?}: {e}", entry_path))?;
+                symlink(&target, &path)?;
+            }
+            EntryType::Link => {
+                let target = entry
+                    .link_name()?
+                    .ok_or(anyhow::format_err!("Hard link {:?} has no target", entry_path))?;
+                let target = entry_dest(&target, options)?.ok_or(anyhow::format_err!(
+                    "Hard link {:?} points outside of the archive",
+                    entry_path
+                ))?;
+                check_parents(dest, &target)?;
+                std::fs::hard_link(dest.join(target), &path)?;
+            }
+            other => anyhow::bail!("Unsupported entry {:?} of type {:?}", entry_path, other),
+        }
+    }
+    Ok(())
+}
+
+/// Path of the entry relative to the destination, `None` for the entries
+/// stripped completely, like the prefix dir itself
+fn entry_dest(path: &Path, options: ExtractOptions) -> anyhow::Result<Option<PathBuf>> {
+    let mut parts = vec![];
+    for part in path.components() {
+        match part {
+            Component::Normal(part) => parts.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                anyhow::bail!("Archive entry {:?} points outside of the destination", path)
+            }
+        }
+    }
+    if let Some(prefix) = options.prefix {
+        if parts.first().map_or(true, |first| *first != prefix) {
+            anyhow::bail!("Archive entry {:?} is outside of {prefix}/", path);
+        }
+    }
+    if parts.len() <= options.strip_components {
+        return Ok(None);
+    }
+    Ok(Some(parts[options.strip_components..].iter().collect()))
+}
+
+/// Existing parents of the entry are real dirs, not symlinks from the archive
+fn check_parents(dest: &Path, relative: &Path) -> anyhow::Result<()> {
+    let mut path = dest.to_path_buf();
+    let parents = relative.parent().map(Path::components).into_iter().flatten();
+    for part in parents {
+        path.push(part);
+        match std::fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                anyhow::bail!("Archive entry {:?} is written through a symlink", relative)
+            }
+            Ok(_) => {}
+            Err(_) => std::fs::create_dir_all(&path)?,
+        }
+    }
+    Ok(())
+}
+
+/// Relative symlink target stays inside of the destination
+fn check_symlink(link: &Path, target: &Path) -> anyhow::Result<()> {
+    let mut depth = link.components().count() - 1;
+    for part in target.components() {
+        match part {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir if depth > 0 => depth -= 1,
+            _ => anyhow::bail!("target {:?} is outside of the destination", target),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, path: &Path) -> anyhow::Result<()> {
+    std::os::unix::fs::symlink(target, path)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn symlink(target: &Path, _path: &Path) -> anyhow::Result<()> {
+    anyhow::bail!("Symlinks are not supported: {:?}", target)
+}
+
+#[cfg(unix)]
+fn set_mode(file: &File, mode: u32) -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    // keep the executable bits only, like tar does for a regular user
+    file.set_permissions(std::fs::Permissions::from_mode(0o644 | (mode & 0o111)))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_mode(_file: &File, _mode: u32) -> anyhow::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use tar::Header;
+
+    use super::*;
+
+    /// Archive with raw entry paths, `tar::Builder` refuses the malicious ones
+    fn archive(entries: &[(&str, EntryType, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(vec![], Compression::fast()));
+        for (path, entry_type, data) in entries {
+            let mut header = Header::new_gnu();
+            let name = &mut header.as_gnu_mut().unwrap().name;
+            name[..path.len()].copy_from_slice(path.as_bytes());
+            header.set_entry_type(*entry_type);
+            header.set_mode(0o755);
+            if *entry_type == EntryType::Symlink {
+                header.set_link_name(data).unwrap();
+                header.set_size(0);
+                header.set_cksum();
+                builder.append(&header, std::io::empty()).unwrap();
+            } else {
+                header.set_size(data.len() as u64);
+                header.set_cksum();
+                builder.append(&header, data.as_bytes()).unwrap();
+            }
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    fn extract(entries: &[(&str, EntryType, &str)]) -> (tempfile::TempDir, anyhow::Result<()>) {
+        let dir = tempfile::tempdir().unwrap();
+        let options = ExtractOptions { prefix: Some("foo-1.0.0"), strip_components: 1 };
+        let res = extract_tar_gz(archive(entries).as_slice(), &dir.path().join("src"), options);
+        (dir, res)
+    }
+
+    #[test]
+    fn test_extract_crate() {
+        let (dir, res) = extract(&[
+            ("foo-1.0.0/", EntryType::Directory, ""),
+            ("foo-1.0.0/Cargo.toml", EntryType::Regular, "[package]"),
+            ("foo-1.0.0/src/lib.rs", EntryType::Regular, "fn main() {}"),
+            ("foo-1.0.0/src/link.rs", EntryType::Symlink, "lib.rs"),
+        ]);
+        res.unwrap();
+        let src = dir.path().join("src");
+        assert_eq!("[package]", std::fs::read_to_string(src.join("Cargo.toml")).unwrap());
+        assert_eq!("fn main() {}", std::fs::read_to_string(src.join("src/link.rs")).unwrap());
+    }
+
+    #[test]
+    fn test_reject_escaping_entries() {
+        for entries in [
+            vec![("foo-1.0.0/../../evil", EntryType::Regular, "")],
+            vec![("/tmp/evil", EntryType::Regular, "")],
+            vec![("bar-1.0.0/lib.rs", EntryType::Regular, "")],
+            vec![("foo-1.0.0/link", EntryType::Symlink, "../../etc")],
+            vec![("foo-1.0.0/link", EntryType::Symlink, "/etc")],
+            vec![
+                ("foo-1.0.0/link", EntryType::Symlink, "."),
+                ("foo-1.0.0/link/evil", EntryType::Regular, ""),
+            ],
+            vec![("foo-1.0.0/dev", EntryType::Char, "")],
+        ] {
+            let (dir, res) = extract(&entries);
+            assert!(res.is_err(), "{entries:?} must be rejected");
+            assert!(!dir.path().join("evil").exists());
+        }
+    }
+}
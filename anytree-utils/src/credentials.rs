@@ -0,0 +1,348 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use git2::{Cred, CredentialType, RemoteCallbacks};
+use serde::Deserialize;
+
+/// User name for SSH and HTTP when neither the config nor the URL has one
+const DEFAULT_USERNAME: &str = "git";
+
+/// Credentials for the git remotes of one host, e.g.
+///
+/// ```toml
+/// [[credentials]]
+/// host = "github.com"
+/// token_env = "GITHUB_TOKEN"
+/// ```
+///
+/// They are only handed to libgit2 when the remote asks for them, nothing is
+/// put into the URLs or written to the repos and checkouts.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HostCredentials {
+    /// Host name, e.g. `github.com`, with the port when it is not the default
+    /// one of the scheme
+    pub host: String,
+    /// User name for SSH and HTTP, defaults to the one from the URL or `git`
+    pub username: Option<String>,
+    /// Private SSH key, `~/` is the home dir
+    pub ssh_key: Option<PathBuf>,
+    /// Env variable with the passphrase of `ssh_key`
+    pub ssh_key_passphrase_env: Option<String>,
+    /// Env variable with the token sent as the HTTP password
+    pub token_env: Option<String>,
+    /// netrc file with the HTTP login and password of the host, `~/` is the
+    /// home dir
+    pub netrc: Option<PathBuf>,
+}
+
+/// Credentials for the git remotes, per host
+#[derive(Debug, Clone, Default)]
+pub struct Credentials {
+    hosts: Vec<HostCredentials>,
+    /// Env variables the git CLI and the remote helpers run with, and the
+    /// `*_env` settings are read from, on top of the ones of the process
+    env: HashMap<String, String>,
+}
+
+impl Credentials {
+    pub fn new(hosts: Vec<HostCredentials>) -> Self {
+        Self { hosts, env: HashMap::new() }
+    }
+
+    /// Sets the env variable `name` for the git CLI, the remote helpers and
+    /// the `*_env` settings without changing the env of the process
+    pub fn with_env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.insert(name.into(), value.into());
+        self
+    }
+
+    pub(crate) fn env(&self) -> &HashMap<String, String> {
+        &self.env
+    }
+
+    /// Callbacks answering the credential requests of libgit2 with the
+    /// credentials of the host of the URL, or with ssh-agent and the git
+    /// credential helpers for hosts without any. Each kind of credential is
+    /// tried once, so rejected ones fail the fetch instead of looping.
+    pub(crate) fn callbacks(&self) -> RemoteCallbacks<'_> {
+        let mut callbacks = RemoteCallbacks::new();
+        let tried = Cell::new(CredentialType::empty());
+        callbacks.credentials(move |url, username_from_url, allowed| {
+            self.credential(&tried, url, username_from_url, allowed)
+        });
+        callbacks
+    }
+
+    /// Credential of the first allowed kind not in `tried` that is configured
+    /// for the host of the URL
+    fn credential(
+        &self,
+        tried: &Cell<CredentialType>,
+        url: &str,
+        username_from_url: Option<&str>,
+        allowed: CredentialType,
+    ) -> Result<Cred, git2::Error> {
+        let host = url_host(url).unwrap_or(url);
+        let Some(credentials) =
+            self.hosts.iter().find(|credentials| host_matches(&credentials.host, host))
+        else {
+            return self.default_credential(tried, url, username_from_url, allowed);
+        };
+        let username =
+            credentials.username.as_deref().or(username_from_url).unwrap_or(DEFAULT_USERNAME);
+        let allowed = allowed - tried.get();
+        if allowed.contains(CredentialType::USERNAME) {
+            tried.set(tried.get() | CredentialType::USERNAME);
+            return Cred::username(username);
+        }
+        if let (Some(key), true) = (&credentials.ssh_key, allowed.contains(CredentialType::SSH_KEY))
+        {
+            tried.set(tried.get() | CredentialType::SSH_KEY);
+            let passphrase = self.var(credentials.ssh_key_passphrase_env.as_deref())?;
+            return Cred::ssh_key(username, None, &expand_home(key), passphrase.as_deref());
+        }
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            tried.set(tried.get() | CredentialType::USER_PASS_PLAINTEXT);
+            if let Some(token) = self.var(credentials.token_env.as_deref())? {
+                return Cred::userpass_plaintext(username, &token);
+            }
+            if let Some(netrc) = &credentials.netrc {
+                let path = expand_home(netrc);
+                let netrc = std::fs::read_to_string(&path).map_err(|e| {
+                    git2::Error::from_str(&format!("Failed to read {:?}: {e}", path))
+                })?;
+                if let Some((login, password)) = netrc_login(&netrc, host) {
+                    return Cred::userpass_plaintext(&login, &password);
+                }
+            }
+        }
+        Err(git2::Error::from_str(&format!("Credentials for {host} were rejected or do not fit")))
+    }
+
+    /// Credential for a host without configured credentials, the ones the
+    /// git CLI would use: keys of ssh-agent and the git credential helpers
+    fn default_credential(
+        &self,
+        tried: &Cell<CredentialType>,
+        url: &str,
+        username_from_url: Option<&str>,
+        allowed: CredentialType,
+    ) -> Result<Cred, git2::Error> {
+        let username = username_from_url.unwrap_or(DEFAULT_USERNAME);
+        let allowed = allowed - tried.get();
+        if allowed.contains(CredentialType::USERNAME) {
+            tried.set(tried.get() | CredentialType::USERNAME);
+            return Cred::username(username);
+        }
+        if allowed.contains(CredentialType::SSH_KEY) {
+            tried.set(tried.get() | CredentialType::SSH_KEY);
+            return Cred::ssh_key_from_agent(username);
+        }
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            tried.set(tried.get() | CredentialType::USER_PASS_PLAINTEXT);
+            if let Ok(cred) = Cred::credential_helper(&self.git_config()?, url, username_from_url) {
+                return Ok(cred);
+            }
+        }
+        let host = url_host(url).unwrap_or(url);
+        Err(git2::Error::from_str(&format!(
+            "No credentials configured for {host}, and neither ssh-agent nor the git credential \
+             helpers have any that fit"
+        )))
+    }
+
+    /// Global git config with the credential helpers, the file of
+    /// `GIT_CONFIG_GLOBAL` when it is set like for the git CLI
+    fn git_config(&self) -> Result<git2::Config, git2::Error> {
+        match self.lookup("GIT_CONFIG_GLOBAL") {
+            Some(path) => git2::Config::open(Path::new(&path)),
+            None => git2::Config::open_default(),
+        }
+    }
+
+    /// Env variable `name`, from the ones set with [`Credentials::with_env`]
+    /// before the ones of the process
+    fn lookup(&self, name: &str) -> Option<String> {
+        self.env.get(name).cloned().or_else(|| std::env::var(name).ok())
+    }
+
+    fn var(&self, name: Option<&str>) -> Result<Option<String>, git2::Error> {
+        name.map(|name| {
+            self.lookup(name)
+                .ok_or_else(|| git2::Error::from_str(&format!("Env variable {name} is not set")))
+        })
+        .transpose()
+    }
+}
+
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), std::env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => Path::new(&home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// `host[:port]` of a URL, including the scp-like `user@host:path` ones
+fn url_host(url: &str) -> Option<&str> {
+    let authority = match url.split_once("://") {
+        Some((_, rest)) => rest.split('/').next()?,
+        None if url.contains('[') => &url[..url.find("]:")? + 1],
+        None => url.split_once(':')?.0,
+    };
+    Some(authority.rsplit_once('@').map_or(authority, |(_, host)| host))
+}
+
+/// Name and port of `host[:port]`, IPv6 addresses without the brackets of
+/// `[::1]:8080`
+fn split_port(host: &str) -> (&str, Option<&str>) {
+    if let Some(rest) = host.strip_prefix('[') {
+        return match rest.split_once(']') {
+            Some((name, port)) => (name, port.strip_prefix(':')),
+            None => (host, None),
+        };
+    }
+    match host.split_once(':') {
+        Some((name, port)) if !port.contains(':') => (name, Some(port)),
+        _ => (host, None),
+    }
+}
+
+/// Config host `github.com` is any port of the host, `host:8080` is that port
+fn host_matches(config_host: &str, host: &str) -> bool {
+    let (config_name, config_port) = split_port(config_host);
+    let (name, port) = split_port(host);
+    config_name == name && (config_port.is_none() || config_port == port)
+}
+
+/// Login and password of the `machine` entry of `host` in a netrc file, else
+/// of the `default` entry
+fn netrc_login(netrc: &str, host: &str) -> Option<(String, String)> {
+    let name = split_port(host).0;
+    let mut tokens = netrc.split_whitespace();
+    let mut entries = vec![];
+    while let Some(token) = tokens.next() {
+        match token {
+            "machine" => entries.push((tokens.next(), None, None)),
+            "default" => entries.push((None, None, None)),
+            "login" | "password" | "account" => {
+                let value = tokens.next();
+                if let Some(entry) = entries.last_mut() {
+                    match token {
+                        "login" => entry.1 = value,
+                        "password" => entry.2 = value,
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    let (_, login, password) = entries
+        .iter()
+        .find(|(machine, _, _)| *machine == Some(host) || *machine == Some(name))
+        .or_else(|| entries.iter().find(|(machine, _, _)| machine.is_none()))?;
+    Some((login.unwrap_or_default().to_string(), (*password)?.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hosts_and_netrc() {
+        assert_eq!(Some("github.com"), url_host("https://github.com/org/repo.git"));
+        assert_eq!(Some("host:2222"), url_host("ssh://git@host:2222/repo"));
+        assert_eq!(Some("github.com"), url_host("git@github.com:org/repo.git"));
+        assert!(host_matches("127.0.0.1", "127.0.0.1:8080"));
+        assert!(!host_matches("127.0.0.1:80", "127.0.0.1:8080"));
+        assert_eq!(Some("[::1]:8080"), url_host("http://user@[::1]:8080/repo"));
+        assert_eq!(Some("[::1]"), url_host("git@[::1]:org/repo.git"));
+        assert!(host_matches("::1", "[::1]:8080"));
+        assert!(host_matches("[::1]", "[::1]"));
+        assert!(host_matches("[::1]:8080", "[::1]:8080"));
+        assert!(!host_matches("[::1]:80", "[::1]:8080"));
+        assert!(!host_matches("::2", "[::1]:8080"));
+
+        let netrc = "machine github.com login user password secret\n\
+                     machine 127.0.0.1 login local password pass\n\
+                     default login anon password none";
+        let login = |host| netrc_login(netrc, host).map(|(login, _)| login);
+        assert_eq!(Some("user".to_string()), login("github.com"));
+        assert_eq!(Some("local".to_string()), login("127.0.0.1:8080"));
+        assert_eq!(Some("anon".to_string()), login("gitlab.com"));
+        let netrc = "machine ::1 login ipv6 password pass";
+        assert_eq!(Some("ipv6".to_string()), netrc_login(netrc, "[::1]:8080").map(|(l, _)| l));
+    }
+
+    #[test]
+    fn test_ssh_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = dir.path().join("id_ed25519");
+        let status = std::process::Command::new("ssh-keygen")
+            .args(["-q", "-t", "ed25519", "-N", "passphrase", "-f"])
+            .arg(&key)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let credentials = Credentials::new(vec![HostCredentials {
+            host: "github.com".to_string(),
+            ssh_key: Some(key),
+            ssh_key_passphrase_env: Some("ANYTREE_TEST_SSH_PASSPHRASE".to_string()),
+            ..Default::default()
+        }]);
+        let url = "git@github.com:org/repo.git";
+        let allowed = CredentialType::USERNAME | CredentialType::SSH_KEY;
+
+        let tried = Cell::new(CredentialType::empty());
+        let ssh_key = CredentialType::SSH_KEY;
+        let err = credentials.credential(&tried, url, Some("git"), ssh_key).err().unwrap();
+        assert_eq!("Env variable ANYTREE_TEST_SSH_PASSPHRASE is not set", err.message());
+
+        let credentials = credentials.with_env("ANYTREE_TEST_SSH_PASSPHRASE", "passphrase");
+        let tried = Cell::new(CredentialType::empty());
+        let credential = |allowed| credentials.credential(&tried, url, Some("git"), allowed);
+        assert_eq!(CredentialType::USERNAME.bits(), credential(allowed).unwrap().credtype());
+        let key = credential(allowed).unwrap();
+        assert_eq!(CredentialType::SSH_KEY.bits(), key.credtype());
+        assert!(key.has_username());
+        // a rejected key is not offered again
+        let err = credential(allowed).err().unwrap();
+        assert_eq!("Credentials for github.com were rejected or do not fit", err.message());
+    }
+
+    #[test]
+    fn test_default_credentials() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("gitconfig");
+        let helper = "!f() { echo username=helper; echo password=secret; }; f";
+        std::fs::write(&config, format!("[credential]\n\thelper = \"{helper}\"\n")).unwrap();
+        let credentials = Credentials::new(vec![HostCredentials {
+            host: "github.com".to_string(),
+            ..Default::default()
+        }])
+        .with_env("GIT_CONFIG_GLOBAL", config.to_string_lossy());
+
+        let tried = Cell::new(CredentialType::empty());
+        let url = "ssh://git@gitlab.com/org/repo.git";
+        let allowed = CredentialType::USERNAME | CredentialType::SSH_KEY;
+        let credential = |allowed| credentials.credential(&tried, url, Some("git"), allowed);
+        assert_eq!(CredentialType::USERNAME.bits(), credential(allowed).unwrap().credtype());
+        // the key of ssh-agent
+        assert_eq!(CredentialType::SSH_KEY.bits(), credential(allowed).unwrap().credtype());
+        let err = credential(allowed).err().unwrap();
+        assert!(err.message().starts_with("No credentials configured for gitlab.com"), "{err}");
+
+        let tried = Cell::new(CredentialType::empty());
+        let url = "https://gitlab.com/org/repo.git";
+        let plaintext = CredentialType::USER_PASS_PLAINTEXT;
+        let cred = credentials.credential(&tried, url, None, plaintext).unwrap();
+        assert_eq!(plaintext.bits(), cred.credtype());
+        assert!(credentials.credential(&tried, url, None, plaintext).is_err());
+
+        let credentials = Credentials::default().with_env("GIT_CONFIG_GLOBAL", "/dev/null");
+        let tried = Cell::new(CredentialType::empty());
+        assert!(credentials.credential(&tried, url, None, plaintext).is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use anytree_sbom::Hash;
+use anytree_sbom::{Component, Hash};
 use md5::{Digest, Md5};
 use serde::{Deserialize, Serialize};
 use sha1::Sha1;
@@ -32,6 +32,61 @@ pub fn check_hashes(hashes: &Vec<Hash>, data: impl AsRef<[u8]>) -> anyhow::Resul
     Ok(())
 }
 
+/// Component property with a tree hash of its sources, `<alg>:<hex digest>`
+/// of the [NAR](crate::nar) of the tree, e.g. `SHA-256:9f86...`. Unlike the
+/// hashes of `git archive` output it does not depend on the git version and
+/// the export attributes. May be repeated for several algorithms.
+pub const TREE_HASH_PROPERTY: &str = "tree_hash";
+
+/// Checks the tree hashes of `component`, if it has any, against the NAR
+/// made by `nar`
+pub fn check_tree_hashes(
+    component: &Component,
+    nar: impl FnOnce() -> anyhow::Result<Vec<u8>>,
+) -> anyhow::Result<()> {
+    let hashes = tree_hashes(component)?;
+    if hashes.is_empty() {
+        return Ok(());
+    }
+    check_hashes(&hashes, nar()?)
+        .map_err(|e| anyhow::format_err!("Tree hash of {} does not match: {e}", component.name))
+}
+
+/// Checks a git source of `component`: the tree hashes against the NAR made by
+/// `nar` when SBOM has any, else the legacy `hashes` against the `git archive`
+/// output made by `archive`
+pub fn check_source_hashes(
+    component: &Component,
+    archive: impl FnOnce() -> anyhow::Result<Vec<u8>>,
+    nar: impl FnOnce() -> anyhow::Result<Vec<u8>>,
+) -> anyhow::Result<()> {
+    if !tree_hashes(component)?.is_empty() {
+        return check_tree_hashes(component, nar);
+    }
+    match &component.hashes {
+        Some(hashes) => check_hashes(hashes, archive()?),
+        None => Ok(()),
+    }
+}
+
+/// Tree hashes of `component` as SBOM hashes of its NAR
+pub fn tree_hashes(component: &Component) -> anyhow::Result<Vec<Hash>> {
+    component
+        .properties
+        .iter()
+        .flatten()
+        .filter(|property| property.name == TREE_HASH_PROPERTY)
+        .map(|property| {
+            let (alg, content) = property.value.split_once(':').ok_or(anyhow::format_err!(
+                "Wrong {TREE_HASH_PROPERTY} of {}, expected <alg>:<hex digest>: {}",
+                component.name,
+                property.value
+            ))?;
+            Ok(Hash { alg: alg.to_string(), content: content.to_string(), ..Default::default() })
+        })
+        .collect()
+}
+
 fn count_hash(alg: impl AsRef<str>, data: impl AsRef<[u8]>) -> anyhow::Result<String> {
     let res = match serde_json::from_str::<Alg>(&format!("\"{}\"", alg.as_ref())) {
         Ok(Alg::MD5) => md5(data),
@@ -68,3 +123,34 @@ fn sha512(data: impl AsRef<[u8]>) -> Vec<u8> {
     hasher.update(data);
     hasher.finalize().to_vec()
 }
+
+#[cfg(test)]
+mod tests {
+    use anytree_sbom::Property;
+
+    use super::*;
+
+    #[test]
+    fn test_source_hashes() {
+        let hash = |data: &[u8]| Hash {
+            alg: "SHA-256".to_string(),
+            content: hex::encode(sha256(data)),
+            ..Default::default()
+        };
+        let archive = || Ok(b"archive".to_vec());
+        let mut component =
+            Component { hashes: Some(vec![hash(b"archive")]), ..Default::default() };
+        check_source_hashes(&component, archive, || unreachable!()).unwrap();
+        assert!(check_source_hashes(&component, || Ok(vec![]), || unreachable!()).is_err());
+
+        // tree hashes take over and need no archive
+        component.properties = Some(vec![Property {
+            name: TREE_HASH_PROPERTY.to_string(),
+            value: format!("SHA-256:{}", hash(b"nar").content),
+            ..Default::default()
+        }]);
+        check_source_hashes(&component, || unreachable!(), || Ok(b"nar".to_vec())).unwrap();
+        let err = check_source_hashes(&component, archive, || Ok(vec![])).unwrap_err();
+        assert!(err.to_string().starts_with("Tree hash of"), "{err}");
+    }
+}
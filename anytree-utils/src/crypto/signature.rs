@@ -0,0 +1,337 @@
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use ssh_key::{HashAlg, PublicKey, SshSig};
+
+const PGP_KEY_HEADER: &str = "-----BEGIN PGP PUBLIC KEY BLOCK-----";
+const PGP_SIGNATURE_HEADER: &str = "-----BEGIN PGP SIGNATURE-----";
+const SSH_SIGNATURE_HEADER: &str = "-----BEGIN SSH SIGNATURE-----";
+/// Namespace git signs commits and tags in
+const SSH_NAMESPACE: &str = "git";
+/// `gpg` status of a signature that is good but not trustworthy, or bad
+const REJECTED_STATUSES: [&str; 5] = ["BADSIG", "ERRSIG", "EXPSIG", "EXPKEYSIG", "REVKEYSIG"];
+
+/// Keys signatures are trusted from: OpenPGP keys checked with `gpg` and SSH
+/// keys checked in-process
+#[derive(Default)]
+pub struct TrustedKeys {
+    /// Armored or binary OpenPGP public keys
+    pgp: Vec<Vec<u8>>,
+    ssh: Vec<PublicKey>,
+}
+
+impl TrustedKeys {
+    /// Adds an armored OpenPGP public key block or an SSH public key line
+    pub fn add(&mut self, key: &str) -> anyhow::Result<()> {
+        let key = key.trim();
+        if key.starts_with(PGP_KEY_HEADER) {
+            self.pgp.push(key.as_bytes().to_vec());
+        } else {
+            let key = parse_ssh_key(key)?.ok_or(anyhow::format_err!(
+                "Key is not allowed to sign in the {SSH_NAMESPACE} namespace"
+            ))?;
+            self.ssh.push(key);
+        }
+        Ok(())
+    }
+
+    /// Adds the keys of a keyring file: an OpenPGP keyring, armored or
+    /// binary, or SSH public keys one per line, in `authorized_keys` or
+    /// `allowed_signers` format
+    pub fn add_keyring(&mut self, path: &Path) -> anyhow::Result<()> {
+        let data = std::fs::read(path)
+            .map_err(|e| anyhow::format_err!("Failed to read keyring {:?}: {e}", path))?;
+        match std::str::from_utf8(&data) {
+            Ok(text) if !text.contains(PGP_KEY_HEADER) => {
+                for line in text.lines().map(str::trim) {
+                    if line.is_empty() || line.starts_with('#') {
+                        continue;
+                    }
+                    match parse_ssh_key(line)
+                        .map_err(|e| anyhow::format_err!("Wrong key in {:?}: {e}", path))?
+                    {
+                        Some(key) => self.ssh.push(key),
+                        None => tracing::debug!("Skipping key for other namespaces: {line}"),
+                    }
+                }
+            }
+            _ => self.pgp.push(data),
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pgp.is_empty() && self.ssh.is_empty()
+    }
+
+    /// Checks an armored signature of `data` made by a trusted key, returns
+    /// the fingerprint of the key
+    pub fn verify(&self, signature: &[u8], data: &[u8]) -> anyhow::Result<String> {
+        if signature.starts_with(SSH_SIGNATURE_HEADER.as_bytes()) {
+            self.verify_ssh(signature, data)
+        } else if signature.starts_with(PGP_SIGNATURE_HEADER.as_bytes()) {
+            self.verify_pgp(signature, data)
+        } else {
+            anyhow::bail!("Unsupported signature format")
+        }
+    }
+
+    fn verify_ssh(&self, signature: &[u8], data: &[u8]) -> anyhow::Result<String> {
+        let signature = SshSig::from_pem(signature)
+            .map_err(|e| anyhow::format_err!("Wrong SSH signature: {e}"))?;
+        let key = self
+            .ssh
+            .iter()
+            .find(|key| key.key_data() == signature.public_key())
+            .ok_or(anyhow::format_err!("SSH signature is made by an untrusted key"))?;
+        key.verify(SSH_NAMESPACE, data, &signature)
+            .map_err(|e| anyhow::format_err!("Bad SSH signature: {e}"))?;
+        Ok(key.fingerprint(HashAlg::Sha256).to_string())
+    }
+
+    /// Runs `gpg` against a temporary keyring with the trusted keys only, so
+    /// the keys of the user keyring are never trusted implicitly
+    fn verify_pgp(&self, signature: &[u8], data: &[u8]) -> anyhow::Result<String> {
+        if self.pgp.is_empty() {
+            anyhow::bail!("OpenPGP signature but no trusted OpenPGP keys");
+        }
+        let home = tempfile::tempdir()?;
+        for key in &self.pgp {
+            gpg(home.path(), &["--import"], key)
+                .map_err(|e| anyhow::format_err!("Failed to import OpenPGP key: {e}"))?;
+        }
+        let signature_path = home.path().join("signature.asc");
+        std::fs::write(&signature_path, signature)?;
+        let signature_path = signature_path.to_string_lossy();
+        let status = gpg(
+            home.path(),
+            &["--status-fd=1", "--trust-model=always", "--verify", &signature_path, "-"],
+            data,
+        )
+        .map_err(|e| anyhow::format_err!("Bad OpenPGP signature: {}", gpg_error(&e.to_string())))?;
+        // signatures of revoked and expired keys are reported as valid too,
+        // with `REVKEYSIG` or `EXPKEYSIG` in place of `GOODSIG`
+        let statuses = status.lines().filter_map(|line| line.strip_prefix("[GNUPG:] "));
+        let mut good = false;
+        let mut fingerprint = None;
+        for line in statuses {
+            let (name, args) = line.split_once(' ').unwrap_or((line, ""));
+            match name {
+                "GOODSIG" => good = true,
+                "VALIDSIG" => fingerprint = args.split(' ').next(),
+                _ if REJECTED_STATUSES.contains(&name) => {
+                    anyhow::bail!("Bad OpenPGP signature: {line}")
+                }
+                _ => {}
+            }
+        }
+        match fingerprint {
+            Some(fingerprint) if good => Ok(fingerprint.to_string()),
+            _ => anyhow::bail!("Bad OpenPGP signature: {}", gpg_error(&status)),
+        }
+    }
+}
+
+/// SSH public key from a line that may start with the principals and options
+/// of `allowed_signers`, `None` when its `namespaces` option leaves out git
+fn parse_ssh_key(line: &str) -> anyhow::Result<Option<PublicKey>> {
+    let parts = line.split_whitespace().collect::<Vec<_>>();
+    let start = parts
+        .iter()
+        .position(|part| {
+            part.starts_with("ssh-") || part.starts_with("ecdsa-") || part.starts_with("sk-")
+        })
+        .ok_or(anyhow::format_err!("Unsupported key: {line}"))?;
+    let options = parts[..start].join(" ");
+    if let Some(namespaces) = option_value(&options, "namespaces") {
+        if !namespaces.split(',').any(|namespace| namespace.trim() == SSH_NAMESPACE) {
+            return Ok(None);
+        }
+    }
+    PublicKey::from_openssh(&parts[start..].join(" "))
+        .map(Some)
+        .map_err(|e| anyhow::format_err!("Wrong SSH key {line}: {e}"))
+}
+
+/// Value of `name="value"` in the options of an `allowed_signers` line, option
+/// names are case-insensitive
+fn option_value<'a>(options: &'a str, name: &str) -> Option<&'a str> {
+    let prefix = format!("{name}=\"");
+    let start = options.to_ascii_lowercase().find(&prefix)? + prefix.len();
+    options[start..].split('"').next()
+}
+
+/// Runs `gpg` with `input` on stdin, returns stdout or the error with stdout
+/// and stderr
+fn gpg(home: &Path, args: &[&str], input: &[u8]) -> anyhow::Result<String> {
+    let mut child = Command::new("gpg")
+        .arg("--homedir")
+        .arg(home)
+        .args(["--batch", "--no-autostart", "--no-tty"])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow::format_err!("Failed to run gpg: {e}"))?;
+    // `gpg` reads the whole input before it writes much, so it can't block
+    // on a full stdout pipe while the input is written
+    child.stdin.take().expect("stdin is piped").write_all(input)?;
+    let output = child.wait_with_output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    if !output.status.success() {
+        anyhow::bail!("{stdout}{}", String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(stdout)
+}
+
+/// Most telling part of the `gpg` output
+fn gpg_error(output: &str) -> String {
+    output
+        .lines()
+        .filter_map(|line| line.strip_prefix("[GNUPG:] "))
+        .find(|line| {
+            line.starts_with("NO_PUBKEY")
+                || REJECTED_STATUSES.iter().any(|status| line.starts_with(status))
+        })
+        .unwrap_or(output.trim())
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "ssh-ed25519 \
+                       AAAAC3NzaC1lZDI1NTE5AAAAIKS2ZbC6MPnXSCRsT14MHXz6qcBDlCgzJsMXW5DJItLN test";
+    const OTHER_KEY: &str =
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIMNJmgBnJ/ysAM8r2OvVgrUfBAPpNhy+bDxy9XKG2a1r";
+    /// `ssh-keygen -Y sign -n git` of "signed data\n" with `KEY`
+    const SIGNATURE: &str = "-----BEGIN SSH SIGNATURE-----
+U1NIU0lHAAAAAQAAADMAAAALc3NoLWVkMjU1MTkAAAAgpLZlsLow+ddIJGxPXgwdfPqpwE
+OUKDMmwxdbkMki0s0AAAADZ2l0AAAAAAAAAAZzaGE1MTIAAABTAAAAC3NzaC1lZDI1NTE5
+AAAAQLICXgb4lFfPnXMbpxAdICLgFrtzj0vZfbBMek1iE5/xrNThaaBczFuhz/m+sTt7No
+XwkKxsFiY/NFxU6bMuXAg=
+-----END SSH SIGNATURE-----
+";
+
+    #[test]
+    fn test_ssh_signature() {
+        let mut keys = TrustedKeys::default();
+        keys.add(&format!("user@example.com namespaces=\"file,git\" {KEY}")).unwrap();
+        let signer = keys.verify(SIGNATURE.as_bytes(), b"signed data\n").unwrap();
+        assert!(signer.starts_with("SHA256:"));
+        assert!(keys.verify(SIGNATURE.as_bytes(), b"other data\n").is_err());
+
+        let mut other = TrustedKeys::default();
+        other.add(OTHER_KEY).unwrap();
+        assert!(other.verify(SIGNATURE.as_bytes(), b"signed data\n").is_err());
+
+        let file_only = format!("user@example.com NAMESPACES=\"file\" {KEY}");
+        assert!(TrustedKeys::default().add(&file_only).is_err());
+        let dir = tempfile::tempdir().unwrap();
+        let allowed_signers = dir.path().join("allowed_signers");
+        std::fs::write(&allowed_signers, format!("{file_only}\n{OTHER_KEY}\n")).unwrap();
+        let mut keyring = TrustedKeys::default();
+        keyring.add_keyring(&allowed_signers).unwrap();
+        assert!(keyring.verify(SIGNATURE.as_bytes(), b"signed data\n").is_err());
+    }
+
+    /// OpenPGP key in its own gpg home, made with `gpg` at `time` if given
+    struct PgpKey {
+        home: tempfile::TempDir,
+        time: Option<&'static str>,
+    }
+
+    impl PgpKey {
+        fn new(time: Option<&'static str>, expire: &str) -> Self {
+            let key = Self { home: tempfile::tempdir().unwrap(), time };
+            key.gpg(
+                &["--passphrase", "", "--quick-gen-key", "Test <test@localhost>", "ed25519"]
+                    .into_iter()
+                    .chain(["sign", expire])
+                    .collect::<Vec<_>>(),
+                b"",
+            );
+            key
+        }
+
+        /// Unlike [`gpg`] it lets `gpg` start the agent the secret keys need
+        fn gpg(&self, args: &[&str], input: &[u8]) -> Vec<u8> {
+            let mut command = Command::new("gpg");
+            command.arg("--homedir").arg(self.home.path()).args(["--batch", "--no-tty"]);
+            if let Some(time) = self.time {
+                command.arg(format!("--faked-system-time={time}!"));
+            }
+            let mut child = command
+                .args(args)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .unwrap();
+            child.stdin.take().unwrap().write_all(input).unwrap();
+            let output = child.wait_with_output().unwrap();
+            assert!(output.status.success(), "gpg {args:?} failed");
+            output.stdout
+        }
+
+        fn sign(&self, data: &[u8]) -> Vec<u8> {
+            let args = ["--pinentry-mode", "loopback", "--passphrase", "", "--armor"];
+            self.gpg(&[&args[..], &["--detach-sign"]].concat(), data)
+        }
+
+        fn public_key(&self) -> String {
+            String::from_utf8(self.gpg(&["--armor", "--export"], b"")).unwrap()
+        }
+
+        /// Imports the revocation certificate gpg made along with the key
+        fn revoke(&self) {
+            let dir = self.home.path().join("openpgp-revocs.d");
+            let path = std::fs::read_dir(dir).unwrap().next().unwrap().unwrap().path();
+            // the header is escaped against an accidental import
+            let certificate = std::fs::read_to_string(path).unwrap().replace(":-----", "-----");
+            self.gpg(&["--import"], certificate.as_bytes());
+        }
+    }
+
+    impl Drop for PgpKey {
+        fn drop(&mut self) {
+            let _ = Command::new("gpgconf")
+                .arg("--homedir")
+                .arg(self.home.path())
+                .args(["--kill", "gpg-agent"])
+                .status();
+        }
+    }
+
+    fn trusted(key: &PgpKey) -> TrustedKeys {
+        let mut keys = TrustedKeys::default();
+        keys.add(&key.public_key()).unwrap();
+        keys
+    }
+
+    #[test]
+    fn test_pgp_signature() {
+        let data = b"signed data\n";
+        let key = PgpKey::new(None, "never");
+        let signature = key.sign(data);
+        let fingerprint = trusted(&key).verify(&signature, data).unwrap();
+        assert_eq!(40, fingerprint.len());
+        let err = trusted(&key).verify(&signature, b"other data\n").unwrap_err();
+        assert!(err.to_string().contains("BADSIG"), "{err}");
+        let other = PgpKey::new(None, "never");
+        let err = trusted(&other).verify(&signature, data).unwrap_err();
+        assert!(err.to_string().contains("ERRSIG"), "{err}");
+
+        key.revoke();
+        let err = trusted(&key).verify(&signature, data).unwrap_err();
+        assert!(err.to_string().contains("REVKEYSIG"), "{err}");
+
+        let expired = PgpKey::new(Some("20200101T000000"), "1d");
+        let signature = expired.sign(data);
+        let err = trusted(&expired).verify(&signature, data).unwrap_err();
+        assert!(err.to_string().contains("EXPKEYSIG"), "{err}");
+    }
+}
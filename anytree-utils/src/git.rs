@@ -0,0 +1,1175 @@
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+
+use git2::build::CheckoutBuilder;
+use git2::{
+    FetchOptions, ObjectType, Oid, ProxyOptions, Repository, Signature, Time, TreeWalkMode,
+};
+
+use crate::credentials::Credentials;
+use crate::rewrite::UrlRewrites;
+
+/// Refspecs `git clone --bare` fetches
+const BARE_REFSPECS: [&str; 2] = ["+refs/heads/*:refs/heads/*", "+refs/tags/*:refs/tags/*"];
+/// Refspecs of a regular clone
+const CLONE_REFSPECS: [&str; 2] =
+    ["+refs/heads/*:refs/remotes/origin/*", "+refs/tags/*:refs/tags/*"];
+const ORIGIN: &str = "origin";
+/// Commits of a shallow repo whose parents were not fetched
+const SHALLOW_FILE_NAME: &str = "shallow";
+
+/// Author of the commits made by anytree itself
+const AUTHOR_NAME: &str = "anytree";
+const AUTHOR_EMAIL: &str = "anytree@localhost";
+/// Headers of the signatures git appends to tag objects
+const SIGNATURE_HEADERS: [&[u8]; 2] =
+    [b"-----BEGIN PGP SIGNATURE-----", b"-----BEGIN SSH SIGNATURE-----"];
+/// URL schemes libgit2 has transports for. The other ones, e.g. `gosh://`, are
+/// fetched with the git CLI, which hands them to `git-remote-<scheme>`.
+const LIBGIT2_SCHEMES: [&str; 7] = ["http", "https", "ssh", "ssh+git", "git+ssh", "git", "file"];
+
+/// Fetches `commit` from `url` into a new bare repo at `dest`, see
+/// [`fetch_commit`]
+pub fn clone_bare(
+    url: &str,
+    commit: &str,
+    dest: &Path,
+    credentials: &Credentials,
+) -> anyhow::Result<()> {
+    tracing::trace!("Cloning the bare repo. url: {}", url);
+    let repo = Repository::init_bare(dest)
+        .map_err(|e| anyhow::format_err!("Failed to init bare repo {:?}: {e}", dest))?;
+    fetch_commit(&repo, url, commit, &BARE_REFSPECS, credentials)?;
+    Ok(())
+}
+
+/// Fetches the branches and the tags of `url` with their whole history into a
+/// new bare repo at `dest`, and `commit` by SHA if none of them has it, then
+/// points `branch` at `commit`. Unlike a shallow clone it can be pushed to
+/// another remote, see [`push`].
+pub fn clone_history(
+    url: &str,
+    commit: &str,
+    branch: &str,
+    dest: &Path,
+    credentials: &Credentials,
+) -> anyhow::Result<()> {
+    tracing::trace!("Cloning the history of {}", url);
+    let repo = Repository::init_bare(dest)
+        .map_err(|e| anyhow::format_err!("Failed to init bare repo {:?}: {e}", dest))?;
+    fetch(&repo, url, &BARE_REFSPECS, None, credentials)
+        .map_err(|e| anyhow::format_err!("Failed to fetch {url}: {e}"))?;
+    if find_commit(&repo, commit, url).is_err() {
+        fetch(&repo, url, &[commit], None, credentials)
+            .map_err(|e| anyhow::format_err!("Failed to fetch {commit} from {url}: {e}"))?;
+    }
+    let id = find_commit(&repo, commit, url)?;
+    repo.reference(&format!("refs/heads/{branch}"), id, true, "anytree")?;
+    Ok(())
+}
+
+/// Creates a bare repo at `dest` unless there is a repo already
+pub fn init_bare(dest: &Path) -> anyhow::Result<()> {
+    if Repository::open_bare(dest).is_err() {
+        Repository::init_bare(dest)
+            .map_err(|e| anyhow::format_err!("Failed to init bare repo {:?}: {e}", dest))?;
+    }
+    Ok(())
+}
+
+/// Pushes `refspecs` of `repo` to `url`. Fails when the remote rejects any of
+/// them, e.g. a non-fast-forward update.
+pub fn push(
+    repo: &Path,
+    url: &str,
+    refspecs: &[&str],
+    credentials: &Credentials,
+) -> anyhow::Result<()> {
+    tracing::trace!("Pushing {:?} to {}", refspecs, url);
+    let repo = Repository::open(repo)
+        .map_err(|e| anyhow::format_err!("Failed to open repo {:?}: {e}", repo))?;
+    if uses_remote_helper(url) {
+        git_cli(Some(repo.path()), &[&["push", url], refspecs].concat(), credentials)?;
+        return Ok(());
+    }
+    let rejected = std::cell::RefCell::new(vec![]);
+    let mut callbacks = credentials.callbacks();
+    callbacks.push_update_reference(|reference, status| {
+        if let Some(status) = status {
+            rejected.borrow_mut().push(format!("{reference}: {status}"));
+        }
+        Ok(())
+    });
+    let mut options = git2::PushOptions::new();
+    options.proxy_options(proxy_options());
+    options.remote_callbacks(callbacks);
+    repo.remote_anonymous(url)?
+        .push(refspecs, Some(&mut options))
+        .map_err(|e| anyhow::format_err!("Failed to push to {url}: {e}"))?;
+    let rejected = rejected.borrow();
+    if !rejected.is_empty() {
+        anyhow::bail!("{url} rejected {}", rejected.join(", "));
+    }
+    Ok(())
+}
+
+/// Content of the file at `path` in `commit` of `url`, fetched into a
+/// temporary repo
+pub fn read_file(
+    url: &str,
+    commit: &str,
+    path: &str,
+    credentials: &Credentials,
+) -> anyhow::Result<Vec<u8>> {
+    let dir = tempfile::tempdir()?;
+    let repo = Repository::init_bare(dir.path())?;
+    let id = fetch_commit(&repo, url, commit, &BARE_REFSPECS, credentials)?;
+    let entry = repo
+        .find_commit(id)?
+        .tree()?
+        .get_path(Path::new(path))
+        .map_err(|_| anyhow::format_err!("File {path} not found in {commit} of {url}"))?;
+    let blob = entry.to_object(&repo)?.peel_to_blob()?;
+    Ok(blob.content().to_vec())
+}
+
+/// Fetches `commit` from `url` to `dest`, see [`fetch_commit`], and checks it
+/// out with a detached HEAD, like `git clone` followed by `git checkout -f`.
+/// Submodules are checked out at the commits recorded in `commit`, from their
+/// `.gitmodules` URLs passed through `rewrites`.
+pub fn checkout(
+    url: &str,
+    commit: &str,
+    dest: &Path,
+    credentials: &Credentials,
+    rewrites: &UrlRewrites,
+) -> anyhow::Result<()> {
+    update_submodules(&checkout_commit(url, commit, dest, credentials)?, credentials, rewrites)
+}
+
+/// Like [`checkout`], but every submodule, nested ones included, is fetched
+/// like the commit itself from the URL `submodule_url` gives for its path
+/// relative to `dest` and its recorded commit, e.g. from SBOM, instead of the
+/// URL from `.gitmodules`. The submodules are checked out as standalone repos.
+pub fn checkout_with_submodules(
+    url: &str,
+    commit: &str,
+    dest: &Path,
+    credentials: &Credentials,
+    submodule_url: &mut dyn FnMut(&str, &str) -> anyhow::Result<String>,
+) -> anyhow::Result<()> {
+    let repo = checkout_commit(url, commit, dest, credentials)?;
+    checkout_submodules(&repo, "", credentials, submodule_url)
+}
+
+fn checkout_commit(
+    url: &str,
+    commit: &str,
+    dest: &Path,
+    credentials: &Credentials,
+) -> anyhow::Result<Repository> {
+    tracing::trace!("Checking out {}#{} to {:?}", url, commit, dest);
+    let repo = Repository::init(dest)
+        .map_err(|e| anyhow::format_err!("Failed to init repo {:?}: {e}", dest))?;
+    repo.remote(ORIGIN, url)?;
+    let id = fetch_commit(&repo, url, commit, &CLONE_REFSPECS, credentials)?;
+    {
+        let target = repo.find_object(id, Some(ObjectType::Commit))?;
+        repo.checkout_tree(&target, Some(CheckoutBuilder::new().force()))
+            .map_err(|e| anyhow::format_err!("Failed to check out {commit} in {:?}: {e}", dest))?;
+    }
+    repo.set_head_detached(id)?;
+    Ok(repo)
+}
+
+fn checkout_submodules(
+    repo: &Repository,
+    prefix: &str,
+    credentials: &Credentials,
+    submodule_url: &mut dyn FnMut(&str, &str) -> anyhow::Result<String>,
+) -> anyhow::Result<()> {
+    let workdir = repo.workdir().ok_or(anyhow::format_err!("Checkout has no workdir"))?;
+    for (path, commit) in submodules(repo)? {
+        let full_path = format!("{prefix}{path}");
+        let url = submodule_url(&full_path, &commit)?;
+        tracing::trace!("Checking out submodule {} from {}", full_path, url);
+        let submodule = checkout_commit(&url, &commit, &workdir.join(&path), credentials)
+            .map_err(|e| anyhow::format_err!("Failed to check out submodule {full_path}: {e}"))?;
+        checkout_submodules(&submodule, &format!("{full_path}/"), credentials, submodule_url)?;
+    }
+    Ok(())
+}
+
+/// Paths and commits of the submodules recorded in the HEAD commit of `repo`
+fn submodules(repo: &Repository) -> anyhow::Result<Vec<(String, String)>> {
+    let mut res = vec![];
+    repo.head()?.peel_to_tree()?.walk(TreeWalkMode::PreOrder, |root, entry| {
+        if entry.kind() == Some(ObjectType::Commit) {
+            let path = format!("{root}{}", String::from_utf8_lossy(entry.name_bytes()));
+            res.push((path, entry.id().to_string()));
+        }
+        git2::TreeWalkResult::Ok
+    })?;
+    Ok(res)
+}
+
+fn update_submodules(
+    repo: &Repository,
+    credentials: &Credentials,
+    rewrites: &UrlRewrites,
+) -> anyhow::Result<()> {
+    for mut submodule in repo.submodules()? {
+        let name = submodule.name().unwrap_or_default().to_string();
+        tracing::trace!("Updating submodule {}", name);
+        let update = |submodule: &mut git2::Submodule| -> anyhow::Result<Repository> {
+            // init puts the URL, relative ones resolved, into the repo config
+            submodule.init(false)?;
+            let key = format!("submodule.{name}.url");
+            let url = repo.config()?.snapshot()?.get_string(&key)?;
+            let url = rewrites.rewrite(&url);
+            let commit =
+                submodule.head_id().ok_or(anyhow::format_err!("No commit recorded in HEAD"))?;
+            let subrepo = submodule.repo_init(true)?;
+            let id =
+                fetch_commit(&subrepo, &url, &commit.to_string(), &CLONE_REFSPECS, credentials)?;
+            {
+                let target = subrepo.find_object(id, Some(ObjectType::Commit))?;
+                subrepo.checkout_tree(&target, Some(CheckoutBuilder::new().force()))?;
+            }
+            subrepo.set_head_detached(id)?;
+            Ok(subrepo)
+        };
+        let subrepo = update(&mut submodule)
+            .map_err(|e| anyhow::format_err!("Failed to update submodule {name}: {e}"))?;
+        update_submodules(&subrepo, credentials, rewrites)?;
+    }
+    Ok(())
+}
+
+/// Commits everything in `dir` but the `exclude` patterns on top of its HEAD,
+/// creating the repo if needed, and points `reference` at the commit. The
+/// author and the date are fixed, so the same content gives the same root
+/// commit. Returns the commit.
+pub fn commit_all(
+    dir: &Path,
+    message: &str,
+    exclude: &[&str],
+    reference: &str,
+) -> anyhow::Result<String> {
+    let repo = Repository::init(dir)
+        .map_err(|e| anyhow::format_err!("Failed to init repo {:?}: {e}", dir))?;
+    let info_path = repo.path().join("info");
+    std::fs::create_dir_all(&info_path)?;
+    let exclude = exclude.iter().map(|pattern| format!("{pattern}\n")).collect::<String>();
+    std::fs::write(info_path.join("exclude"), exclude)?;
+    let mut index = repo.index()?;
+    index.add_all(["*"], git2::IndexAddOption::DEFAULT, None)?;
+    index.write()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let signature = Signature::new(AUTHOR_NAME, AUTHOR_EMAIL, &Time::new(0, 0))?;
+    let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+    let parents = parent.iter().collect::<Vec<_>>();
+    let id = repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &parents)?;
+    repo.reference(reference, id, true, message)?;
+    Ok(id.to_string())
+}
+
+/// Commit `reference` of `url` points to, e.g. for `refs/tags/v1.0`, with
+/// annotated tags peeled. `None` when the remote has no such reference.
+pub fn ls_remote(
+    url: &str,
+    reference: &str,
+    credentials: &Credentials,
+) -> anyhow::Result<Option<String>> {
+    let heads = if uses_remote_helper(url) {
+        git_cli(None, &["ls-remote", url], credentials)?
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .map(|(id, name)| (name.to_string(), id.to_string()))
+            .collect::<Vec<_>>()
+    } else {
+        let mut remote = git2::Remote::create_detached(url)?;
+        remote
+            .connect_auth(
+                git2::Direction::Fetch,
+                Some(credentials.callbacks()),
+                Some(proxy_options()),
+            )
+            .map_err(|e| anyhow::format_err!("Failed to connect to {url}: {e}"))?;
+        let heads = remote.list()?;
+        heads.iter().map(|head| (head.name().to_string(), head.oid().to_string())).collect()
+    };
+    let peeled = format!("{reference}^{{}}");
+    let target = heads
+        .iter()
+        .find(|(name, _)| *name == peeled)
+        .or_else(|| heads.iter().find(|(name, _)| name == reference))
+        .map(|(_, id)| id.clone());
+    Ok(target)
+}
+
+/// Tells whether `commit` is in the history of `branch` of `url`. The tip
+/// the remote advertises settles it when the branch still points at the
+/// commit. Otherwise the branch history is fetched into the bare repo
+/// `history`, which must not be a clone of the sources, as it gets the whole
+/// history. The next checks fetch only the commits it does not have yet.
+pub fn branch_contains(
+    history: &Path,
+    url: &str,
+    branch: &str,
+    commit: &str,
+    credentials: &Credentials,
+) -> anyhow::Result<bool> {
+    let reference = format!("refs/heads/{branch}");
+    let tip = ls_remote(url, &reference, credentials)?
+        .ok_or(anyhow::format_err!("Branch {branch} not found in {url}"))?;
+    if !commit.is_empty() && tip.starts_with(commit) {
+        return Ok(true);
+    }
+    init_bare(history)?;
+    let repo = Repository::open_bare(history)
+        .map_err(|e| anyhow::format_err!("Failed to open repo {:?}: {e}", history))?;
+    let tip = Oid::from_str(&tip)?;
+    if repo.find_commit(tip).is_err() {
+        fetch(&repo, url, &[&format!("+{reference}:{reference}")], None, credentials)
+            .map_err(|e| anyhow::format_err!("Failed to fetch {branch} from {url}: {e}"))?;
+    }
+    // the whole branch is fetched, a commit it lacks is not in the branch
+    let Ok(id) = find_commit(&repo, commit, url) else {
+        return Ok(false);
+    };
+    Ok(id == tip || repo.graph_descendant_of(tip, id)?)
+}
+
+/// Signature and signed data of `commit` in `repo`, `None` for an unsigned
+/// commit
+pub fn commit_signature(repo: &Path, commit: &str) -> anyhow::Result<Option<(Vec<u8>, Vec<u8>)>> {
+    let repo = Repository::open(repo)
+        .map_err(|e| anyhow::format_err!("Failed to open repo {:?}: {e}", repo))?;
+    let id = find_commit(&repo, commit, &repo.path().to_string_lossy())?;
+    // NotFound of a commit that is there means it has no signature
+    match repo.extract_signature(&id, None) {
+        Ok((signature, data)) => Ok(Some((signature.to_vec(), data.to_vec()))),
+        Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(anyhow::format_err!("Failed to read signature of {commit}: {e}")),
+    }
+}
+
+/// Fetches `tag` of `url` into `repo` and returns its signature and signed
+/// data, `None` for a lightweight or unsigned tag. Fails when the tag does not
+/// point to `commit`, as its signature would not vouch for it.
+pub fn tag_signature(
+    repo: &Path,
+    url: &str,
+    tag: &str,
+    commit: &str,
+    credentials: &Credentials,
+) -> anyhow::Result<Option<(Vec<u8>, Vec<u8>)>> {
+    let repo = Repository::open(repo)
+        .map_err(|e| anyhow::format_err!("Failed to open repo {:?}: {e}", repo))?;
+    let id = fetch_reference(&repo, url, &format!("refs/tags/{tag}"), credentials)
+        .map_err(|e| anyhow::format_err!("Failed to fetch tag {tag} from {url}: {e}"))?
+        .ok_or(anyhow::format_err!("Tag {tag} not found in {url}"))?;
+    let Ok(object) = repo.find_tag(id) else {
+        return Ok(None);
+    };
+    if object.target_id() != find_commit(&repo, commit, url)? {
+        anyhow::bail!("Tag {tag} of {url} does not point to the pinned commit {commit}");
+    }
+    let raw = repo.odb()?.read(id)?.data().to_vec();
+    // Same as git's `parse_signed_buffer`: the signature starts at the last
+    // line with a signature header
+    let start = (0..raw.len())
+        .rev()
+        .filter(|i| *i == 0 || raw[i - 1] == b'\n')
+        .find(|i| SIGNATURE_HEADERS.iter().any(|header| raw[*i..].starts_with(header)));
+    Ok(start.map(|start| (raw[start..].to_vec(), raw[..start].to_vec())))
+}
+
+/// Fetches just `commit` and the objects of its tree from `url`, without the
+/// history and the other branches. Falls back to fetching `refspecs` when the
+/// server does not allow fetching by SHA or `commit` is not a full SHA.
+fn fetch_commit(
+    repo: &Repository,
+    url: &str,
+    commit: &str,
+    refspecs: &[&str],
+    credentials: &Credentials,
+) -> anyhow::Result<Oid> {
+    if is_local(url) {
+        return copy_commit(repo, url, commit);
+    }
+    if Oid::from_str(commit).map_or(false, |id| id.to_string() == commit) {
+        match fetch(repo, url, &[commit], Some(1), credentials) {
+            Ok(()) => return find_commit(repo, commit, url),
+            Err(e) => tracing::debug!("Failed to fetch {commit} from {url} by SHA: {e}"),
+        }
+    }
+    tracing::trace!("Fetching all refs of {}", url);
+    fetch(repo, url, refspecs, None, credentials)
+        .map_err(|e| anyhow::format_err!("Failed to fetch {url}: {e}"))?;
+    find_commit(repo, commit, url)
+}
+
+/// Fetches `refspecs` of `url` into `repo`, with the git CLI for the URLs of
+/// the remote helpers
+fn fetch(
+    repo: &Repository,
+    url: &str,
+    refspecs: &[&str],
+    depth: Option<i32>,
+    credentials: &Credentials,
+) -> anyhow::Result<()> {
+    if uses_remote_helper(url) {
+        let depth = depth.map(|depth| format!("--depth={depth}"));
+        let mut args = vec!["fetch", "--no-tags"];
+        args.extend(depth.as_deref());
+        args.push(url);
+        args.extend(refspecs);
+        git_cli(Some(repo.path()), &args, credentials)?;
+        return Ok(());
+    }
+    let mut options = fetch_options(credentials);
+    if let Some(depth) = depth {
+        options.depth(depth);
+    }
+    repo.remote_anonymous(url)?.fetch(refspecs, Some(&mut options), None)?;
+    Ok(())
+}
+
+/// Fetches `reference` of `url` into `repo` and returns the object it points
+/// to, not peeled, `None` when the remote has no such reference
+fn fetch_reference(
+    repo: &Repository,
+    url: &str,
+    reference: &str,
+    credentials: &Credentials,
+) -> anyhow::Result<Option<Oid>> {
+    if uses_remote_helper(url) {
+        if ls_remote(url, reference, credentials)?.is_none() {
+            return Ok(None);
+        }
+        fetch(repo, url, &[reference], None, credentials)?;
+        // the first line of FETCH_HEAD is the only fetched reference
+        let fetch_head = std::fs::read_to_string(repo.path().join("FETCH_HEAD"))?;
+        let id = fetch_head.split(|c: char| c.is_whitespace()).next().unwrap_or_default();
+        return Ok(Some(Oid::from_str(id)?));
+    }
+    let mut remote = repo.remote_anonymous(url)?;
+    remote.fetch(&[reference], Some(&mut fetch_options(credentials)), None)?;
+    let id = remote.list()?.iter().find(|head| head.name() == reference).map(|head| head.oid());
+    Ok(id)
+}
+
+/// Url has a scheme libgit2 has no transport for, or names a remote helper
+/// explicitly as `<transport>::<address>`
+fn uses_remote_helper(url: &str) -> bool {
+    let is_transport = |name: &str| {
+        !name.is_empty()
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    };
+    match (url.split_once("::"), url.split_once("://")) {
+        (Some((transport, _)), _) if is_transport(transport) => true,
+        (_, Some((scheme, _))) => !LIBGIT2_SCHEMES.contains(&scheme),
+        _ => false,
+    }
+}
+
+/// Runs git in `repo` with the env of `credentials`, for the remote helpers.
+/// Credentials are left to the helpers and the credential helpers of git, it
+/// never prompts for them.
+fn git_cli(
+    repo: Option<&Path>,
+    args: &[&str],
+    credentials: &Credentials,
+) -> anyhow::Result<String> {
+    Ok(String::from_utf8_lossy(&git_cli_output(repo, args, credentials)?).to_string())
+}
+
+fn git_cli_output(
+    repo: Option<&Path>,
+    args: &[&str],
+    credentials: &Credentials,
+) -> anyhow::Result<Vec<u8>> {
+    let mut command = Command::new("git");
+    if let Some(repo) = repo {
+        command.arg("--git-dir").arg(repo);
+    }
+    let output = command
+        .args(args)
+        .envs(credentials.env())
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+        .map_err(|e| anyhow::format_err!("Failed to run git: {e}"))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(output.stdout)
+}
+
+/// Url is served by the local transport of libgit2
+fn is_local(url: &str) -> bool {
+    url.starts_with("file://") || Path::new(url).exists()
+}
+
+/// Local transport of libgit2 copies all the objects of the repo even when
+/// fetching by SHA, so the objects of `commit` are packed right from it and
+/// the commit is marked shallow
+fn copy_commit(repo: &Repository, url: &str, commit: &str) -> anyhow::Result<Oid> {
+    let source = Repository::open(url.strip_prefix("file://").unwrap_or(url))
+        .map_err(|e| anyhow::format_err!("Failed to open {url}: {e}"))?;
+    let id = find_commit(&source, commit, url)?;
+    let mut builder = source.packbuilder()?;
+    builder.insert_commit(id)?;
+    let mut pack = git2::Buf::new();
+    builder.write_buf(&mut pack)?;
+    let odb = repo.odb()?;
+    let mut writer = odb.packwriter()?;
+    writer.write_all(&pack)?;
+    writer.commit()?;
+
+    // parents of the commit are missing from a shallow source as well
+    if source.is_shallow() || source.find_commit(id)?.parent_count() > 0 {
+        let shallow_path = repo.path().join(SHALLOW_FILE_NAME);
+        let mut shallow = std::fs::read_to_string(&shallow_path).unwrap_or_default();
+        if !shallow.lines().any(|line| line == id.to_string()) {
+            shallow.push_str(&format!("{id}\n"));
+            std::fs::write(shallow_path, shallow)?;
+        }
+    }
+    Ok(id)
+}
+
+/// Resolves `commit` in the repo, failing when it was not fetched from `url`
+fn find_commit(repo: &Repository, commit: &str, url: &str) -> anyhow::Result<Oid> {
+    repo.revparse_single(commit)
+        .and_then(|object| object.peel_to_commit())
+        .map(|commit| commit.id())
+        .map_err(|_| anyhow::format_err!("Commit {commit} not found in {url}"))
+}
+
+fn fetch_options(credentials: &Credentials) -> FetchOptions<'_> {
+    let mut options = FetchOptions::new();
+    options.proxy_options(proxy_options());
+    options.remote_callbacks(credentials.callbacks());
+    options
+}
+
+fn proxy_options<'a>() -> ProxyOptions<'a> {
+    let mut proxy = ProxyOptions::new();
+    proxy.auto();
+    proxy
+}
+
+/// Tag from SBOM is a valid ref name, so it can't point outside `refs/tags`,
+/// e.g. with `../`
+pub fn check_tag_name(tag: &str) -> anyhow::Result<()> {
+    if !git2::Reference::is_valid_name(&format!("refs/tags/{tag}")) {
+        anyhow::bail!("Invalid tag name {tag:?}");
+    }
+    Ok(())
+}
+
+/// Full ID of `commit`, which may be abbreviated or a ref, in the repo
+pub fn resolve_commit(repo: &Path, commit: &str) -> anyhow::Result<String> {
+    let git_repo = Repository::open(repo)
+        .map_err(|e| anyhow::format_err!("Failed to open repo {:?}: {e}", repo))?;
+    Ok(find_commit(&git_repo, commit, &repo.to_string_lossy())?.to_string())
+}
+
+/// Bytes of `git archive --format=tar <commit>`, the legacy SBOM hashes of git
+/// sources are computed over them. The format is up to the git CLI, SBOMs
+/// with tree hashes are checked without it, see
+/// [`check_source_hashes`](crate::crypto::hash::check_source_hashes).
+pub fn archive(repo: &Path, commit: &str) -> anyhow::Result<Vec<u8>> {
+    let git_repo = Repository::open(repo)
+        .map_err(|e| anyhow::format_err!("Failed to open repo {:?}: {e}", repo))?;
+    let id = find_commit(&git_repo, commit, &repo.to_string_lossy())?.to_string();
+    git_cli_output(
+        Some(git_repo.path()),
+        &["archive", "--format=tar", &id],
+        &Credentials::default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read};
+    use std::net::TcpListener;
+    use std::process::Stdio;
+
+    use sha2::{Digest, Sha256};
+
+    use super::*;
+    use crate::credentials::HostCredentials;
+    use crate::rewrite::RewriteRule;
+
+    /// `Authorization` header of `user:secret`
+    const AUTHORIZATION: &str = "Basic dXNlcjpzZWNyZXQ=";
+
+    /// Repo with a single commit made at a fixed time, so its archive is
+    /// always the same
+    fn repo(dir: &Path) -> String {
+        let repo = Repository::init(dir).unwrap();
+        let long_dir = "a".repeat(60);
+        let files = [
+            ("Cargo.toml", "[package]", 0o100644),
+            ("src/lib.rs", "fn main() {}", 0o100644),
+            // git sorts it before `src/`, NAR after `src`
+            ("src.rs", "", 0o100644),
+            ("run.sh", "#!/bin/sh", 0o100755),
+            (&format!("{long_dir}/{long_dir}/{long_dir}.rs") as &str, "", 0o100644),
+            (&format!("{}.rs", "b".repeat(120)), "", 0o100644),
+        ];
+        let mut index = repo.index().unwrap();
+        for (path, content, mode) in files {
+            let blob = repo.blob(content.as_bytes()).unwrap();
+            let mut entry = index_entry(path, blob, mode);
+            entry.file_size = content.len() as u32;
+            index.add(&entry).unwrap();
+        }
+        let link = repo.blob(b"src/lib.rs").unwrap();
+        index.add(&index_entry("link.rs", link, 0o120000)).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature =
+            Signature::new("test", "test@localhost", &Time::new(1700000000, 0)).unwrap();
+        let id = repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[]).unwrap();
+        id.to_string()
+    }
+
+    /// Serves the repos in `root` over smart HTTP with `git http-backend`,
+    /// only to the requests with [`AUTHORIZATION`]
+    fn serve_git(root: &Path) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let root = root.to_path_buf();
+        std::thread::Builder::new()
+            .name("test-git-server".to_string())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    let mut stream = stream.unwrap();
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut request = String::new();
+                    reader.read_line(&mut request).unwrap();
+                    let mut headers = vec![];
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        match line.trim_end().split_once(": ") {
+                            Some((name, value)) => {
+                                headers.push((name.to_lowercase(), value.to_string()))
+                            }
+                            None => break,
+                        }
+                    }
+                    let header = |name: &str| {
+                        headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.clone())
+                    };
+                    if header("authorization").as_deref() != Some(AUTHORIZATION) {
+                        let response = "HTTP/1.1 401 Unauthorized\r\nWWW-Authenticate: Basic \
+                                        realm=\"git\"\r\nContent-Length: 0\r\nConnection: \
+                                        close\r\n\r\n";
+                        stream.write_all(response.as_bytes()).unwrap();
+                        continue;
+                    }
+                    let mut body = vec![];
+                    if let Some(length) = header("content-length") {
+                        body.resize(length.parse().unwrap(), 0);
+                        reader.read_exact(&mut body).unwrap();
+                    } else if header("transfer-encoding").as_deref() == Some("chunked") {
+                        loop {
+                            let mut size = String::new();
+                            reader.read_line(&mut size).unwrap();
+                            let size = usize::from_str_radix(size.trim(), 16).unwrap();
+                            let mut chunk = vec![0; size + 2];
+                            reader.read_exact(&mut chunk).unwrap();
+                            body.extend_from_slice(&chunk[..size]);
+                            if size == 0 {
+                                break;
+                            }
+                        }
+                    }
+                    let mut parts = request.split(' ');
+                    let method = parts.next().unwrap();
+                    let target = parts.next().unwrap();
+                    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+                    let mut backend = Command::new("git")
+                        .arg("http-backend")
+                        .env("GIT_PROJECT_ROOT", &root)
+                        .env("GIT_HTTP_EXPORT_ALL", "1")
+                        .env("REQUEST_METHOD", method)
+                        .env("PATH_INFO", path)
+                        .env("QUERY_STRING", query)
+                        .env("CONTENT_TYPE", header("content-type").unwrap_or_default())
+                        .env(
+                            "HTTP_CONTENT_ENCODING",
+                            header("content-encoding").unwrap_or_default(),
+                        )
+                        .env("GIT_PROTOCOL", header("git-protocol").unwrap_or_default())
+                        .env("REMOTE_USER", "user")
+                        .stdin(Stdio::piped())
+                        .stdout(Stdio::piped())
+                        .spawn()
+                        .unwrap();
+                    backend.stdin.take().unwrap().write_all(&body).unwrap();
+                    let output = backend.wait_with_output().unwrap().stdout;
+                    let split = output.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+                    let cgi_headers = String::from_utf8_lossy(&output[..split]).to_string();
+                    let status = cgi_headers
+                        .lines()
+                        .find_map(|line| line.strip_prefix("Status: "))
+                        .unwrap_or("200 OK");
+                    let body = &output[split + 4..];
+                    let head = format!(
+                        "HTTP/1.1 {status}\r\n{cgi_headers}\r\nContent-Length: {}\r\n\
+                         Connection: close\r\n\r\n",
+                        body.len()
+                    );
+                    stream.write_all(head.as_bytes()).unwrap();
+                    stream.write_all(body).unwrap();
+                }
+            })
+            .unwrap();
+        format!("http://{addr}")
+    }
+
+    fn index_entry(path: &str, id: Oid, mode: u32) -> git2::IndexEntry {
+        git2::IndexEntry {
+            ctime: git2::IndexTime::new(0, 0),
+            mtime: git2::IndexTime::new(0, 0),
+            dev: 0,
+            ino: 0,
+            mode,
+            uid: 0,
+            gid: 0,
+            file_size: 0,
+            id,
+            flags: 0,
+            flags_extended: 0,
+            path: path.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_clone_and_checkout_local_repo() {
+        let dir = tempfile::tempdir().unwrap();
+        let origin = dir.path().join("origin");
+        let commit = repo(&origin);
+        commit_all(&origin, "next", &[], "refs/heads/next").unwrap();
+        let next = Repository::open(&origin).unwrap().head().unwrap().target().unwrap();
+        let url = format!("file://{}", origin.display());
+
+        let bare = dir.path().join("bare");
+        clone_bare(&url, &commit, &bare, &Credentials::default()).unwrap();
+        // only the pinned commit is fetched, not the branches
+        assert!(Repository::open(&bare).unwrap().find_commit(next).is_err());
+        let missing = "0".repeat(40);
+        let err = clone_bare(&url, &missing, &dir.path().join("missing"), &Credentials::default())
+            .unwrap_err();
+        assert_eq!(format!("Commit {missing} not found in {url}"), err.to_string());
+
+        let checkout_dir = dir.path().join("checkout");
+        checkout(
+            &format!("file://{}", bare.display()),
+            &commit,
+            &checkout_dir,
+            &Credentials::default(),
+            &UrlRewrites::default(),
+        )
+        .unwrap();
+        let lib = std::fs::read_to_string(checkout_dir.join("src/lib.rs")).unwrap();
+        assert_eq!("fn main() {}", lib);
+        assert_eq!("fn main() {}", std::fs::read_to_string(checkout_dir.join("link.rs")).unwrap());
+    }
+
+    #[test]
+    fn test_shallow_fetch_over_http() {
+        let dir = tempfile::tempdir().unwrap();
+        let origin = dir.path().join("origin");
+        let root = Oid::from_str(&repo(&origin)).unwrap();
+        std::fs::write(origin.join("pinned.txt"), "pinned").unwrap();
+        let pinned = commit_all(&origin, "pinned", &[], "refs/heads/main").unwrap();
+        let tip =
+            Oid::from_str(&commit_all(&origin, "tip", &[], "refs/heads/main").unwrap()).unwrap();
+        let url = format!("{}/origin", serve_git(dir.path()));
+        let netrc = dir.path().join("netrc");
+        std::fs::write(&netrc, "machine 127.0.0.1 login user password secret").unwrap();
+        let credentials = Credentials::new(vec![HostCredentials {
+            host: "127.0.0.1".to_string(),
+            netrc: Some(netrc),
+            ..Default::default()
+        }]);
+
+        let fetched = |name: &str, commit: &str| {
+            let dest = dir.path().join(name);
+            clone_bare(&url, commit, &dest, &credentials).unwrap();
+            let repo = Repository::open(&dest).unwrap();
+            let has = |id| repo.find_commit(id).is_ok();
+            (has(root), has(tip), repo.is_shallow())
+        };
+
+        // the server does not let fetch by SHA, so the refs are fetched
+        assert_eq!((true, true, false), fetched("refs", &pinned));
+        Repository::open(&origin)
+            .unwrap()
+            .config()
+            .unwrap()
+            .set_bool("uploadpack.allowReachableSHA1InWant", true)
+            .unwrap();
+        // just the pinned commit, its parent is cut off
+        assert_eq!((false, false, true), fetched("shallow", &pinned));
+        // an abbreviated commit can only be found among the refs
+        assert_eq!((true, true, false), fetched("short", &pinned[..10]));
+
+        let checkout_dir = dir.path().join("checkout");
+        checkout(&url, &pinned, &checkout_dir, &credentials, &UrlRewrites::default()).unwrap();
+        assert!(Repository::open(&checkout_dir).unwrap().is_shallow());
+        let content = std::fs::read_to_string(checkout_dir.join("pinned.txt")).unwrap();
+        assert_eq!("pinned", content);
+    }
+
+    #[test]
+    fn test_checkout_submodules_from_given_urls() {
+        let dir = tempfile::tempdir().unwrap();
+        let sub = dir.path().join("sub");
+        let sub_commit = repo(&sub);
+        let origin = dir.path().join("origin");
+        let repo = Repository::init(&origin).unwrap();
+        let mut index = repo.index().unwrap();
+        index
+            .add(&index_entry("vendor/sub", Oid::from_str(&sub_commit).unwrap(), 0o160000))
+            .unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("test", "test@localhost").unwrap();
+        let commit = repo.commit(Some("HEAD"), &signature, &signature, "sub", &tree, &[]).unwrap();
+
+        let checkout_dir = dir.path().join("checkout");
+        let url = format!("file://{}", origin.display());
+        checkout_with_submodules(
+            &url,
+            &commit.to_string(),
+            &checkout_dir,
+            &Credentials::default(),
+            &mut |path, commit| {
+                assert_eq!(("vendor/sub", sub_commit.as_str()), (path, commit));
+                Ok(format!("file://{}", sub.display()))
+            },
+        )
+        .unwrap();
+        let lib = std::fs::read_to_string(checkout_dir.join("vendor/sub/src/lib.rs")).unwrap();
+        assert_eq!("fn main() {}", lib);
+
+        let err = checkout_with_submodules(
+            &url,
+            &commit.to_string(),
+            &dir.path().join("no"),
+            &Credentials::default(),
+            &mut |_, _| anyhow::bail!("Unknown submodule"),
+        )
+        .unwrap_err();
+        assert_eq!("Unknown submodule", err.to_string());
+    }
+
+    #[test]
+    fn test_authenticated_http_remote() {
+        let dir = tempfile::tempdir().unwrap();
+        let commit = repo(&dir.path().join("origin"));
+        let url = format!("{}/origin", serve_git(dir.path()));
+        let netrc = dir.path().join("netrc");
+        std::fs::write(&netrc, "machine 127.0.0.1 login user password secret").unwrap();
+        let host = |credentials: HostCredentials| {
+            Credentials::new(vec![HostCredentials { host: "127.0.0.1".to_string(), ..credentials }])
+        };
+
+        let no_helpers = Credentials::default().with_env("GIT_CONFIG_GLOBAL", "/dev/null");
+        let err = clone_bare(&url, &commit, &dir.path().join("none"), &no_helpers).unwrap_err();
+        assert!(err.to_string().contains("No credentials configured for 127.0.0.1"), "{err}");
+
+        // hosts without credentials fall back to the git credential helpers
+        let config = dir.path().join("gitconfig");
+        let helper = "!f() { echo username=user; echo password=secret; }; f";
+        std::fs::write(&config, format!("[credential]\n\thelper = \"{helper}\"\n")).unwrap();
+        let credentials =
+            Credentials::default().with_env("GIT_CONFIG_GLOBAL", config.to_string_lossy());
+        clone_bare(&url, &commit, &dir.path().join("helper"), &credentials).unwrap();
+
+        let credentials = host(HostCredentials { netrc: Some(netrc), ..Default::default() });
+        let checkout_dir = dir.path().join("netrc-checkout");
+        checkout(&url, &commit, &checkout_dir, &credentials, &UrlRewrites::default()).unwrap();
+
+        let credentials = host(HostCredentials {
+            username: Some("user".to_string()),
+            token_env: Some("ANYTREE_TEST_GIT_TOKEN".to_string()),
+            ..Default::default()
+        })
+        .with_env("ANYTREE_TEST_GIT_TOKEN", "secret");
+        clone_bare(&url, &commit, &dir.path().join("token"), &credentials).unwrap();
+        assert_eq!(Some(commit.clone()), ls_remote(&url, "HEAD", &credentials).unwrap());
+        // nothing of the credentials is stored in the repo
+        let config = std::fs::read_to_string(dir.path().join("token/config")).unwrap();
+        assert!(!config.contains("secret") && !config.contains("user"), "{config}");
+
+        let credentials = host(HostCredentials {
+            username: Some("other".to_string()),
+            token_env: Some("ANYTREE_TEST_GIT_TOKEN".to_string()),
+            ..Default::default()
+        })
+        .with_env("ANYTREE_TEST_GIT_TOKEN", "secret");
+        let err = clone_bare(&url, &commit, &dir.path().join("wrong"), &credentials).unwrap_err();
+        assert!(err.to_string().contains("were rejected"), "{err}");
+    }
+
+    /// Remote helper serving `anytreetest://<path>` from the repo at the path
+    /// over the `connect` capability
+    const REMOTE_HELPER: &str = r#"#!/bin/sh
+while read -r line; do
+    case "$line" in
+        capabilities) printf 'connect\n\n' ;;
+        "connect "*) printf '\n'; exec git "${line#connect git-}" "${2#anytreetest://}" ;;
+        *) exit 1 ;;
+    esac
+done
+"#;
+
+    #[test]
+    fn test_remote_helper_and_rewritten_submodules() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let bin = dir.path().join("bin");
+        std::fs::create_dir(&bin).unwrap();
+        let helper = bin.join("git-remote-anytreetest");
+        std::fs::write(&helper, REMOTE_HELPER).unwrap();
+        std::fs::set_permissions(&helper, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let path = std::env::var("PATH").unwrap_or_default();
+        let credentials =
+            Credentials::default().with_env("PATH", format!("{}:{path}", bin.display()));
+
+        let sub_commit = repo(&dir.path().join("sub"));
+        let origin = Repository::init(dir.path().join("origin")).unwrap();
+        let gitmodules = "[submodule \"sub\"]\n\tpath = sub\n\turl = https://example.invalid/sub\n";
+        let mut index = origin.index().unwrap();
+        let mut entry =
+            index_entry(".gitmodules", origin.blob(gitmodules.as_bytes()).unwrap(), 0o100644);
+        entry.file_size = gitmodules.len() as u32;
+        index.add(&entry).unwrap();
+        index.add(&index_entry("sub", Oid::from_str(&sub_commit).unwrap(), 0o160000)).unwrap();
+        let tree = origin.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("test", "test@localhost").unwrap();
+        let commit =
+            origin.commit(Some("HEAD"), &signature, &signature, "sub", &tree, &[]).unwrap();
+        origin.tag_lightweight("v1", &origin.find_object(commit, None).unwrap(), false).unwrap();
+        let commit = commit.to_string();
+
+        let url = format!("anytreetest://{}", dir.path().join("origin").display());
+        let rewrites = UrlRewrites::new(vec![RewriteRule {
+            prefix: "https://example.invalid/".to_string(),
+            replacement: format!("anytreetest://{}/", dir.path().display()),
+        }]);
+        // the helper is only on the PATH of the git CLI run with the credentials
+        assert!(ls_remote(&url, "refs/tags/v1", &Credentials::default()).is_err());
+        assert_eq!(Some(commit.clone()), ls_remote(&url, "refs/tags/v1", &credentials).unwrap());
+        let bare = dir.path().join("bare");
+        clone_bare(&url, &commit, &bare, &credentials).unwrap();
+        let branch = origin.head().unwrap().shorthand().unwrap().to_string();
+        let history = dir.path().join("history");
+        assert!(branch_contains(&history, &url, &branch, &commit[..10], &credentials).unwrap());
+
+        let checkout_dir = dir.path().join("checkout");
+        checkout(&url, &commit, &checkout_dir, &credentials, &rewrites).unwrap();
+        let lib = std::fs::read_to_string(checkout_dir.join("sub/src/lib.rs")).unwrap();
+        assert_eq!("fn main() {}", lib);
+        let submodule = Repository::open(checkout_dir.join("sub")).unwrap();
+        assert_eq!(sub_commit, submodule.head().unwrap().target().unwrap().to_string());
+
+        // without the rule the submodule is fetched from its own URL
+        let err = checkout(
+            &url,
+            &commit,
+            &dir.path().join("no-rewrite"),
+            &credentials,
+            &UrlRewrites::default(),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("Failed to update submodule sub"), "{err}");
+    }
+
+    #[test]
+    fn test_remote_refs() {
+        let dir = tempfile::tempdir().unwrap();
+        let origin_path = dir.path().join("origin");
+        let commit = repo(&origin_path);
+        let url = format!("file://{}", origin_path.display());
+        let origin = Repository::open(&origin_path).unwrap();
+        let pinned = origin.find_commit(Oid::from_str(&commit).unwrap()).unwrap();
+        let signature = pinned.author();
+        origin.tag("v1", pinned.as_object(), &signature, "v1", false).unwrap();
+        origin.branch("main", &pinned, false).unwrap();
+        // rewritten branch has the same content but a new history
+        let tree = pinned.tree().unwrap();
+        let rewritten = origin.commit(None, &signature, &signature, "again", &tree, &[]).unwrap();
+        origin.branch("rewritten", &origin.find_commit(rewritten).unwrap(), false).unwrap();
+
+        // annotated tag is peeled to the commit
+        assert_eq!(
+            Some(commit.clone()),
+            ls_remote(&url, "refs/tags/v1", &Credentials::default()).unwrap()
+        );
+        assert_eq!(None, ls_remote(&url, "refs/tags/v2", &Credentials::default()).unwrap());
+        check_tag_name("v1.0/rc-1").unwrap();
+        for tag in ["../../config", "/etc/passwd", "v1..v2", "v1 ", "", "v1.lock"] {
+            assert!(check_tag_name(tag).is_err(), "{tag}");
+        }
+
+        // the pinned commit is deep in the history of main
+        let mut tip = pinned.clone();
+        for message in ["second", "third"] {
+            let id = origin
+                .commit(Some("refs/heads/main"), &signature, &signature, message, &tree, &[&tip])
+                .unwrap();
+            tip = origin.find_commit(id).unwrap();
+        }
+
+        let bare = dir.path().join("bare");
+        clone_bare(&url, &commit, &bare, &Credentials::default()).unwrap();
+        let history = dir.path().join("history");
+        let contains = |branch: &str, commit: &str| {
+            branch_contains(&history, &url, branch, commit, &Credentials::default())
+        };
+        // the advertised tip needs no fetch
+        assert!(contains("main", &tip.id().to_string()).unwrap());
+        assert!(!history.exists());
+        assert!(contains("main", &commit).unwrap());
+        assert!(contains("main", &commit[..10]).unwrap());
+        assert!(!contains("rewritten", &commit).unwrap());
+        assert!(!contains("rewritten", &tip.id().to_string()[..10]).unwrap());
+        assert!(contains("missing", &commit).is_err());
+        // the clone stays shallow
+        let bare = Repository::open(&bare).unwrap();
+        assert!(bare.find_commit(tip.id()).is_err());
+    }
+
+    #[test]
+    fn test_commit_and_tag_signatures() {
+        use crate::crypto::signature::TrustedKeys;
+
+        let dir = tempfile::tempdir().unwrap();
+        let key = dir.path().join("key");
+        let status = Command::new("ssh-keygen")
+            .args(["-q", "-t", "ed25519", "-N", "", "-f"])
+            .arg(&key)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let origin = dir.path().join("origin");
+        Repository::init(&origin).unwrap();
+        let git = |args: &[&str]| {
+            let output = Command::new("git")
+                .arg("-C")
+                .arg(&origin)
+                .args(["-c", "user.name=test", "-c", "user.email=test@localhost"])
+                .args(["-c", "gpg.format=ssh", "-c"])
+                .arg(format!("user.signingkey={}", key.display()))
+                .args(args)
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+            String::from_utf8(output.stdout).unwrap().trim().to_string()
+        };
+        git(&["commit", "--allow-empty", "-S", "-m", "signed"]);
+        git(&["tag", "-s", "v1", "-m", "v1"]);
+        git(&["tag", "light"]);
+        let signed = git(&["rev-parse", "HEAD"]);
+        git(&["commit", "--allow-empty", "-m", "unsigned"]);
+        git(&["tag", "-a", "v2", "-m", "v2"]);
+        let unsigned = git(&["rev-parse", "HEAD"]);
+        let mut keys = TrustedKeys::default();
+        keys.add(&std::fs::read_to_string(key.with_extension("pub")).unwrap()).unwrap();
+
+        let (signature, data) = commit_signature(&origin, &signed[..10]).unwrap().unwrap();
+        keys.verify(&signature, &data).unwrap();
+        assert!(keys.verify(&signature, b"other").is_err());
+        assert_eq!(None, commit_signature(&origin, &unsigned).unwrap());
+
+        let url = format!("file://{}", origin.display());
+        let bare = dir.path().join("bare");
+        let credentials = Credentials::default();
+        clone_bare(&url, &signed, &bare, &credentials).unwrap();
+        let (signature, data) =
+            tag_signature(&bare, &url, "v1", &signed[..10], &credentials).unwrap().unwrap();
+        keys.verify(&signature, &data).unwrap();
+        assert!(data.starts_with(format!("object {signed}").as_bytes()));
+        assert_eq!(None, tag_signature(&bare, &url, "light", &signed, &credentials).unwrap());
+        let err = tag_signature(&bare, &url, "v2", &signed, &credentials).unwrap_err();
+        assert!(err.to_string().contains("does not point to the pinned commit"), "{err}");
+        assert!(tag_signature(&bare, &url, "v3", &signed, &credentials).is_err());
+    }
+
+    #[test]
+    fn test_archive_with_attributes() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            let output = Command::new("git")
+                .arg("-C")
+                .arg(dir.path())
+                .args(["-c", "user.name=test", "-c", "user.email=test@localhost"])
+                .args(args)
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+            output.stdout
+        };
+        git(&["init", "-q"]);
+        let files = [
+            (
+                ".gitattributes",
+                "ignored.txt export-ignore\nversion.txt export-subst\n*.bat eol=crlf\n",
+            ),
+            ("ignored.txt", "ignored"),
+            ("version.txt", "$Format:%H$"),
+            ("run.bat", "echo 1\necho 2\n"),
+        ];
+        for (path, content) in files {
+            std::fs::write(dir.path().join(path), content).unwrap();
+        }
+        git(&["add", "."]);
+        git(&["commit", "-q", "-m", "attributes"]);
+        let commit = String::from_utf8(git(&["rev-parse", "HEAD"])).unwrap();
+        let commit = commit.trim();
+
+        assert_eq!(commit, resolve_commit(dir.path(), &commit[..7]).unwrap());
+        let data = archive(dir.path(), &commit[..7]).unwrap();
+        assert_eq!(git(&["archive", "--format=tar", commit]), data);
+        let contains = |needle: &[u8]| data.windows(needle.len()).any(|w| w == needle);
+        assert!(!contains(b"ignored.txt\0"));
+        assert!(!contains(b"$Format"));
+        assert!(contains(b"echo 1\r\necho 2\r\n"));
+    }
+
+    #[test]
+    fn test_nar_matches_checkout() {
+        let dir = tempfile::tempdir().unwrap();
+        let origin = dir.path().join("origin");
+        let commit = repo(&origin);
+        let checkout_dir = dir.path().join("checkout");
+        checkout(
+            &format!("file://{}", origin.display()),
+            &commit,
+            &checkout_dir,
+            &Credentials::default(),
+            &UrlRewrites::default(),
+        )
+        .unwrap();
+        let data = crate::nar::dump_checkout(&checkout_dir).unwrap();
+        // sha256 of the NAR `nix-store --dump` makes of the checkout
+        assert_eq!(
+            "c1d9da58651ef63f27303d18f5e9befd59eaa9c13c9b843243004fdcddbe28ab",
+            hex::encode(Sha256::digest(data))
+        );
+    }
+}
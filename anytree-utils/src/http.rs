@@ -0,0 +1,377 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::rewrite::UrlRewrites;
+
+const PARTIAL_EXTENSION: &str = "part";
+const USER_AGENT: &str = concat!("anytree/", env!("CARGO_PKG_VERSION"));
+
+#[derive(Debug, Clone)]
+pub struct HttpConfig {
+    /// Attempts per url, the first one included
+    pub attempts: u32,
+    /// Delay before the first retry, doubled for every next one
+    pub backoff: Duration,
+    /// Connect and read timeout
+    pub timeout: Duration,
+    /// Proxy url. `HTTPS_PROXY`, `ALL_PROXY` and `HTTP_PROXY` are used when
+    /// not set
+    pub proxy: Option<String>,
+    /// PEM bundle with CA certificates trusted in addition to the default ones
+    pub ca_bundle: Option<PathBuf>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            backoff: Duration::from_secs(1),
+            timeout: Duration::from_secs(60),
+            proxy: None,
+            ca_bundle: None,
+        }
+    }
+}
+
+impl HttpConfig {
+    /// Default config overridden by `ANYTREE_HTTP_ATTEMPTS`,
+    /// `ANYTREE_HTTP_TIMEOUT` (seconds), `ANYTREE_HTTP_PROXY` and
+    /// `ANYTREE_CA_BUNDLE` env variables
+    pub fn from_env() -> anyhow::Result<Self> {
+        let mut config = Self::default();
+        if let Ok(attempts) = std::env::var("ANYTREE_HTTP_ATTEMPTS") {
+            config.attempts = attempts
+                .parse()
+                .map_err(|e| anyhow::format_err!("Wrong ANYTREE_HTTP_ATTEMPTS: {e}"))?;
+        }
+        if let Ok(timeout) = std::env::var("ANYTREE_HTTP_TIMEOUT") {
+            config.timeout = Duration::from_secs(
+                timeout
+                    .parse()
+                    .map_err(|e| anyhow::format_err!("Wrong ANYTREE_HTTP_TIMEOUT: {e}"))?,
+            );
+        }
+        if let Ok(proxy) = std::env::var("ANYTREE_HTTP_PROXY") {
+            config.proxy = Some(proxy);
+        }
+        if let Ok(ca_bundle) = std::env::var("ANYTREE_CA_BUNDLE") {
+            config.ca_bundle = Some(PathBuf::from(ca_bundle));
+        }
+        Ok(config)
+    }
+}
+
+/// Result of a single attempt to fetch the url
+enum AttemptError {
+    /// Worth trying the same url again: network errors, 5xx, 429
+    Retry(anyhow::Error),
+    /// Url won't work, e.g. 404
+    Fail(anyhow::Error),
+}
+
+/// Blocking HTTP client with retries and fallback urls
+pub struct HttpClient {
+    agent: ureq::Agent,
+    config: HttpConfig,
+    rewrites: UrlRewrites,
+}
+
+impl HttpClient {
+    pub fn new(config: HttpConfig) -> anyhow::Result<Self> {
+        let mut builder = ureq::AgentBuilder::new()
+            .timeout_connect(config.timeout)
+            .timeout_read(config.timeout)
+            .user_agent(USER_AGENT)
+            .try_proxy_from_env(true);
+        if let Some(proxy) = &config.proxy {
+            builder = builder.proxy(
+                ureq::Proxy::new(proxy).map_err(|e| anyhow::format_err!("Wrong proxy: {e}"))?,
+            );
+        }
+        if let Some(ca_bundle) = &config.ca_bundle {
+            builder = builder.tls_config(Arc::new(tls_config(ca_bundle)?));
+        }
+        Ok(Self { agent: builder.build(), config, rewrites: UrlRewrites::default() })
+    }
+
+    /// Client fetching the urls passed through `rewrites`
+    pub fn with_rewrites(self, rewrites: UrlRewrites) -> Self {
+        Self { rewrites, ..self }
+    }
+
+    /// Body of the first url that responds successfully
+    pub fn get(&self, urls: &[&str]) -> anyhow::Result<Vec<u8>> {
+        self.try_urls(urls, |url| {
+            let response = self.request(url, None)?;
+            let mut body = vec![];
+            response.into_reader().read_to_end(&mut body).map_err(|e| {
+                AttemptError::Retry(anyhow::format_err!("Failed to read body: {e}"))
+            })?;
+            Ok(body)
+        })
+    }
+
+    /// Downloads the first url that responds successfully to `dest`.
+    ///
+    /// Data is written to `<dest>.part` first. If an attempt is interrupted
+    /// the next one to the same url continues from the end of the partial
+    /// file with a range request. The partial file is removed before falling
+    /// back to the next url, which may serve different bytes. `dest` appears
+    /// only when the download is complete.
+    pub fn download(&self, urls: &[&str], dest: &Path) -> anyhow::Result<()> {
+        let partial_path = partial_path(dest);
+        let mut last_url = None;
+        let res = self.try_urls(urls, |url| {
+            if last_url.replace(url.to_string()).map_or(false, |last| last != url) {
+                remove_partial(&partial_path).map_err(AttemptError::Fail)?;
+            }
+            self.download_attempt(url, &partial_path)
+        });
+        if let Err(e) = res {
+            // the next download starts with the first url again
+            remove_partial(&partial_path)?;
+            return Err(e);
+        }
+        std::fs::rename(&partial_path, dest)
+            .map_err(|e| anyhow::format_err!("Failed to move download to {dest:?}: {e}"))
+    }
+
+    fn download_attempt(&self, url: &str, partial_path: &Path) -> Result<(), AttemptError> {
+        let io_err = |e: std::io::Error| AttemptError::Fail(anyhow::format_err!("{e}"));
+        let offset = std::fs::metadata(partial_path).map(|metadata| metadata.len()).unwrap_or(0);
+        let response = match self.request(url, (offset > 0).then_some(offset)) {
+            Err(AttemptError::Fail(e)) if offset > 0 => {
+                // server may refuse the range (e.g. 416), so start from scratch
+                tracing::trace!("Failed to resume download of {url}: {e}");
+                std::fs::remove_file(partial_path).map_err(io_err)?;
+                return Err(AttemptError::Retry(e));
+            }
+            other => other?,
+        };
+
+        let mut file = if response.status() == 206 {
+            tracing::trace!("Resuming download of {url} from {offset}");
+            OpenOptions::new().append(true).open(partial_path).map_err(io_err)?
+        } else {
+            File::create(partial_path).map_err(io_err)?
+        };
+        let mut reader = response.into_reader();
+        let mut buf = vec![0; 64 * 1024];
+        loop {
+            let len = reader.read(&mut buf).map_err(|e| {
+                AttemptError::Retry(anyhow::format_err!("Download was interrupted: {e}"))
+            })?;
+            if len == 0 {
+                break;
+            }
+            file.write_all(&buf[..len]).map_err(io_err)?;
+        }
+        file.flush().map_err(io_err)
+    }
+
+    fn request(&self, url: &str, offset: Option<u64>) -> Result<ureq::Response, AttemptError> {
+        let mut request = self.agent.get(url);
+        if let Some(offset) = offset {
+            request = request.set("Range", &format!("bytes={offset}-"));
+        }
+        match request.call() {
+            Ok(response) if response.status() == 200 || response.status() == 206 => Ok(response),
+            Ok(response) => Err(AttemptError::Fail(anyhow::format_err!(
+                "Unexpected HTTP status {}",
+                response.status()
+            ))),
+            Err(ureq::Error::Status(code, _)) if code == 408 || code == 429 || code >= 500 => {
+                Err(AttemptError::Retry(anyhow::format_err!("HTTP status {code}")))
+            }
+            Err(ureq::Error::Status(code, _)) => {
+                Err(AttemptError::Fail(anyhow::format_err!("HTTP status {code}")))
+            }
+            Err(ureq::Error::Transport(e)) => Err(AttemptError::Retry(anyhow::format_err!("{e}"))),
+        }
+    }
+
+    fn try_urls<T>(
+        &self,
+        urls: &[&str],
+        mut attempt: impl FnMut(&str) -> Result<T, AttemptError>,
+    ) -> anyhow::Result<T> {
+        if urls.is_empty() {
+            anyhow::bail!("No urls to fetch");
+        }
+        let mut errors = vec![];
+        for url in urls {
+            let url = &*self.rewrites.rewrite(url);
+            let mut backoff = self.config.backoff;
+            for attempt_number in 1..=self.config.attempts.max(1) {
+                tracing::trace!("Fetching {url}, attempt {attempt_number}");
+                match attempt(url) {
+                    Ok(res) => return Ok(res),
+                    Err(AttemptError::Fail(e)) => {
+                        errors.push(format!("{url}: {e}"));
+                        break;
+                    }
+                    Err(AttemptError::Retry(e)) => {
+                        tracing::trace!("Failed to fetch {url}: {e}");
+                        if attempt_number == self.config.attempts.max(1) {
+                            errors.push(format!("{url}: {e}"));
+                        } else {
+                            std::thread::sleep(backoff);
+                            backoff *= 2;
+                        }
+                    }
+                }
+            }
+        }
+        anyhow::bail!("Failed to fetch:\n  {}", errors.join("\n  "))
+    }
+}
+
+fn partial_path(dest: &Path) -> PathBuf {
+    let mut file_name = dest.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".");
+    file_name.push(PARTIAL_EXTENSION);
+    dest.with_file_name(file_name)
+}
+
+fn remove_partial(partial_path: &Path) -> anyhow::Result<()> {
+    match std::fs::remove_file(partial_path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(anyhow::format_err!("Failed to remove {partial_path:?}: {e}"))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn tls_config(ca_bundle: &Path) -> anyhow::Result<rustls::ClientConfig> {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    let mut reader = BufReader::new(
+        File::open(ca_bundle)
+            .map_err(|e| anyhow::format_err!("Failed to open CA bundle {ca_bundle:?}: {e}"))?,
+    );
+    for cert in rustls_pemfile::certs(&mut reader) {
+        let cert = cert.map_err(|e| anyhow::format_err!("Failed to read CA bundle: {e}"))?;
+        roots.add(cert).map_err(|e| anyhow::format_err!("Wrong CA certificate: {e}"))?;
+    }
+    Ok(rustls::ClientConfig::builder().with_root_certificates(roots).with_no_client_auth())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+
+    use super::*;
+
+    type Handler = Box<dyn Fn(&str) -> Vec<u8> + Send>;
+
+    /// Serves one connection per handler, handler gets the request head
+    fn serve(handlers: Vec<Handler>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::Builder::new()
+            .name("test-http-server".to_string())
+            .spawn(move || {
+                for handler in handlers {
+                    let (mut stream, _) = listener.accept().unwrap();
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut head = String::new();
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        if line == "\r\n" || line.is_empty() {
+                            break;
+                        }
+                        head.push_str(&line);
+                    }
+                    stream.write_all(&handler(&head)).unwrap();
+                }
+            })
+            .unwrap();
+        format!("http://{addr}/file")
+    }
+
+    fn response(status: &str, headers: &str, body: &[u8]) -> Vec<u8> {
+        let mut res = format!(
+            "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n{headers}\r\n",
+            body.len()
+        )
+        .into_bytes();
+        res.extend_from_slice(body);
+        res
+    }
+
+    fn client() -> HttpClient {
+        HttpClient::new(HttpConfig { backoff: Duration::from_millis(1), ..Default::default() })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_retry_on_server_error() {
+        let url = serve(vec![
+            Box::new(|_| response("503 Service Unavailable", "", b"")),
+            Box::new(|_| response("200 OK", "", b"data")),
+        ]);
+        assert_eq!(b"data".to_vec(), client().get(&[&url]).unwrap());
+    }
+
+    #[test]
+    fn test_not_found_falls_back_to_next_url() {
+        let missing = serve(vec![Box::new(|_| response("404 Not Found", "", b"not found"))]);
+        let mirror = serve(vec![Box::new(|_| response("200 OK", "", b"data"))]);
+        assert_eq!(b"data".to_vec(), client().get(&[&missing, &mirror]).unwrap());
+
+        let missing = serve(vec![Box::new(|_| response("404 Not Found", "", b"not found"))]);
+        let err = client().get(&[&missing]).unwrap_err();
+        assert!(err.to_string().contains("404"));
+    }
+
+    #[test]
+    fn test_resume_partial_download() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("file.crate");
+        std::fs::write(partial_path(&dest), b"dat").unwrap();
+
+        let url = serve(vec![Box::new(|head| {
+            if head.to_lowercase().contains("range: bytes=3-") {
+                response("206 Partial Content", "Content-Range: bytes 3-5/6\r\n", b"a12")
+            } else {
+                response("200 OK", "", b"wrong!")
+            }
+        })]);
+        client().download(&[&url], &dest).unwrap();
+        assert_eq!(b"data12".to_vec(), std::fs::read(&dest).unwrap());
+        assert!(!partial_path(&dest).exists());
+    }
+
+    #[test]
+    fn test_partial_download_is_not_resumed_from_next_url() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("file.crate");
+        let client = HttpClient::new(HttpConfig { attempts: 1, ..Default::default() }).unwrap();
+        let interrupted = || -> Handler {
+            Box::new(|_| {
+                let mut res = response("200 OK", "", b"data12");
+                res.truncate(res.len() - 3);
+                res
+            })
+        };
+
+        let mirror = serve(vec![Box::new(|head| {
+            if head.to_lowercase().contains("range:") {
+                response("206 Partial Content", "Content-Range: bytes 3-5/6\r\n", b"a12")
+            } else {
+                response("200 OK", "", b"other!")
+            }
+        })]);
+        client.download(&[&serve(vec![interrupted()]), &mirror], &dest).unwrap();
+        assert_eq!(b"other!".to_vec(), std::fs::read(&dest).unwrap());
+
+        let err = client.download(&[&serve(vec![interrupted()])], &dest).unwrap_err();
+        assert!(err.to_string().contains("interrupted"), "{err}");
+        assert!(!partial_path(&dest).exists());
+    }
+}
@@ -1,2 +1,8 @@
+pub mod archive;
+pub mod credentials;
 pub mod crypto;
+pub mod git;
+pub mod http;
+pub mod nar;
+pub mod rewrite;
 pub mod tracing;
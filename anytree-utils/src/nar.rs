@@ -0,0 +1,222 @@
+//! Nix archive (NAR) serialization of source trees, the input of tree hashes.
+//!
+//! Unlike a tar archive a NAR has nothing but the tree itself: entries sorted
+//! by name bytes, the executable bit of files, file contents and symlink
+//! targets. No owners, times or umask, so the same tree gives the same bytes
+//! whatever made it: a checkout or an extracted archive. The
+//! format is the one of `nix-store --dump`, so a tree hash can be checked
+//! with `nix-hash --type sha256 --base16` of a checkout without `.git`.
+
+use std::path::Path;
+
+const MAGIC: &str = "nix-archive-1";
+/// Repo of a checkout, a dir or a file pointing to it in the submodules
+const GIT_DIR: &str = ".git";
+
+/// Writes a NAR node by node. Entries of a dir must come sorted by name bytes.
+pub struct NarWriter {
+    out: Vec<u8>,
+}
+
+impl Default for NarWriter {
+    fn default() -> Self {
+        let mut nar = Self { out: Vec::new() };
+        nar.string(MAGIC.as_bytes());
+        nar
+    }
+}
+
+impl NarWriter {
+    pub fn file(&mut self, executable: bool, contents: &[u8]) {
+        self.strings(&["(", "type", "regular"]);
+        if executable {
+            self.strings(&["executable", ""]);
+        }
+        self.strings(&["contents"]);
+        self.string(contents);
+        self.strings(&[")"]);
+    }
+
+    pub fn symlink(&mut self, target: &[u8]) {
+        self.strings(&["(", "type", "symlink", "target"]);
+        self.string(target);
+        self.strings(&[")"]);
+    }
+
+    /// Starts a dir, its entries follow as [`Self::entry`] with the node and
+    /// [`Self::end`] each, then [`Self::end`] of the dir
+    pub fn dir(&mut self) {
+        self.strings(&["(", "type", "directory"]);
+    }
+
+    /// Starts an entry of the current dir, the node follows
+    pub fn entry(&mut self, name: &[u8]) {
+        self.strings(&["entry", "(", "name"]);
+        self.string(name);
+        self.strings(&["node"]);
+    }
+
+    pub fn end(&mut self) {
+        self.strings(&[")"]);
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.out
+    }
+
+    fn strings(&mut self, strings: &[&str]) {
+        for string in strings {
+            self.string(string.as_bytes());
+        }
+    }
+
+    /// Length as u64 LE, then the bytes padded with zeros to 8 bytes
+    fn string(&mut self, data: &[u8]) {
+        self.out.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        self.out.extend_from_slice(data);
+        self.out.resize(self.out.len() + (8 - data.len() % 8) % 8, 0);
+    }
+}
+
+/// NAR of the file, symlink or dir at `path`, e.g. an extracted archive
+pub fn dump_path(path: &Path) -> anyhow::Result<Vec<u8>> {
+    let mut nar = NarWriter::default();
+    dump_node(&mut nar, path, None)?;
+    Ok(nar.finish())
+}
+
+/// NAR of the git checkout at `path` without `.git`, the nested checkouts of
+/// the submodules are empty dirs. The files are hashed as they were checked
+/// out, with the eol and filter attributes applied.
+pub fn dump_checkout(path: &Path) -> anyhow::Result<Vec<u8>> {
+    let mut nar = NarWriter::default();
+    dump_node(&mut nar, path, Some(path))?;
+    Ok(nar.finish())
+}
+
+/// `checkout` is the root of the checkout `path` is in, if any
+fn dump_node(nar: &mut NarWriter, path: &Path, checkout: Option<&Path>) -> anyhow::Result<()> {
+    let metadata = std::fs::symlink_metadata(path)
+        .map_err(|e| anyhow::format_err!("Failed to read {:?}: {e}", path))?;
+    if metadata.is_symlink() {
+        nar.symlink(path_bytes(&std::fs::read_link(path)?));
+    } else if metadata.is_dir() {
+        let mut entries = std::fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.file_name()))
+            .collect::<Result<Vec<_>, _>>()?;
+        if checkout.is_some() {
+            let is_submodule = checkout != Some(path) && entries.iter().any(|name| name == GIT_DIR);
+            if is_submodule {
+                entries.clear();
+            }
+            entries.retain(|name| name != GIT_DIR);
+        }
+        entries.sort_by(|a, b| path_bytes(a.as_ref()).cmp(path_bytes(b.as_ref())));
+        nar.dir();
+        for name in entries {
+            nar.entry(path_bytes(name.as_ref()));
+            dump_node(nar, &path.join(name), checkout)?;
+            nar.end();
+        }
+        nar.end();
+    } else if metadata.is_file() {
+        nar.file(is_executable(&metadata), &std::fs::read(path)?);
+    } else {
+        anyhow::bail!("Unsupported file type of {:?}", path);
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn path_bytes(path: &Path) -> &[u8] {
+    use std::os::unix::ffi::OsStrExt;
+
+    path.as_os_str().as_bytes()
+}
+
+#[cfg(not(unix))]
+fn path_bytes(path: &Path) -> &[u8] {
+    path.to_str().unwrap_or_default().as_bytes()
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    metadata.permissions().mode() & 0o100 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &std::fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// NAR strings spelled out: length as u64 LE, the bytes, zero padding
+    fn strings(parts: &[&str]) -> Vec<u8> {
+        let mut out = vec![];
+        for part in [MAGIC].iter().chain(parts).map(|part| part.as_bytes()) {
+            out.extend_from_slice(&(part.len() as u64).to_le_bytes());
+            out.extend_from_slice(part);
+            out.resize(out.len() + (8 - part.len() % 8) % 8, 0);
+        }
+        out
+    }
+
+    #[test]
+    fn test_dump_nodes() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file");
+        std::fs::write(&file, "hello").unwrap();
+        assert_eq!(
+            strings(&["(", "type", "regular", "contents", "hello", ")"]),
+            dump_path(&file).unwrap()
+        );
+        // 13 bytes of the magic padded to 16
+        assert_eq!(b"\x0d\0\0\0\0\0\0\0nix-archive-1\0\0\0", &dump_path(&file).unwrap()[..24]);
+
+        std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(
+            strings(&["(", "type", "regular", "executable", "", "contents", "hello", ")"]),
+            dump_path(&file).unwrap()
+        );
+
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink("../target", &link).unwrap();
+        assert_eq!(
+            strings(&["(", "type", "symlink", "target", "../target", ")"]),
+            dump_path(&link).unwrap()
+        );
+
+        // entries sorted by name bytes, `a` < `a.txt` < `b`
+        let tree = dir.path().join("tree");
+        std::fs::create_dir_all(tree.join("a")).unwrap();
+        std::fs::write(tree.join("a.txt"), "").unwrap();
+        std::fs::write(tree.join("b"), "b").unwrap();
+        let expected = strings(
+            &[
+                &["(", "type", "directory"][..],
+                &["entry", "(", "name", "a", "node", "(", "type", "directory", ")", ")"],
+                &["entry", "(", "name", "a.txt", "node"],
+                &["(", "type", "regular", "contents", "", ")", ")"],
+                &["entry", "(", "name", "b", "node"],
+                &["(", "type", "regular", "contents", "b", ")", ")"],
+                &[")"],
+            ]
+            .concat(),
+        );
+        assert_eq!(expected, dump_path(&tree).unwrap());
+
+        // the repo of a checkout is left out, a submodule is an empty dir
+        std::fs::create_dir_all(tree.join(".git/objects")).unwrap();
+        std::fs::write(tree.join("a/.git"), "gitdir: ../.git/modules/a").unwrap();
+        std::fs::write(tree.join("a/lib.c"), "").unwrap();
+        assert_eq!(expected, dump_checkout(&tree).unwrap());
+        assert_ne!(expected, dump_path(&tree).unwrap());
+    }
+}
@@ -0,0 +1,70 @@
+use std::borrow::Cow;
+
+use serde::Deserialize;
+
+/// Rule replacing the start of URLs, like git's `url.<base>.insteadOf`, e.g.
+///
+/// ```toml
+/// [[rewrite]]
+/// prefix = "https://github.com/"
+/// replacement = "gosh://0:b00a7a5a.../mirror/"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RewriteRule {
+    pub prefix: String,
+    pub replacement: String,
+}
+
+/// Rewrite rules applied to the URLs of the sources right before fetching
+/// them. SBOM keeps the original URLs, so the rules change where the sources
+/// come from, not what they are.
+#[derive(Debug, Clone, Default)]
+pub struct UrlRewrites {
+    rules: Vec<RewriteRule>,
+}
+
+impl UrlRewrites {
+    pub fn new(rules: Vec<RewriteRule>) -> Self {
+        Self { rules }
+    }
+
+    /// `url` with the longest matching prefix replaced, as git picks between
+    /// `insteadOf` rules. Rewritten URLs are not rewritten again.
+    pub fn rewrite<'a>(&self, url: &'a str) -> Cow<'a, str> {
+        let rule = self
+            .rules
+            .iter()
+            .filter(|rule| url.starts_with(&rule.prefix))
+            .max_by_key(|rule| rule.prefix.len());
+        match rule {
+            Some(rule) => {
+                let rewritten = format!("{}{}", rule.replacement, &url[rule.prefix.len()..]);
+                tracing::trace!("Rewrote {} to {}", url, rewritten);
+                Cow::Owned(rewritten)
+            }
+            None => Cow::Borrowed(url),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_longest_prefix_wins() {
+        let rule = |prefix: &str, replacement: &str| RewriteRule {
+            prefix: prefix.to_string(),
+            replacement: replacement.to_string(),
+        };
+        let rewrites = UrlRewrites::new(vec![
+            rule("https://github.com/", "gosh://0:1/mirror/"),
+            rule("https://github.com/org/", "file:///srv/org/"),
+            rule("gosh://", "https://github.com/"),
+        ]);
+        assert_eq!("gosh://0:1/mirror/a/b", rewrites.rewrite("https://github.com/a/b"));
+        assert_eq!("file:///srv/org/b", rewrites.rewrite("https://github.com/org/b"));
+        assert_eq!("https://gitlab.com/a", rewrites.rewrite("https://gitlab.com/a"));
+    }
+}
@@ -362,6 +362,38 @@ for package in cargo_lock.get('patch', {}).get('unused', []):
         if os.path.isfile(tmp_file):
             os.remove(tmp_file)
 
+# Process git submodules of the project, nested ones included. anytree checks
+# them out from these components at the commits recorded in the project
+def get_submodules(repo_dir):
+    output = subprocess.check_output(
+        ['git', 'submodule', 'foreach', '--quiet', '--recursive', 'echo "$displaypath $sha1 $(git remote get-url origin)"'],
+        cwd=repo_dir
+    ).decode('utf-8')
+    return [line.split(' ', 2) for line in output.splitlines() if line]
+
+repo_root = subprocess.check_output(['git', 'rev-parse', '--show-toplevel'], cwd=os.path.dirname(CARGO_LOCK_PATH)).decode('utf-8').strip()
+for path, commit, url in get_submodules(repo_root):
+    tmp_file = os.path.abspath('tmp_file')
+    try:
+        subprocess.run(['git', 'archive', '--format=tar', '-o', tmp_file, commit], cwd=os.path.join(repo_root, path), check=True)
+        hashes = get_hashes(tmp_file)
+    finally:
+        if os.path.isfile(tmp_file):
+            os.remove(tmp_file)
+    bom["components"].append({
+        "bom-ref": f"{os.path.basename(path)}_{commit[:7]}_{uuid.uuid4()}",
+        "type": "library",
+        "name": os.path.basename(path),
+        "version": commit[:7],
+        "mime-type": "git/submodule",
+        "externalReferences": [{"url": url, "type": "vcs"}],
+        "hashes": [{"alg": alg, "content": content} for alg, content in hashes.items()],
+        "properties": [
+            {"name": "path", "value": path},
+            {"name": "commit", "value": commit},
+        ],
+    })
+
 # Remove the existing component, if any, with the same name and version
 components = bom.get("components", [])
 bom["components"] = [component for component in components if component.get("name") != project_name and component.get("version") != project_version]